@@ -0,0 +1,211 @@
+use anyhow::{bail, Context, Result};
+use chrono::{Local, NaiveDate, TimeZone};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::SystemTime;
+
+/// Distinguishes the temporary folders of archives extracted in one run
+static EXTRACT_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Detect an archive by its file extension
+    fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveKind::Zip)
+        } else if name.ends_with(".tar") {
+            Some(ArchiveKind::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveKind::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+/// Check if an input path is a `.zip`, `.tar`, or `.tar.gz` archive to import
+pub fn is_archive(path: &Path) -> bool {
+    ArchiveKind::from_path(path).is_some()
+}
+
+/// An archive unpacked into a temporary folder, which is removed on drop
+/// unless `keep` was called
+#[derive(Debug)]
+pub struct ExtractedArchive {
+    dir: PathBuf,
+    keep: bool,
+}
+
+impl ExtractedArchive {
+    /// A new, empty temporary folder to unpack (or stage) files into
+    pub(crate) fn create() -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!(
+            "collect_media_{}_{}",
+            std::process::id(),
+            EXTRACT_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create temporary folder: {}", dir.display()))?;
+        Ok(ExtractedArchive { dir, keep: false })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Leave the temporary folder in place, e.g. so Failed Cases links into
+    /// it stay valid
+    pub fn keep(&mut self) {
+        self.keep = true;
+    }
+}
+
+impl Drop for ExtractedArchive {
+    fn drop(&mut self) {
+        if !self.keep {
+            if let Err(e) = fs::remove_dir_all(&self.dir) {
+                eprintln!("Warning: Failed to remove {}: {}", self.dir.display(), e);
+            }
+        }
+    }
+}
+
+/// Unpack an archive into a new temporary folder, leaving out entries for
+/// which `skip` returns true. Entries that would land outside the folder
+/// (absolute paths, `..`) are skipped
+pub fn extract_archive(archive: &Path, skip: impl Fn(&Path) -> bool) -> Result<ExtractedArchive> {
+    let Some(kind) = ArchiveKind::from_path(archive) else {
+        bail!("Not a supported archive: {}", archive.display());
+    };
+
+    let extracted = ExtractedArchive::create()?;
+
+    let file = File::open(archive).with_context(|| format!("Failed to open archive: {}", archive.display()))?;
+    let result = match kind {
+        ArchiveKind::Zip => extract_zip(file, extracted.dir(), &skip),
+        ArchiveKind::Tar => extract_tar(file, extracted.dir(), &skip),
+        ArchiveKind::TarGz => extract_tar(flate2::read::GzDecoder::new(file), extracted.dir(), &skip),
+    };
+    result.with_context(|| format!("Failed to extract archive: {}", archive.display()))?;
+
+    Ok(extracted)
+}
+
+fn extract_zip(file: File, dir: &Path, skip: &dyn Fn(&Path) -> bool) -> Result<()> {
+    let mut zip = zip::ZipArchive::new(file)?;
+
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        if entry.is_dir() {
+            continue;
+        }
+        let Some(relative) = entry.enclosed_name() else {
+            eprintln!("Warning: Skipping unsafe archive entry: {}", entry.name());
+            continue;
+        };
+        if skip(&relative) {
+            continue;
+        }
+
+        let target = dir.join(&relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Keep the entry's date, which is what a file without EXIF is
+        // archived by (tar's unpack_in does the same)
+        let modified = entry.last_modified().and_then(zip_modified);
+        let mut file = File::create(&target)?;
+        io::copy(&mut entry, &mut file)?;
+        if let Some(modified) = modified {
+            file.set_modified(modified)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// A zip entry's modification time, which zip tools record in local time
+fn zip_modified(time: zip::DateTime) -> Option<SystemTime> {
+    let date = NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?;
+    let local = date.and_hms_opt(time.hour().into(), time.minute().into(), time.second().into())?;
+    Local.from_local_datetime(&local).earliest().map(SystemTime::from)
+}
+
+fn extract_tar(reader: impl io::Read, dir: &Path, skip: &dyn Fn(&Path) -> bool) -> Result<()> {
+    let mut tar = tar::Archive::new(reader);
+
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let relative = entry.path()?.into_owned();
+        if skip(&relative) {
+            continue;
+        }
+
+        // unpack_in refuses entries that would escape `dir`
+        if !entry.unpack_in(dir)? {
+            eprintln!("Warning: Skipping unsafe archive entry: {}", relative.display());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::io::Write;
+
+    #[test]
+    fn test_is_archive() {
+        assert!(is_archive(Path::new("backup.zip")));
+        assert!(is_archive(Path::new("backup.TAR")));
+        assert!(is_archive(Path::new("backup.tar.gz")));
+        assert!(is_archive(Path::new("backup.tgz")));
+        assert!(!is_archive(Path::new("IMG_0001.JPG")));
+        assert!(!is_archive(Path::new("DCIM")));
+    }
+
+    #[test]
+    fn test_extract_zip_skips_filtered_entries() {
+        let root = TempDir::new("zip");
+        let archive = root.join("test.zip");
+        {
+            let mut zip = zip::ZipWriter::new(File::create(&archive).unwrap());
+            let modified = zip::DateTime::from_date_and_time(2015, 6, 1, 12, 30, 0).unwrap();
+            let options = zip::write::SimpleFileOptions::default().last_modified_time(modified);
+            zip.start_file("DCIM/IMG_0001.JPG", options).unwrap();
+            zip.write_all(b"photo").unwrap();
+            zip.start_file("DCIM/._IMG_0001.JPG", options).unwrap();
+            zip.write_all(b"resource fork").unwrap();
+            zip.finish().unwrap();
+        }
+
+        let extracted = extract_archive(&archive, |path| {
+            path.file_name().and_then(|n| n.to_str()).is_some_and(|n| n.starts_with("._"))
+        })
+        .unwrap();
+        let dir = extracted.dir().to_path_buf();
+
+        assert_eq!(fs::read(dir.join("DCIM/IMG_0001.JPG")).unwrap(), b"photo");
+        assert!(!dir.join("DCIM/._IMG_0001.JPG").exists());
+        let expected = Local.with_ymd_and_hms(2015, 6, 1, 12, 30, 0).unwrap();
+        let modified = fs::metadata(dir.join("DCIM/IMG_0001.JPG")).unwrap().modified().unwrap();
+        assert_eq!(modified, SystemTime::from(expected));
+
+        drop(extracted);
+        assert!(!dir.exists());
+    }
+}
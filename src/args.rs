@@ -1,73 +1,925 @@
 use anyhow::{anyhow, bail, Result};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+
+use crate::archive::is_archive;
+use crate::mtp::is_mtp_input;
+use crate::config::load_config;
+use crate::copy::MAX_COPY_BUFFER_SIZE;
+use crate::filename::{normalize_extension, sanitize_affix, CounterStyle, ExtCase, MillisMode, OutputTimezone};
+use crate::hash::Hasher;
+use crate::ignore::IgnoreList;
+use crate::metadata::{validate_exiftool_args, ArrayDate, DateOrder, ExtractOptions, PrimaryDate};
+use crate::organize::DateLayout;
+use crate::pairs::PreviewPolicy;
+use crate::phash::MAX_DISTANCE;
+use crate::settings::{
+    validate_folder_name, ConflictPolicy, DedupeBy, DedupeScope, FailurePolicy, GpsFilter, LinkMode, Settings, SummaryFormat,
+};
+use crate::style::ColorChoice;
+
+const USAGE: &str = "Usage: collect_media <dirs...> -o <output_dir> [options]
+       collect_media --inspect <file> [--array-date <POLICY>]
+                     [--date-order <MODE>] [--exiftool-arg <ARG>]...
+                     [--future-tolerance <DURATION>] [--single-date]
+
+Example:
+  collect_media /Volumes/Thumb/One /Volumes/Thumb/Two -o /Users/me/Pictures/Library
+
+Options taking a value also accept --option=value; -r and -o can be bundled
+(-ro <output_dir>) and -o written as -o=<output_dir>. Arguments after -- are
+input directories, even if they start with a dash. --no-<flag> turns off a
+flag given earlier or by a config file (--no-recursive).
+
+Exit codes: 0 when every file was handled, 2 when some files failed, 3 when
+some paths couldn't be scanned, 1 on a fatal error.
+
+Inputs ending in .zip, .tar, .tar.gz or .tgz are extracted to a temporary
+folder and archived from there. The input mtp: copies from the first connected
+MTP/PTP device instead (needs a build with the mtp feature).";
+
+/// A long option as --help lists it: its name without `--`, any short form,
+/// the placeholder for its value (none for a flag), and its help text, whose
+/// continuation lines are separated by `\n`
+struct OptionSpec {
+    name: &'static str,
+    short: Option<char>,
+    value: Option<&'static str>,
+    help: &'static str,
+}
+
+const fn flag(name: &'static str, help: &'static str) -> OptionSpec {
+    OptionSpec { name, short: None, value: None, help }
+}
+
+const fn value(name: &'static str, placeholder: &'static str, help: &'static str) -> OptionSpec {
+    OptionSpec { name, short: None, value: Some(placeholder), help }
+}
+
+/// Every option, in --help order. The usage text and the keys a config file
+/// accepts are both built from this, so the two can't drift apart
+const OPTIONS: &[OptionSpec] = &[
+    flag(
+        "analyze-overlap",
+        "Only print how many files each pair of inputs\n\
+         have in common, without archiving anything",
+    ),
+    value(
+        "array-date",
+        "<POLICY>",
+        "Which date to use from a multi-value tag:\n\
+         mode-earliest (default), mode-latest, earliest,\n\
+         or latest",
+    ),
+    flag(
+        "auto-orient",
+        "Rotate archived images upright (needs jpegtran\n\
+         for JPEGs, ImageMagick for other formats)",
+    ),
+    value(
+        "checksum-algo",
+        "<ALGO>",
+        "Hash for duplicate checks and --manifest: blake3,\n\
+         sha256, or md5 (default: sha256)",
+    ),
+    value(
+        "color",
+        "<auto|always|never>",
+        "Color the output (default: auto, when writing to\n\
+         a terminal); --no-color is short for never",
+    ),
+    value(
+        "config",
+        "<PATH>",
+        "Read options from a JSON file (see README);\n\
+         options given here override its values",
+    ),
+    flag(
+        "confirm-deletes",
+        "Keep copied source files until the run ends,\n\
+         then ask once before deleting them all",
+    ),
+    value(
+        "copy-buffer-size",
+        "<BYTES>",
+        "Copy in chunks of BYTES (suffixes K, M, G; at\n\
+         most 256M) instead of the platform's fast copy",
+    ),
+    value(
+        "counter-style",
+        "<STYLE>",
+        "always (default) numbers every name from 1;\n\
+         on-collision leaves the first name bare",
+    ),
+    value(
+        "date-order",
+        "<warn|swap|fail>",
+        "What to do when a creation date is after the\n\
+         modification date (default: warn)",
+    ),
+    value(
+        "dedupe-by",
+        "<bytes|identity>",
+        "With identity, also report files sharing\n\
+         DateTimeOriginal, camera serial and image size\n\
+         as the same shot (both are kept for review)",
+    ),
+    value(
+        "dedupe-report-only",
+        "<REPORT>",
+        "Find duplicates and write them to REPORT without\n\
+         moving, copying, or deleting anything",
+    ),
+    value(
+        "dedupe-scope",
+        "<SCOPE>",
+        "Skip sources identical to a library file with the\n\
+         same date-based name (date-bucket, the default)\n\
+         or anywhere in the library (global)",
+    ),
+    flag(
+        "dry-run",
+        "List each source as NEW, DUPLICATE-OF or\n\
+         WOULD-COLLIDE against the library, writing nothing",
+    ),
+    flag(
+        "emit-sidecar",
+        "Write each archived file's dates, their source\n\
+         tags and its source path to <file>.json beside it",
+    ),
+    value(
+        "exec",
+        "<CMD>",
+        "Run CMD on each archived file, with {} replaced\n\
+         by its path (appended if CMD has no {})",
+    ),
+    value(
+        "exif-timeout",
+        "<SECS>",
+        "Fail a file exiftool takes longer than SECS\n\
+         seconds to read, restarting exiftool",
+    ),
+    value(
+        "exiftool-arg",
+        "<ARG>",
+        "Pass ARG to exiftool when reading metadata\n\
+         (repeatable; e.g. --exiftool-arg -api\n\
+         --exiftool-arg LargeFileSupport=1)",
+    ),
+    value(
+        "ext-case",
+        "<CASE>",
+        "Extension case in generated names: upper\n\
+         (default), lower, or preserve",
+    ),
+    value(
+        "failed-name",
+        "<NAME>",
+        "Name of the folder failed files are linked into\n\
+         (default: Failed Cases)",
+    ),
+    value(
+        "field-sep",
+        "<TEXT>",
+        "Put TEXT between the dates and before the counter\n\
+         in generated names (default: a space)",
+    ),
+    flag(
+        "fix-extension",
+        "Name files with the extension of the type\n\
+         exiftool detects when the source's disagrees",
+    ),
+    flag(
+        "fix-perms",
+        "On permission denied, add owner read/write to the\n\
+         source file and its folder, then retry once",
+    ),
+    value(
+        "from-file",
+        "<PATH>",
+        "Archive the files listed in PATH (one per line,\n\
+         '-' for stdin) instead of scanning directories",
+    ),
+    value(
+        "future-tolerance",
+        "<DURATION>",
+        "Accept dates up to DURATION in the future\n\
+         (e.g. 48h; units s, m, h, d)",
+    ),
+    value(
+        "group-window",
+        "<MS>",
+        "Floor the dates in generated names to MS\n\
+         milliseconds, so files dated within one window\n\
+         share a base name and counter sequence",
+    ),
+    value(
+        "ignore-name",
+        "<PATTERN>",
+        "Also skip files whose name matches PATTERN (a\n\
+         glob, any case; repeatable). ._*, .DS_Store,\n\
+         Thumbs.db, ehthumbs.db and desktop.ini are\n\
+         always skipped; add '*.AAE' for Apple edits",
+    ),
+    flag(
+        "include-hidden",
+        "Scan dotfiles and dot-directories (skipped by\n\
+         default)",
+    ),
+    flag("interactive-dupes", "Decide keep/delete for each duplicate source file"),
+    flag(
+        "keep-earliest-date",
+        "With --dedupe-scope global, rename a library file\n\
+         to the earlier date of an identical source",
+    ),
+    flag(
+        "keep-failed-originals-in-place",
+        "Leave failed files where they are, linked from\n\
+         Failed Cases (the default)",
+    ),
+    flag("keep-linked-sources", "With --link hardlink, keep the source names too"),
+    value("limit", "<N>", "Process only the first N files (sorted by path)"),
+    value(
+        "link",
+        "<auto|hardlink|copy>",
+        "How files enter the library: rename on the same\n\
+         volume else copy (auto, the default), hard link\n\
+         falling back to copy, or always copy",
+    ),
+    flag(
+        "list-unsupported",
+        "After the summary, count failed files by\n\
+         extension (types exiftool can't date)",
+    ),
+    value(
+        "location-grid",
+        "<DEGREES>",
+        "Grid cell size for --organize-by-location\n\
+         (default: 1)",
+    ),
+    value("manifest", "<PATH>", "Write a SHA256SUMS-style manifest of archived files"),
+    value(
+        "max-collisions",
+        "<N>",
+        "Give up on a file once N files share its dates\n\
+         in one folder (default: 10000)",
+    ),
+    value(
+        "max-depth",
+        "<N>",
+        "Scan N directory levels into each input\n\
+         (default: 1)",
+    ),
+    value(
+        "max-rate",
+        "<BYTES>",
+        "Limit copy throughput to BYTES per second\n\
+         (suffixes K, M, G; e.g. 5M)",
+    ),
+    value(
+        "max-read-size",
+        "<BYTES>",
+        "Skip source files larger than BYTES with a\n\
+         warning (suffixes K, M, G; default: 64G)",
+    ),
+    value(
+        "media-folder",
+        "<KEY>=<NAME>",
+        "Override a --split-media folder name; KEY is\n\
+         image, video, other, or an exiftool FileType",
+    ),
+    flag(
+        "move-failed",
+        "Move failed files into Failed Cases instead of\n\
+         linking to them, so the sources end up empty",
+    ),
+    value(
+        "ms",
+        "<always|nonzero|never>",
+        "When dates in generated names end in .NNN\n\
+         milliseconds (default: always)",
+    ),
+    flag(
+        "no-prompt",
+        "Never ask before deleting: duplicate and copied\n\
+         sources are left in place",
+    ),
+    value(
+        "on-conflict",
+        "<POLICY>",
+        "When a different file has the same name: increment,\n\
+         keep-larger, or keep-newer (default: increment;\n\
+         keep-newer requires --preserve-mtime)",
+    ),
+    value(
+        "on-failure",
+        "<POLICY>",
+        "After a failed file: symlink it into Failed Cases\n\
+         and continue, or stop the run (default: symlink)",
+    ),
+    flag("only-with-gps", "Only archive files with GPS coordinates"),
+    flag("only-without-gps", "Only archive files without GPS coordinates"),
+    value(
+        "organize-by",
+        "<LAYOUT>",
+        "Date folders: flat, year, month, or day\n\
+         (default: flat)",
+    ),
+    flag(
+        "organize-by-location",
+        "Sort files with GPS data into lat/long grid-cell\n\
+         folders; others use the --organize-by layout",
+    ),
+    value(
+        "output-timezone",
+        "<ZONE>",
+        "Show dates in names and date folders in ZONE:\n\
+         utc (default), local, or an offset like +05:30",
+    ),
+    value(
+        "pair-previews",
+        "<skip|sidecar>",
+        "Treat same-name files of different types (e.g.\n\
+         a ProRAW DNG and its JPG) as one shot; leave the\n\
+         previews in place, or archive them beside it",
+    ),
+    value(
+        "pair-priority",
+        "<EXT,...>",
+        "Type priority for --pair-previews\n\
+         (default: DNG,HEIC,JPG)",
+    ),
+    value(
+        "phash-dedupe",
+        "<DISTANCE>",
+        "List images whose perceptual hash is within\n\
+         DISTANCE bits (0-64, e.g. 6) of a library image\n\
+         as probable near-duplicates (needs ImageMagick)",
+    ),
+    value("prefix", "<TEXT>", "Prepend TEXT to every generated filename"),
+    flag(
+        "preserve-mtime",
+        "Give copied files their source's access and\n\
+         modification times",
+    ),
+    flag(
+        "preserve-structure",
+        "Recreate each file's subdirectory under the output\n\
+         (use with --recursive)",
+    ),
+    value(
+        "primary-date",
+        "<DATE>",
+        "Date that leads filenames and picks date folders:\n\
+         creation or modify (default: creation)",
+    ),
+    value(
+        "progress-fd",
+        "<N>",
+        "Write a JSON line with done, total and path to\n\
+         file descriptor N as each file is handled",
+    ),
+    flag(
+        "prune-empty-sources",
+        "After the run, remove folders in the inputs that\n\
+         are empty (or hold only .DS_Store/._* files)",
+    ),
+    flag("quarantine-empty", "Move zero-byte files into an Empty Files folder"),
+    OptionSpec { short: Some('r'), ..flag("recursive", "Scan input directories recursively") },
+    flag(
+        "resume",
+        "Skip files the output's transfer log records as\n\
+         archived by an earlier (interrupted) run",
+    ),
+    flag(
+        "review-suspicious",
+        "Archive files dated before the suspicious-date\n\
+         threshold (2010 by default) into a Review\n\
+         folder instead of the main library",
+    ),
+    flag(
+        "single-date",
+        "If only one date resolves, use it for both the\n\
+         creation and modification dates",
+    ),
+    flag(
+        "skip-symlinks",
+        "Skip symlinked files instead of archiving the\n\
+         files they point to",
+    ),
+    flag("split-media", "Sort files into Photos/Videos/Other subfolders"),
+    value(
+        "stats-every",
+        "<DURATION>",
+        "Also print progress every DURATION (e.g. 5s;\n\
+         units s, m, h, d), even while no file finishes",
+    ),
+    value(
+        "stats-interval",
+        "<N>",
+        "Print progress every N files (default: 100;\n\
+         0 for only the final summary)",
+    ),
+    value("suffix", "<TEXT>", "Append TEXT after the counter in every filename"),
+    value(
+        "summary-format",
+        "<FORMAT>",
+        "Print the end-of-run summary as text (default),\n\
+         json, or both; with json, everything else\n\
+         goes to stderr",
+    ),
+    flag(
+        "tag-provenance",
+        "Write each archived image's or video's source\n\
+         path and import time into its XMP",
+    ),
+    value("tolerate-failures", "<N>", "Exit 0 even if up to N files fail (default: 0)"),
+    flag(
+        "trash",
+        "Move deleted source files and duplicates to the\n\
+         system trash instead of deleting them",
+    ),
+    flag(
+        "validate-decode",
+        "Decode each image (JPEG, PNG, GIF, TIFF, WebP)\n\
+         and archive any that are truncated or corrupt\n\
+         into a Corrupt folder",
+    ),
+    flag(
+        "verify-output",
+        "After the run, hash the whole output, list any\n\
+         byte-identical files and offer to delete extras",
+    ),
+    value(
+        "workers-per-volume",
+        "<N>",
+        "Read each source volume with its own N worker\n\
+         threads, so one disk isn't read at many places",
+    ),
+];
+
+/// Column the help text of each option starts at
+const HELP_COLUMN: usize = 32;
+
+/// The usage text, with one entry per option in [`OPTIONS`]
+fn usage() -> String {
+    let mut text = format!("{}\n\nOptions:", USAGE);
+    for option in OPTIONS {
+        let mut left = match option.short {
+            Some(short) => format!("  -{}, --{}", short, option.name),
+            None => format!("  --{}", option.name),
+        };
+        if let Some(placeholder) = option.value {
+            left = format!("{} {}", left, placeholder);
+        }
+        // A name too long for the column puts its help on the next line
+        let mut lines = option.help.lines();
+        if left.len() < HELP_COLUMN {
+            text = format!("{}\n{:<width$}{}", text, left, lines.next().unwrap_or_default(), width = HELP_COLUMN);
+        } else {
+            text = format!("{}\n{}", text, left);
+        }
+        for line in lines {
+            text = format!("{}\n{:<width$}{}", text, "", line, width = HELP_COLUMN);
+        }
+    }
+    text
+}
+
+/// Number of synthetic files the self-test generates by default
+const DEFAULT_SELFTEST_FILES: usize = 50;
+
+/// What the program was asked to do
+#[derive(Debug)]
+pub enum Command {
+    /// Archive media into the output directory
+    Archive(Box<Args>),
+    /// Print how a single file's dates are extracted, as JSON (`--inspect <file>`)
+    Inspect { file: PathBuf, extract: ExtractOptions },
+    /// Hidden self-test of the filename collision logic (`--selftest [N]`)
+    SelfTest { count: usize },
+}
+
+impl Command {
+    /// Parse command line arguments into the requested command
+    pub fn parse() -> Result<Self> {
+        let args: Vec<String> = std::env::args().collect();
+
+        if args.get(1).map(|s| s.as_str()) == Some("--selftest") {
+            let count = match args.get(2) {
+                Some(n) => n
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| anyhow!("--selftest expects a positive file count, got '{}'", n))?,
+                None => DEFAULT_SELFTEST_FILES,
+            };
+            if args.len() > 3 {
+                bail!("--selftest takes at most one argument");
+            }
+            return Ok(Command::SelfTest { count });
+        }
+
+        if args.get(1).is_some_and(|arg| arg == "--inspect" || arg.starts_with("--inspect=")) {
+            return parse_inspect(&args);
+        }
+
+        Ok(Command::Archive(Box::new(Args::parse_from(&args)?)))
+    }
+}
 
 #[derive(Debug)]
 pub struct Args {
     pub input_dirs: Vec<PathBuf>,
     pub output_dir: PathBuf,
+    /// File list to read instead of scanning `input_dirs` (`-` for stdin)
+    pub from_file: Option<PathBuf>,
+    pub settings: Settings,
 }
 
 impl Args {
     /// Parse and validate command line arguments
     pub fn parse() -> Result<Self> {
         let args: Vec<String> = std::env::args().collect();
+        Self::parse_from(&args)
+    }
 
-        if args.len() < 3 {
-            bail!("Usage: collect_media <dirs...> -o <output_dir>\n\nExample:\n  collect_media /Volumes/Thumb/One /Volumes/Thumb/Two -o /Users/me/Pictures/Library");
+    /// Parse and validate an argument list, program name first
+    pub fn parse_from(args: &[String]) -> Result<Self> {
+        let config = match find_config(args)? {
+            Some(path) => Some(load_config(Path::new(path), &usage_options())?),
+            None => None,
+        };
+        if args.len() < 3 && config.is_none() {
+            bail!("{}", usage());
         }
 
+        // Config file options go first, so the command line's override them
+        let args: Vec<String> = match &config {
+            Some(config) => args[..1].iter().chain(&config.options).chain(&args[1..]).cloned().collect(),
+            None => args.to_vec(),
+        };
+        let args = apply_negations(&args)?;
+        let args = args.as_slice();
+
         let mut output_dir: Option<PathBuf> = None;
-        let mut input_dirs: Vec<PathBuf> = Vec::new();
+        // Number of input directories seen before the output flag
+        let mut output_position: Option<usize> = None;
+        let mut input_args: Vec<String> = Vec::new();
+        let mut from_file: Option<PathBuf> = None;
+        let mut keep_failed_in_place = false;
+        let mut settings = Settings::default();
         let mut i = 1; // Skip program name
 
-        // Check if output flag is first
-        if args[i] == "-o" || args[i] == "--output-directory" || args[i] == "--output-dir" {
-            if i + 1 >= args.len() {
-                bail!("Output directory flag provided but no directory specified");
+        while i < args.len() {
+            // Everything after `--` is an input, even if it looks like a flag
+            if args[i] == "--" {
+                input_args.extend(args[i + 1..].iter().cloned());
+                break;
             }
-            output_dir = Some(PathBuf::from(&args[i + 1]));
-            i += 2;
-
-            // Collect remaining args as input directories
-            while i < args.len() {
-                input_dirs.push(PathBuf::from(&args[i]));
-                i += 1;
-            }
-        } else {
-            // Output flag must be last
-            // Collect input directories until we hit the output flag
-            while i < args.len() {
-                let arg = &args[i];
-                if arg == "-o" || arg == "--output-directory" || arg == "--output-dir" {
-                    if i + 1 >= args.len() {
-                        bail!("Output directory flag provided but no directory specified");
-                    }
-                    output_dir = Some(PathBuf::from(&args[i + 1]));
-                    i += 2;
-                    break;
+            for (arg, mut inline) in split_arg(&args[i])? {
+                match arg {
+                    "-o" | "--output-directory" | "--output-dir" => {
+                        if output_dir.is_some() {
+                            bail!("Output directory flag must be given exactly once");
+                        }
+                        let value = take_value(args, &mut i, &mut inline)
+                            .map_err(|_| anyhow!("Output directory flag provided but no directory specified"))?;
+                        output_dir = Some(PathBuf::from(value));
+                        output_position = Some(input_args.len());
+                    }
+                    "--analyze-overlap" => settings.analyze_overlap = true,
+                    "--array-date" => settings.extract.array_date = ArrayDate::parse(take_value(args, &mut i, &mut inline)?)?,
+                    "--auto-orient" => settings.auto_orient = true,
+                    // Already loaded by find_config
+                    "--config" => {
+                        take_value(args, &mut i, &mut inline)?;
+                    }
+                    "--checksum-algo" => settings.checksum_algo = Hasher::parse(take_value(args, &mut i, &mut inline)?)?,
+                    "--color" => settings.color = ColorChoice::parse(take_value(args, &mut i, &mut inline)?)?,
+                    "--no-color" => settings.color = ColorChoice::Never,
+                    "--no-prompt" => settings.no_prompt = true,
+                    "--copy-buffer-size" => {
+                        let size = parse_byte_size(take_value(args, &mut i, &mut inline)?)?;
+                        let size = usize::try_from(size)
+                            .ok()
+                            .filter(|&size| size > 0)
+                            .ok_or_else(|| anyhow!("--copy-buffer-size must be greater than zero"))?;
+                        if size > MAX_COPY_BUFFER_SIZE {
+                            bail!("--copy-buffer-size can be at most 256M, got {} bytes", size);
+                        }
+                        settings.copy_buffer_size = Some(size);
+                    }
+                    "--date-order" => {
+                        settings.extract.date_order = parse_date_order(take_value(args, &mut i, &mut inline)?)?;
+                    }
+                    "--dedupe-by" => settings.dedupe_by = DedupeBy::parse(take_value(args, &mut i, &mut inline)?)?,
+                    "--dedupe-scope" => {
+                        settings.dedupe_scope = DedupeScope::parse(take_value(args, &mut i, &mut inline)?)?;
+                    }
+                    "--dry-run" => settings.dry_run = true,
+                    "--dedupe-report-only" => settings.dedupe_report = Some(PathBuf::from(take_value(args, &mut i, &mut inline)?)),
+                    "--emit-sidecar" => settings.emit_sidecar = true,
+                    "--exiftool-arg" => settings.extract.exiftool_args.push(take_value(args, &mut i, &mut inline)?.to_string()),
+                    "--exif-timeout" => {
+                        let value = take_value(args, &mut i, &mut inline)?;
+                        let secs = value
+                            .parse::<u64>()
+                            .ok()
+                            .filter(|&secs| secs > 0)
+                            .ok_or_else(|| anyhow!("--exif-timeout expects a number of seconds, got '{}'", value))?;
+                        settings.extract.timeout = Some(std::time::Duration::from_secs(secs));
+                    }
+                    "--exec" => settings.exec = Some(take_value(args, &mut i, &mut inline)?.to_string()),
+                    "--failed-name" => {
+                        let name = take_value(args, &mut i, &mut inline)?;
+                        validate_folder_name(name)?;
+                        settings.failed_name = Some(name.to_string());
+                    }
+                    "--move-failed" => settings.move_failed = true,
+                    "--keep-failed-originals-in-place" => keep_failed_in_place = true,
+                    "--fix-extension" => settings.fix_extension = true,
+                    "--fix-perms" => settings.fix_perms = true,
+                    "--future-tolerance" => {
+                        settings.extract.date_policy.future_tolerance = parse_duration(take_value(args, &mut i, &mut inline)?)?;
+                    }
+                    "--from-file" => from_file = Some(PathBuf::from(take_value(args, &mut i, &mut inline)?)),
+                    "--group-window" => {
+                        let value = take_value(args, &mut i, &mut inline)?;
+                        settings.name_format.group_window_ms = value
+                            .parse::<u64>()
+                            .map_err(|_| anyhow!("--group-window expects a number of milliseconds, got '{}'", value))?;
+                    }
+                    "--ignore-name" => settings.ignore_names.push(take_value(args, &mut i, &mut inline)?.to_string()),
+                    "--include-hidden" => settings.include_hidden = true,
+                    "--interactive-dupes" => settings.interactive_dupes = true,
+                    "--keep-linked-sources" => settings.keep_linked_sources = true,
+                    "--link" => settings.link = LinkMode::parse(take_value(args, &mut i, &mut inline)?)?,
+                    "--limit" => {
+                        let value = take_value(args, &mut i, &mut inline)?;
+                        let limit = value
+                            .parse::<usize>()
+                            .map_err(|_| anyhow!("--limit expects a number of files, got '{}'", value))?;
+                        settings.limit = Some(limit);
+                    }
+                    "--location-grid" => {
+                        let value = take_value(args, &mut i, &mut inline)?;
+                        let grid = value
+                            .parse::<f64>()
+                            .ok()
+                            .filter(|g| *g > 0.0 && *g <= 180.0)
+                            .ok_or_else(|| anyhow!("--location-grid expects degrees between 0 and 180, got '{}'", value))?;
+                        settings.location_grid = Some(grid);
+                    }
+                    "--manifest" => settings.manifest = Some(PathBuf::from(take_value(args, &mut i, &mut inline)?)),
+                    "--max-rate" => {
+                        let rate = parse_byte_size(take_value(args, &mut i, &mut inline)?)?;
+                        if rate == 0 {
+                            bail!("--max-rate must be greater than zero");
+                        }
+                        settings.max_rate = Some(rate);
+                    }
+                    "--max-read-size" => {
+                        let size = parse_byte_size(take_value(args, &mut i, &mut inline)?)?;
+                        if size == 0 {
+                            bail!("--max-read-size must be greater than zero");
+                        }
+                        settings.max_read_size = Some(size);
+                    }
+                    "--max-depth" => {
+                        let value = take_value(args, &mut i, &mut inline)?;
+                        let depth = value
+                            .parse::<usize>()
+                            .ok()
+                            .filter(|&n| n >= 1)
+                            .ok_or_else(|| anyhow!("--max-depth expects a number of at least 1, got '{}'", value))?;
+                        settings.max_depth = Some(depth);
+                    }
+                    "--counter-style" => {
+                        settings.name_format.counter_style = CounterStyle::parse(take_value(args, &mut i, &mut inline)?)?;
+                    }
+                    "--max-collisions" => {
+                        let value = take_value(args, &mut i, &mut inline)?;
+                        settings.max_collisions = Some(
+                            value
+                                .parse::<u32>()
+                                .ok()
+                                .filter(|&n| n > 0)
+                                .ok_or_else(|| anyhow!("--max-collisions expects a positive number, got '{}'", value))?,
+                        );
+                    }
+                    "--ext-case" => settings.name_format.ext_case = ExtCase::parse(take_value(args, &mut i, &mut inline)?)?,
+                    "--ms" => settings.name_format.millis = MillisMode::parse(take_value(args, &mut i, &mut inline)?)?,
+                    "--on-conflict" => settings.on_conflict = ConflictPolicy::parse(take_value(args, &mut i, &mut inline)?)?,
+                    "--on-failure" => settings.on_failure = FailurePolicy::parse(take_value(args, &mut i, &mut inline)?)?,
+                    "--organize-by" => settings.organize_by = DateLayout::parse(take_value(args, &mut i, &mut inline)?)?,
+                    "--only-with-gps" | "--only-without-gps" => {
+                        let filter = if arg == "--only-with-gps" { GpsFilter::WithGps } else { GpsFilter::WithoutGps };
+                        if settings.gps_filter.is_some_and(|other| other != filter) {
+                            bail!("--only-with-gps cannot be combined with --only-without-gps");
+                        }
+                        settings.gps_filter = Some(filter);
+                    }
+                    "--organize-by-location" => settings.organize_by_location = true,
+                    "--preserve-structure" => settings.preserve_structure = true,
+                    "--quarantine-empty" => settings.quarantine_empty = true,
+                    "-r" | "--recursive" => settings.recursive = true,
+                    "--primary-date" => {
+                        settings.name_format.primary_date = PrimaryDate::parse(take_value(args, &mut i, &mut inline)?)?;
+                    }
+                    "--output-timezone" => {
+                        settings.name_format.timezone = OutputTimezone::parse(take_value(args, &mut i, &mut inline)?)?;
+                    }
+                    "--pair-previews" => settings.pair_previews = Some(PreviewPolicy::parse(take_value(args, &mut i, &mut inline)?)?),
+                    "--pair-priority" => {
+                        settings.pair_priority = take_value(args, &mut i, &mut inline)?
+                            .split(',')
+                            .map(|ext| normalize_extension(ext.trim().trim_start_matches('.')))
+                            .filter(|ext| !ext.is_empty())
+                            .collect();
+                        if settings.pair_priority.len() < 2 {
+                            bail!("--pair-priority expects at least two comma-separated types, e.g. DNG,JPG");
+                        }
+                    }
+                    "--phash-dedupe" => {
+                        let value = take_value(args, &mut i, &mut inline)?;
+                        let distance = value
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|d| *d <= MAX_DISTANCE)
+                            .ok_or_else(|| anyhow!("--phash-dedupe expects a distance from 0 to 64, got '{}'", value))?;
+                        settings.phash_dedupe = Some(distance);
+                    }
+                    "--preserve-mtime" => settings.preserve_mtime = true,
+                    "--keep-earliest-date" => settings.keep_earliest_date = true,
+                    "--prune-empty-sources" => settings.prune_empty_sources = true,
+                    "--field-sep" => {
+                        let sep = sanitize_affix(take_value(args, &mut i, &mut inline)?);
+                        if sep.is_empty() {
+                            bail!("--field-sep must not be empty");
+                        }
+                        settings.name_format.field_sep = Some(sep);
+                    }
+                    "--prefix" => settings.name_format.prefix = sanitize_affix(take_value(args, &mut i, &mut inline)?),
+                    "--suffix" => settings.name_format.suffix = sanitize_affix(take_value(args, &mut i, &mut inline)?),
+                    "--tolerate-failures" => {
+                        let value = take_value(args, &mut i, &mut inline)?;
+                        settings.tolerate_failures = value
+                            .parse::<usize>()
+                            .map_err(|_| anyhow!("--tolerate-failures expects a number of files, got '{}'", value))?;
+                    }
+                    "--resume" => settings.resume = true,
+                    "--review-suspicious" => settings.review_suspicious = true,
+                    "--single-date" => settings.extract.single_date = true,
+                    "--skip-symlinks" => settings.skip_symlinks = true,
+                    "--split-media" => settings.split_media = true,
+                    "--summary-format" => {
+                        settings.summary_format = SummaryFormat::parse(take_value(args, &mut i, &mut inline)?)?;
+                    }
+                    "--tag-provenance" => settings.tag_provenance = true,
+                    "--trash" => settings.trash = true,
+                    "--stats-every" => {
+                        let every = parse_duration(take_value(args, &mut i, &mut inline)?)?;
+                        settings.stats_every = every.to_std().ok().filter(|every| !every.is_zero());
+                        if settings.stats_every.is_none() {
+                            bail!("--stats-every must be longer than zero");
+                        }
+                    }
+                    "--progress-fd" => {
+                        let value = take_value(args, &mut i, &mut inline)?;
+                        settings.progress_fd = Some(
+                            value
+                                .parse::<i32>()
+                                .ok()
+                                .filter(|&fd| fd >= 0)
+                                .ok_or_else(|| anyhow!("--progress-fd expects a file descriptor number, got '{}'", value))?,
+                        );
+                    }
+                    "--stats-interval" => {
+                        let value = take_value(args, &mut i, &mut inline)?;
+                        settings.stats_interval = Some(
+                            value
+                                .parse()
+                                .map_err(|_| anyhow!("--stats-interval expects a number of files, got '{}'", value))?,
+                        );
+                    }
+                    "--validate-decode" => settings.validate_decode = true,
+                    "--confirm-deletes" => settings.confirm_deletes = true,
+                    "--list-unsupported" => settings.list_unsupported = true,
+                    "--verify-output" => settings.verify_output = true,
+                    "--workers-per-volume" => {
+                        let value = take_value(args, &mut i, &mut inline)?;
+                        settings.workers_per_volume = Some(
+                            value
+                                .parse::<usize>()
+                                .ok()
+                                .filter(|&n| n > 0)
+                                .ok_or_else(|| anyhow!("--workers-per-volume expects a positive number, got '{}'", value))?,
+                        );
+                    }
+                    "--media-folder" => {
+                        let value = take_value(args, &mut i, &mut inline)?;
+                        let (key, name) = value
+                            .split_once('=')
+                            .ok_or_else(|| anyhow!("--media-folder expects KEY=NAME, got '{}'", value))?;
+                        validate_folder_name(name)?;
+                        settings.media_folders.insert(normalize_media_key(key), name.to_string());
+                    }
+                    _ => input_args.push(arg.to_string()),
+                }
+                if inline.is_some() {
+                    bail!("{} does not take a value", arg);
                 }
-                input_dirs.push(PathBuf::from(arg));
-                i += 1;
             }
+            i += 1;
+        }
 
-            // Check if there are any arguments after the output directory
-            if i < args.len() {
-                bail!("Output directory flag must be either first or last in the argument list");
+        validate_exiftool_args(&settings.extract.exiftool_args)?;
+        IgnoreList::new(&settings.ignore_names)?;
+
+        if !settings.pair_priority.is_empty() && settings.pair_previews.is_none() {
+            bail!("--pair-priority requires --pair-previews");
+        }
+
+        if keep_failed_in_place && settings.move_failed {
+            bail!("--keep-failed-originals-in-place cannot be combined with --move-failed");
+        }
+
+        if settings.no_prompt && settings.interactive_dupes {
+            bail!("--no-prompt cannot be combined with --interactive-dupes");
+        }
+
+        if settings.keep_earliest_date && settings.dedupe_scope != DedupeScope::Global {
+            bail!("--keep-earliest-date requires --dedupe-scope global");
+        }
+
+        // Without it a copied library file's modification time is when it
+        // was archived, so it would always look newer than the source
+        if settings.on_conflict == ConflictPolicy::KeepNewer && !settings.preserve_mtime {
+            bail!("--on-conflict keep-newer requires --preserve-mtime");
+        }
+
+        if settings.keep_linked_sources && settings.link != LinkMode::Hardlink {
+            bail!("--keep-linked-sources requires --link hardlink");
+        }
+
+        if settings.recursive && settings.max_depth.is_some() {
+            bail!("--max-depth cannot be combined with --recursive");
+        }
+
+        // The command line's output and inputs replace the config file's
+        if let Some(config) = config {
+            if output_dir.is_none() {
+                output_dir = config.output.map(PathBuf::from);
+            }
+            if input_args.is_empty() && from_file.is_none() {
+                input_args = config.inputs;
             }
         }
 
         let output_dir = output_dir
             .ok_or_else(|| anyhow!("Output directory must be specified with -o, --output-directory, or --output-dir"))?;
 
-        if input_dirs.is_empty() {
+        // The output flag must come before or after all of the input directories
+        if let Some(position) = output_position {
+            if position != 0 && position != input_args.len() {
+                bail!("Output directory flag must be either first or last in the argument list");
+            }
+        }
+
+        let input_dirs = expand_input_dirs(&input_args)?;
+
+        if from_file.is_some() {
+            if !input_dirs.is_empty() {
+                bail!("Input directories cannot be combined with --from-file");
+            }
+        } else if input_dirs.is_empty() {
             bail!("At least one input directory must be specified");
         }
 
-        // Validate input directories exist and are directories
+        if settings.dry_run && (settings.dedupe_report.is_some() || settings.analyze_overlap) {
+            bail!("--dry-run cannot be combined with --dedupe-report-only or --analyze-overlap");
+        }
+
+        if settings.analyze_overlap {
+            if settings.dedupe_report.is_some() {
+                bail!("--analyze-overlap cannot be combined with --dedupe-report-only");
+            }
+            if input_dirs.len() < 2 {
+                bail!("--analyze-overlap compares input directories, so needs at least two");
+            }
+        }
+
+        // Validate input directories exist and are directories (or archives to import)
         for dir in &input_dirs {
+            if is_mtp_input(dir) {
+                continue;
+            }
             if !dir.exists() {
                 bail!("Input directory does not exist: {}", dir.display());
             }
+            if is_archive(dir) && dir.is_file() {
+                continue;
+            }
             if !dir.is_dir() {
                 bail!("Input path is not a directory: {}", dir.display());
             }
@@ -76,17 +928,498 @@ impl Args {
         Ok(Args {
             input_dirs,
             output_dir,
+            from_file,
+            settings,
         })
     }
 }
 
+/// Parse `--inspect <file>` plus the options that affect date extraction
+fn parse_inspect(args: &[String]) -> Result<Command> {
+    let mut file: Option<PathBuf> = None;
+    let mut extract = ExtractOptions::default();
+    let mut i = 1;
+
+    while i < args.len() {
+        for (arg, mut inline) in split_arg(&args[i])? {
+            match arg {
+                "--inspect" => {
+                    if file.is_some() {
+                        bail!("--inspect takes exactly one file");
+                    }
+                    file = Some(PathBuf::from(take_value(args, &mut i, &mut inline)?));
+                }
+                "--array-date" => extract.array_date = ArrayDate::parse(take_value(args, &mut i, &mut inline)?)?,
+                "--date-order" => extract.date_order = parse_date_order(take_value(args, &mut i, &mut inline)?)?,
+                "--exiftool-arg" => extract.exiftool_args.push(take_value(args, &mut i, &mut inline)?.to_string()),
+                "--future-tolerance" => {
+                    extract.date_policy.future_tolerance = parse_duration(take_value(args, &mut i, &mut inline)?)?
+                }
+                "--single-date" => extract.single_date = true,
+                other => bail!(
+                    "--inspect only accepts --array-date, --date-order, --exiftool-arg, --future-tolerance, and --single-date, got '{}'",
+                    other
+                ),
+            }
+            if inline.is_some() {
+                bail!("{} does not take a value", arg);
+            }
+        }
+        i += 1;
+    }
+
+    let file = file.ok_or_else(|| anyhow!("--inspect expects a file"))?;
+    validate_exiftool_args(&extract.exiftool_args)?;
+    if !file.is_file() {
+        bail!("Not a file: {}", file.display());
+    }
+
+    Ok(Command::Inspect { file, extract })
+}
+
+/// Parse a byte count such as `500000`, `512K`, `5M` or `1G` (binary units)
+fn parse_byte_size(value: &str) -> Result<u64> {
+    let invalid = || anyhow!("Expected a size in bytes such as 5M (suffixes K, M, G), got '{}'", value);
+    let (number, multiplier) = match value.chars().last() {
+        Some('K' | 'k') => (&value[..value.len() - 1], 1024),
+        Some('M' | 'm') => (&value[..value.len() - 1], 1024 * 1024),
+        Some('G' | 'g') => (&value[..value.len() - 1], 1024 * 1024 * 1024),
+        _ => (value, 1),
+    };
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+    number.checked_mul(multiplier).ok_or_else(invalid)
+}
+
+/// Parse a duration such as `90s`, `30m`, `48h` or `2d`
+fn parse_duration(value: &str) -> Result<chrono::Duration> {
+    let invalid = || anyhow!("Expected a duration such as 48h (units s, m, h, d), got '{}'", value);
+    let unit = value.chars().last().ok_or_else(invalid)?;
+    let number = &value[..value.len() - unit.len_utf8()];
+    let number: i64 = number.parse().ok().filter(|&n| n >= 0).ok_or_else(invalid)?;
+
+    let duration = match unit {
+        's' => chrono::TimeDelta::try_seconds(number),
+        'm' => chrono::TimeDelta::try_minutes(number),
+        'h' => chrono::TimeDelta::try_hours(number),
+        'd' => chrono::TimeDelta::try_days(number),
+        _ => return Err(invalid()),
+    };
+    duration.ok_or_else(|| anyhow!("Duration '{}' is too long", value))
+}
+
+fn parse_date_order(value: &str) -> Result<DateOrder> {
+    match value {
+        "warn" => Ok(DateOrder::Warn),
+        "swap" => Ok(DateOrder::Swap),
+        "fail" => Ok(DateOrder::Fail),
+        other => bail!("--date-order expects warn, swap, or fail, got '{}'", other),
+    }
+}
+
+/// Turn input arguments into directory paths, ignoring trailing slashes and
+/// expanding any containing glob wildcards (`*`, `?`, `[`) to the matching
+/// directories, for when the shell did not expand them
+fn expand_input_dirs(input_args: &[String]) -> Result<Vec<PathBuf>> {
+    let mut input_dirs: Vec<PathBuf> = Vec::new();
+
+    for arg in input_args {
+        if !arg.contains(['*', '?', '[']) {
+            input_dirs.push(normalize_dir(Path::new(arg)));
+            continue;
+        }
+
+        let mut matched = false;
+        for entry in glob::glob(arg).map_err(|e| anyhow!("Invalid glob pattern '{}': {}", arg, e))? {
+            let path = entry.map_err(|e| anyhow!("Failed to expand '{}': {}", arg, e))?;
+            if path.is_dir() {
+                matched = true;
+                let path = normalize_dir(&path);
+                if !input_dirs.contains(&path) {
+                    input_dirs.push(path);
+                }
+            }
+        }
+
+        if !matched {
+            bail!("Input pattern matched no directories: {}", arg);
+        }
+    }
+
+    Ok(input_dirs)
+}
+
+/// Rebuild a path from its components, dropping any trailing slash
+fn normalize_dir(path: &Path) -> PathBuf {
+    path.components().collect()
+}
+
+/// The `--config` file named in `args`, if any
+fn find_config(args: &[String]) -> Result<Option<&str>> {
+    let mut config = None;
+    for (i, arg) in args.iter().enumerate().skip(1).take_while(|(_, arg)| *arg != "--") {
+        let value = match arg.split_once('=') {
+            Some(("--config", value)) => value,
+            _ if arg == "--config" => args
+                .get(i + 1)
+                .ok_or_else(|| anyhow!("--config requires a value"))?,
+            _ => continue,
+        };
+        if config.replace(value).is_some() {
+            bail!("--config can only be given once");
+        }
+    }
+    Ok(config)
+}
+
+/// Every long option (without its `--`), and whether it takes a value
+fn usage_options() -> Vec<(&'static str, bool)> {
+    OPTIONS.iter().map(|option| (option.name, option.value.is_some())).collect()
+}
+
+/// Short flags, the option each stands for, and whether it takes a value
+const SHORT_FLAGS: &[(char, &str, bool)] = &[('o', "-o", true), ('r', "-r", false)];
+
+/// Split one command line argument into the options it holds, each with any
+/// value given inline: `--key=value` gives its value, and a short flag
+/// cluster such as `-ro/out`, `-ro=/out` or `-r -o /out` gives one option per
+/// letter, the letters after a value-taking flag being its value. Anything
+/// else, including a lone `-` and unknown words, is passed through as is
+fn split_arg(arg: &str) -> Result<Vec<(&str, Option<&str>)>> {
+    if let Some(long) = arg.strip_prefix("--") {
+        return Ok(match long.split_once('=') {
+            Some((key, value)) if !key.is_empty() => vec![(&arg[..key.len() + 2], Some(value))],
+            _ => vec![(arg, None)],
+        });
+    }
+
+    let Some(cluster) = arg.strip_prefix('-') else {
+        return Ok(vec![(arg, None)]);
+    };
+    let short = |c: char| SHORT_FLAGS.iter().find(|(letter, _, _)| *letter == c);
+    if cluster.chars().next().and_then(short).is_none() {
+        return Ok(vec![(arg, None)]);
+    }
+
+    let mut flags = Vec::new();
+    for (at, c) in cluster.char_indices() {
+        let &(_, flag, takes_value) = short(c).ok_or_else(|| anyhow!("Unknown flag -{} in '{}'", c, arg))?;
+        if takes_value {
+            let rest = &cluster[at + c.len_utf8()..];
+            let value = match rest.strip_prefix('=') {
+                Some(value) => Some(value),
+                None => Some(rest).filter(|rest| !rest.is_empty()),
+            };
+            flags.push((flag, value));
+            break;
+        }
+        flags.push((flag, None));
+    }
+    Ok(flags)
+}
+
+/// Whether `option`, given without an inline value, takes the next argument
+fn takes_next_value(option: &str) -> bool {
+    if SHORT_FLAGS.iter().any(|&(_, flag, takes_value)| flag == option && takes_value) {
+        return true;
+    }
+    let Some(name) = option.strip_prefix("--") else {
+        return false;
+    };
+    matches!(name, "output-dir" | "output-directory")
+        || OPTIONS.iter().any(|spec| spec.name == name && spec.value.is_some())
+}
+
+/// Apply each `--no-<flag>`, which turns off a flag given before it (such as
+/// by a config file): it removes the flag's earlier occurrences and itself.
+/// Short clusters are split up so a bundled `-r` can be removed too
+fn apply_negations(args: &[String]) -> Result<Vec<String>> {
+    // Each kept argument, and whether it's an option's value (never removed)
+    let mut kept: Vec<(String, bool)> = Vec::new();
+    let mut rest = args.iter();
+    if let Some(program) = rest.next() {
+        kept.push((program.clone(), true));
+    }
+
+    while let Some(arg) = rest.next() {
+        if arg == "--" {
+            kept.push((arg.clone(), false));
+            kept.extend(rest.by_ref().map(|arg| (arg.clone(), true)));
+            break;
+        }
+        let parts = split_arg(arg)?;
+        let negated = arg.strip_prefix("--no-").and_then(|name| {
+            let (name, inline) = name.split_once('=').map_or((name, None), |(name, value)| (name, Some(value)));
+            let spec = OPTIONS.iter().find(|spec| spec.name == name && spec.value.is_none())?;
+            Some((spec, inline))
+        });
+        if let Some((spec, inline)) = negated {
+            if inline.is_some() {
+                bail!("--no-{} does not take a value", spec.name);
+            }
+            let long = format!("--{}", spec.name);
+            let short = spec.short.map(|c| format!("-{}", c));
+            kept.retain(|(kept, is_value)| *is_value || (*kept != long && Some(kept) != short.as_ref()));
+            continue;
+        }
+
+        let mut last = None;
+        if parts.len() > 1 {
+            for (option, inline) in parts {
+                match inline {
+                    Some(value) => kept.push((format!("{}={}", option, value), false)),
+                    None => kept.push((option.to_string(), false)),
+                }
+                last = Some((option, inline));
+            }
+        } else {
+            kept.push((arg.clone(), false));
+            last = parts.into_iter().next();
+        }
+        if let Some((option, None)) = last {
+            if takes_next_value(option) {
+                kept.extend(rest.next().map(|value| (value.clone(), true)));
+            }
+        }
+    }
+    Ok(kept.into_iter().map(|(arg, _)| arg).collect())
+}
+
+/// Take the value given inline with the flag at `args[*i]` (`--key=value`),
+/// or else consume the argument following it
+fn take_value<'a>(args: &'a [String], i: &mut usize, inline: &mut Option<&'a str>) -> Result<&'a str> {
+    if let Some(value) = inline.take() {
+        return Ok(value);
+    }
+    let flag = &args[*i];
+    *i += 1;
+    args.get(*i)
+        .map(|s| s.as_str())
+        .ok_or_else(|| anyhow!("{} requires a value", flag))
+}
+
+/// Media categories are matched case-insensitively and FileTypes uppercased
+fn normalize_media_key(key: &str) -> String {
+    match key.to_lowercase().as_str() {
+        "image" | "video" | "other" => key.to_lowercase(),
+        _ => key.to_uppercase(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::TempDir;
+
+    /// Program name followed by `list`
+    fn argv(list: &[&str]) -> Vec<String> {
+        std::iter::once("collect_media").chain(list.iter().copied()).map(String::from).collect()
+    }
+
+    #[test]
+    fn test_output_flag_position() {
+        let root = TempDir::new("output_flag");
+        let (one, two) = (root.join("One"), root.join("Two"));
+        std::fs::create_dir_all(&one).unwrap();
+        std::fs::create_dir_all(&two).unwrap();
+        let (one, two) = (one.to_str().unwrap(), two.to_str().unwrap());
+
+        // First
+        let parsed = Args::parse_from(&argv(&["-o", "/library", one, two, "--recursive"])).unwrap();
+        assert_eq!(parsed.output_dir, PathBuf::from("/library"));
+        assert_eq!(parsed.input_dirs, [PathBuf::from(one), PathBuf::from(two)]);
+
+        // Last, with options in between not counting as inputs
+        let parsed = Args::parse_from(&argv(&[one, "--recursive", two, "--output-dir", "/library"])).unwrap();
+        assert_eq!(parsed.output_dir, PathBuf::from("/library"));
+        assert_eq!(parsed.input_dirs, [PathBuf::from(one), PathBuf::from(two)]);
+
+        let err = Args::parse_from(&argv(&[one, two, "--recursive"])).unwrap_err();
+        assert!(err.to_string().starts_with("Output directory must be specified"));
+
+        let err = Args::parse_from(&argv(&[one, "--output-directory", "/library", two])).unwrap_err();
+        assert_eq!(err.to_string(), "Output directory flag must be either first or last in the argument list");
+
+        let err = Args::parse_from(&argv(&["-o", "/library", one, "-o", "/elsewhere"])).unwrap_err();
+        assert_eq!(err.to_string(), "Output directory flag must be given exactly once");
+
+        let err = Args::parse_from(&argv(&[one, two, "-o"])).unwrap_err();
+        assert_eq!(err.to_string(), "Output directory flag provided but no directory specified");
+
+        // After `--`, flags are input paths
+        let parsed = Args::parse_from(&argv(&["-o", "/library", "--", one, two])).unwrap();
+        assert_eq!(parsed.input_dirs, [PathBuf::from(one), PathBuf::from(two)]);
+        let err = Args::parse_from(&argv(&["-o", "/library", one, "--", "--recursive"])).unwrap_err();
+        assert_eq!(err.to_string(), "Input directory does not exist: --recursive");
+        let err = Args::parse_from(&argv(&["-o", "/library", "--", one, "-o", "/elsewhere"])).unwrap_err();
+        assert_eq!(err.to_string(), "Input directory does not exist: -o");
+    }
+
+    #[test]
+    fn test_split_arg() {
+        assert_eq!(split_arg("--output-dir=/out").unwrap(), vec![("--output-dir", Some("/out"))]);
+        assert_eq!(split_arg("--media-folder=image=Pics").unwrap(), vec![("--media-folder", Some("image=Pics"))]);
+        assert_eq!(split_arg("--exec=").unwrap(), vec![("--exec", Some(""))]);
+        assert_eq!(split_arg("--recursive").unwrap(), vec![("--recursive", None)]);
+        assert_eq!(split_arg("-o=/out").unwrap(), vec![("-o", Some("/out"))]);
+        assert_eq!(split_arg("-o/out").unwrap(), vec![("-o", Some("/out"))]);
+        assert_eq!(split_arg("-ro").unwrap(), vec![("-r", None), ("-o", None)]);
+        assert_eq!(split_arg("-ro/out").unwrap(), vec![("-r", None), ("-o", Some("/out"))]);
+        assert!(split_arg("-rx").is_err());
+
+        // Values and paths that merely look like flags pass through
+        assert_eq!(split_arg("-api").unwrap(), vec![("-api", None)]);
+        assert_eq!(split_arg("-").unwrap(), vec![("-", None)]);
+        assert_eq!(split_arg("/Volumes/A=B").unwrap(), vec![("/Volumes/A=B", None)]);
+    }
+
+    #[test]
+    fn test_parse_from_joined_values() {
+        let input = TempDir::new("args");
+        let input_arg = input.to_str().unwrap();
+
+        let parsed = Args::parse_from(&argv(&[
+            input_arg,
+            "--prefix=Trip",
+            "--exif-timeout=30",
+            "--exiftool-arg=-api",
+            "--exiftool-arg",
+            "LargeFileSupport=1",
+            "--stats-interval=0",
+            "--stats-every=5s",
+            "--workers-per-volume=1",
+            "-ro=/out",
+        ]))
+        .unwrap();
+        assert_eq!(parsed.output_dir, PathBuf::from("/out"));
+        assert!(parsed.settings.recursive);
+        assert_eq!(parsed.settings.name_format.prefix, "Trip");
+        assert_eq!(parsed.settings.extract.timeout, Some(std::time::Duration::from_secs(30)));
+        assert_eq!(parsed.settings.extract.exiftool_args, ["-api", "LargeFileSupport=1"]);
+        assert_eq!(parsed.settings.stats_interval, Some(0));
+        assert_eq!(parsed.settings.stats_every, Some(std::time::Duration::from_secs(5)));
+        assert_eq!(parsed.settings.workers_per_volume, Some(1));
+
+        let parsed = Args::parse_from(&argv(&["-ro", "/out", input_arg])).unwrap();
+        assert_eq!(parsed.output_dir, PathBuf::from("/out"));
+        assert_eq!(parsed.input_dirs, vec![input.to_path_buf()]);
+
+        let err = Args::parse_from(&argv(&[input_arg, "--recursive=yes", "-o", "/out"])).unwrap_err();
+        assert_eq!(err.to_string(), "--recursive does not take a value");
+
+        let err = Args::parse_from(&argv(&[input_arg, "--copy-buffer-size=1G", "-o", "/out"])).unwrap_err();
+        assert_eq!(err.to_string(), "--copy-buffer-size can be at most 256M, got 1073741824 bytes");
+        let parsed = Args::parse_from(&argv(&[input_arg, "--copy-buffer-size=256M", "-o", "/out"])).unwrap();
+        assert_eq!(parsed.settings.copy_buffer_size, Some(MAX_COPY_BUFFER_SIZE));
+
+        let err = Args::parse_from(&argv(&[input_arg, "--on-conflict=keep-newer", "-o", "/out"])).unwrap_err();
+        assert_eq!(err.to_string(), "--on-conflict keep-newer requires --preserve-mtime");
+        let parsed = Args::parse_from(&argv(&[input_arg, "--on-conflict=keep-newer", "--preserve-mtime", "-o", "/out"]))
+            .unwrap();
+        assert_eq!(parsed.settings.on_conflict, ConflictPolicy::KeepNewer);
+    }
+
+    #[test]
+    fn test_parse_from_config() {
+        let root = TempDir::new("config");
+        let (one, two) = (root.join("One"), root.join("Two"));
+        std::fs::create_dir_all(&one).unwrap();
+        std::fs::create_dir_all(&two).unwrap();
+        let config = root.join("monthly.json");
+        let json = serde_json::json!({
+            "inputs": [one, two],
+            "output": "/library",
+            "organize-by": "month",
+            "recursive": true,
+            "tolerate-failures": 3,
+        });
+        std::fs::write(&config, json.to_string()).unwrap();
+        let config_arg = format!("--config={}", config.display());
+
+        let parsed = Args::parse_from(&argv(&[&config_arg])).unwrap();
+        assert_eq!(parsed.input_dirs, [one.clone(), two.clone()]);
+        assert_eq!(parsed.output_dir, PathBuf::from("/library"));
+        assert!(parsed.settings.recursive);
+        assert_eq!(parsed.settings.organize_by, DateLayout::Month);
+        assert_eq!(parsed.settings.tolerate_failures, 3);
+
+        // The command line wins
+        let one_arg = one.to_str().unwrap();
+        let parsed =
+            Args::parse_from(&argv(&[one_arg, "--config", config.to_str().unwrap(), "--organize-by", "year", "-o", "/other"]))
+                .unwrap();
+        assert_eq!(parsed.input_dirs, vec![one.clone()]);
+        assert_eq!(parsed.output_dir, PathBuf::from("/other"));
+        assert_eq!(parsed.settings.organize_by, DateLayout::Year);
+        assert!(parsed.settings.recursive);
+
+        // --no- turns off a flag the file sets
+        let parsed = Args::parse_from(&argv(&[&config_arg, "--no-recursive"])).unwrap();
+        assert!(!parsed.settings.recursive);
+
+        std::fs::write(&config, r#"{"output": "/library", "organise-by": "month"}"#).unwrap();
+        let err = Args::parse_from(&argv(&[one_arg, &config_arg])).unwrap_err();
+        assert!(format!("{:#}", err).contains("Unknown key 'organise-by'"));
+    }
+
+    #[test]
+    fn test_apply_negations() {
+        let negated = |args: &[&str]| apply_negations(&argv(args)).unwrap()[1..].to_vec();
+        assert_eq!(negated(&["-r", "/in", "--no-recursive", "-o", "/out"]), ["/in", "-o", "/out"]);
+        assert_eq!(negated(&["--no-recursive", "-ro", "/out"]), ["-r", "-o", "/out"]);
+        assert_eq!(negated(&["-ro", "/out", "--no-recursive"]), ["-o", "/out"]);
+        // Values and inputs after -- are never removed
+        assert_eq!(negated(&["--exiftool-arg", "--recursive", "--no-recursive"]), ["--exiftool-arg", "--recursive"]);
+        assert_eq!(negated(&["--no-recursive", "--", "--no-recursive"]), ["--", "--no-recursive"]);
+        // Real options starting with no- are left alone
+        assert_eq!(negated(&["--no-color", "--no-prompt"]), ["--no-color", "--no-prompt"]);
+        assert!(apply_negations(&argv(&["--no-recursive=yes"])).is_err());
+    }
+
+    #[test]
+    fn test_usage_options() {
+        let options = usage_options();
+        assert!(options.contains(&("recursive", false)));
+        assert!(options.contains(&("organize-by", true)));
+        assert!(options.contains(&("config", true)));
+        assert!(!options.iter().any(|(name, _)| name.is_empty() || name.starts_with('-')));
+    }
+
+    #[test]
+    fn test_parse_byte_size() {
+        assert_eq!(parse_byte_size("500000").unwrap(), 500_000);
+        assert_eq!(parse_byte_size("512K").unwrap(), 512 * 1024);
+        assert_eq!(parse_byte_size("5m").unwrap(), 5 * 1024 * 1024);
+        assert_eq!(parse_byte_size("1G").unwrap(), 1024 * 1024 * 1024);
+        assert!(parse_byte_size("fast").is_err());
+        assert!(parse_byte_size("M").is_err());
+    }
 
     #[test]
-    fn test_arg_parsing_logic() {
-        // Note: These tests would need to mock std::env::args
-        // For now, they serve as documentation of expected behavior
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("48h").unwrap(), chrono::Duration::hours(48));
+        assert_eq!(parse_duration("90s").unwrap(), chrono::Duration::seconds(90));
+        assert_eq!(parse_duration("2d").unwrap(), chrono::Duration::days(2));
+        assert!(parse_duration("48").is_err());
+        assert!(parse_duration("h").is_err());
+        assert!(parse_duration("-1h").is_err());
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("9223372036854775807d").unwrap_err().to_string().contains("too long"));
+    }
+
+    #[test]
+    fn test_expand_input_dirs() {
+        let root = TempDir::new("glob");
+        for name in ["DCIM_A", "DCIM_B", "Other"] {
+            std::fs::create_dir_all(root.join(name)).unwrap();
+        }
+        let root_str = root.to_str().unwrap();
+
+        let dirs = expand_input_dirs(&[format!("{}/DCIM_*", root_str)]).unwrap();
+        assert_eq!(dirs, vec![root.join("DCIM_A"), root.join("DCIM_B")]);
+
+        let dirs = expand_input_dirs(&[format!("{}/Other/", root_str)]).unwrap();
+        assert_eq!(dirs, vec![root.join("Other")]);
+
+        assert!(expand_input_dirs(&[format!("{}/Missing*", root_str)]).is_err());
     }
 }
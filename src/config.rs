@@ -0,0 +1,135 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::Path;
+
+/// Settings loaded from a `--config` file: a JSON object whose keys are the
+/// long option names without their `--`, plus `inputs` and `output`
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    /// Input directories, used when none are given on the command line
+    pub inputs: Vec<String>,
+    /// Output directory, used when no output flag is given
+    pub output: Option<String>,
+    /// The other keys as command line arguments, to be parsed ahead of the
+    /// real ones so those take precedence
+    pub options: Vec<String>,
+}
+
+/// Options a config file can't set: another config file, or the output flag
+/// (given as `output` instead)
+const EXCLUDED_KEYS: &[&str] = &["config", "output-dir", "output-directory"];
+
+/// Load a config file, checking each key against `known` long options (name
+/// and whether it takes a value). A value option takes a string or number,
+/// or an array of them to repeat it; a flag takes `true` or `false`
+pub fn load_config(path: &Path, known: &[(&str, bool)]) -> Result<Config> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read config file {}", path.display()))?;
+    let value: Value =
+        serde_json::from_str(&text).with_context(|| format!("Invalid JSON in config file {}", path.display()))?;
+    let Value::Object(object) = value else {
+        bail!("Config file {} must hold a JSON object of option names to values", path.display());
+    };
+    parse_config(object, known).with_context(|| format!("In config file {}", path.display()))
+}
+
+fn parse_config(object: Map<String, Value>, known: &[(&str, bool)]) -> Result<Config> {
+    let mut config = Config::default();
+
+    for (key, value) in object {
+        match key.as_str() {
+            "inputs" => {
+                config.inputs = match value {
+                    Value::Array(items) => items.iter().map(|item| scalar(&key, item)).collect::<Result<_>>()?,
+                    other => vec![scalar(&key, &other)?],
+                };
+                continue;
+            }
+            "output" => {
+                config.output = Some(scalar(&key, &value)?);
+                continue;
+            }
+            _ => {}
+        }
+
+        let takes_value = known
+            .iter()
+            .find(|(name, _)| *name == key)
+            .filter(|(name, _)| !EXCLUDED_KEYS.contains(name))
+            .map(|&(_, takes_value)| takes_value)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown key '{}': expected inputs, output, or an option name without its leading --",
+                    key
+                )
+            })?;
+        let flag = format!("--{}", key);
+
+        match (takes_value, value) {
+            (false, Value::Bool(true)) => config.options.push(flag),
+            (false, Value::Bool(false)) => config.options.push(format!("--no-{}", key)),
+            (false, _) => bail!("'{}' is a flag, so takes true or false", key),
+            (true, Value::Array(items)) => {
+                for item in &items {
+                    config.options.push(flag.clone());
+                    config.options.push(scalar(&key, item)?);
+                }
+            }
+            (true, value) => {
+                config.options.push(flag);
+                config.options.push(scalar(&key, &value)?);
+            }
+        }
+    }
+
+    Ok(config)
+}
+
+/// A string or number value as its command line text
+fn scalar(key: &str, value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Number(n) => Ok(n.to_string()),
+        _ => bail!("'{}' takes a string or number (or an array of them), got {}", key, value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const KNOWN: &[(&str, bool)] = &[("recursive", false), ("organize-by", true), ("exiftool-arg", true), ("config", true)];
+
+    fn parse(value: Value) -> Result<Config> {
+        match value {
+            Value::Object(object) => parse_config(object, KNOWN),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_config() {
+        let config = parse(json!({
+            "inputs": ["/Volumes/A", "/Volumes/B"],
+            "output": "/Library",
+            "recursive": true,
+            "organize-by": "month",
+            "exiftool-arg": ["-api", "LargeFileSupport=1"],
+        }))
+        .unwrap();
+        assert_eq!(config.inputs, ["/Volumes/A", "/Volumes/B"]);
+        assert_eq!(config.output.as_deref(), Some("/Library"));
+        assert_eq!(
+            config.options,
+            ["--exiftool-arg", "-api", "--exiftool-arg", "LargeFileSupport=1", "--organize-by", "month", "--recursive"]
+        );
+
+        let err = parse(json!({ "recursve": true })).unwrap_err();
+        assert!(err.to_string().starts_with("Unknown key 'recursve'"));
+        assert!(parse(json!({ "config": "other.json" })).is_err());
+        assert_eq!(parse(json!({ "recursive": false })).unwrap().options, ["--no-recursive"]);
+        assert!(parse(json!({ "recursive": "yes" })).is_err());
+        assert!(parse(json!({ "organize-by": true })).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::{Context, Result};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::throttle::RateLimiter;
+
+/// Chunk size for buffered copies when --copy-buffer-size isn't given
+pub const DEFAULT_COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Largest --copy-buffer-size accepted; the buffer is allocated for every
+/// copy, and beyond this a bigger one gains nothing
+pub const MAX_COPY_BUFFER_SIZE: usize = 256 * 1024 * 1024;
+
+/// Suffix of the temporary file a copy is written to before it is renamed
+/// to its final name
+pub const PARTIAL_SUFFIX: &str = ".part";
+
+/// Where a copy to `target` is written until it is complete
+pub fn partial_path(target: &Path) -> PathBuf {
+    let mut name = OsString::from(target.as_os_str());
+    name.push(PARTIAL_SUFFIX);
+    PathBuf::from(name)
+}
+
+/// Copy a file, returning the number of bytes copied. With neither a buffer
+/// size nor a rate limit this is `fs::copy`, which uses the platform's fast
+/// paths (`copy_file_range` on Linux, `clonefile`/`fcopyfile` on macOS).
+/// Otherwise the file is copied in chunks of `buffer_size`, pausing as needed
+/// to stay under the rate limit
+pub fn copy_file(
+    source: &Path,
+    target: &Path,
+    buffer_size: Option<usize>,
+    limiter: Option<&Mutex<RateLimiter>>,
+) -> Result<u64> {
+    if buffer_size.is_none() && limiter.is_none() {
+        return fs::copy(source, target)
+            .with_context(|| format!("Failed to copy {} to {}", source.display(), target.display()));
+    }
+
+    copy_buffered(source, target, buffer_size.unwrap_or(DEFAULT_COPY_BUFFER_SIZE), limiter)
+}
+
+/// Chunked copy; like `fs::copy`, the permissions of the source are copied too
+fn copy_buffered(
+    source: &Path,
+    target: &Path,
+    buffer_size: usize,
+    limiter: Option<&Mutex<RateLimiter>>,
+) -> Result<u64> {
+    let mut reader = File::open(source).with_context(|| format!("Failed to open {}", source.display()))?;
+    let mut writer = File::create(target).with_context(|| format!("Failed to create {}", target.display()))?;
+    let mut buffer = vec![0u8; buffer_size];
+    let mut copied = 0u64;
+
+    loop {
+        let read = reader
+            .read(&mut buffer)
+            .with_context(|| format!("Failed to read {}", source.display()))?;
+        if read == 0 {
+            break;
+        }
+        if let Some(limiter) = limiter {
+            limiter.lock().unwrap().consume(read);
+        }
+        writer
+            .write_all(&buffer[..read])
+            .with_context(|| format!("Failed to write {}", target.display()))?;
+        copied += read as u64;
+    }
+
+    let permissions = reader.metadata()?.permissions();
+    fs::set_permissions(target, permissions)?;
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_copy_buffered() {
+        let dir = TempDir::new("copy");
+        let content: Vec<u8> = (0..10_000u32).map(|i| (i % 251) as u8).collect();
+        fs::write(dir.join("source"), &content).unwrap();
+
+        let copied = copy_file(&dir.join("source"), &dir.join("target"), Some(4096), None).unwrap();
+        assert_eq!(copied, content.len() as u64);
+        assert_eq!(fs::read(dir.join("target")).unwrap(), content);
+    }
+
+    #[test]
+    fn test_partial_path() {
+        assert_eq!(partial_path(Path::new("/out/2020/a b.JPG")), PathBuf::from("/out/2020/a b.JPG.part"));
+    }
+}
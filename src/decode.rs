@@ -0,0 +1,49 @@
+use image::{ImageError, ImageReader};
+use std::path::Path;
+
+/// Try to fully decode an image, returning why it failed if it is truncated
+/// or corrupt. Formats the `image` crate can't decode (HEIC, RAW, ...) are
+/// not judged and return None
+pub fn decode_error(path: &Path) -> Option<String> {
+    let reader = match ImageReader::open(path).and_then(|reader| reader.with_guessed_format()) {
+        Ok(reader) => reader,
+        Err(e) => return Some(format!("Failed to open for decoding: {}", e)),
+    };
+    reader.format()?;
+
+    match reader.decode() {
+        Ok(_) => None,
+        Err(ImageError::Unsupported(_)) => None,
+        Err(e) => Some(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use image::{ImageFormat, RgbImage};
+    use std::fs;
+
+    #[test]
+    fn test_decode_error() {
+        let dir = TempDir::new("decode");
+        let picture = RgbImage::from_fn(64, 64, |x, y| image::Rgb([x as u8 * 4, y as u8 * 4, 128]));
+
+        for (name, format) in [("shot.jpg", ImageFormat::Jpeg), ("shot.png", ImageFormat::Png)] {
+            let whole = dir.join(name);
+            picture.save_with_format(&whole, format).unwrap();
+            assert_eq!(decode_error(&whole), None);
+
+            // A partial download
+            let content = fs::read(&whole).unwrap();
+            let truncated = dir.join(format!("truncated {}", name));
+            fs::write(&truncated, &content[..content.len() / 2]).unwrap();
+            assert!(decode_error(&truncated).is_some());
+        }
+
+        let other = dir.join("notes.heic");
+        fs::write(&other, b"not an image the image crate knows").unwrap();
+        assert_eq!(decode_error(&other), None);
+    }
+}
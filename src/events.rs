@@ -0,0 +1,19 @@
+use std::path::PathBuf;
+
+/// What the processor reports to an embedder (e.g. a GUI) as each file is
+/// handled, through the channel given to `Processor::set_event_sender`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProcessEvent {
+    /// The file's metadata was read and it is about to be archived
+    Started { path: PathBuf },
+    /// Renamed into the library (or hard-linked)
+    Moved { src: PathBuf, dst: PathBuf },
+    /// Copied into the library
+    Copied { src: PathBuf, dst: PathBuf },
+    /// Left out as a duplicate of `duplicate_of`
+    Skipped { path: PathBuf, duplicate_of: PathBuf },
+    /// Sent to Failed Cases (or only reported, in read-only runs)
+    Failed { path: PathBuf, reason: String },
+    /// `done` of the `total` files found have been handled
+    Progress { done: usize, total: usize },
+}
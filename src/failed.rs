@@ -4,11 +4,17 @@ use std::os::unix::fs as unix_fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
 
-/// Handle a failed file by creating a symlink and debug info file
+use crate::metadata::NoDateTags;
+
+/// Handle a failed file by creating a debug info file and a symlink to the
+/// original, which stays where it is. With `move_original` (--move-failed)
+/// the original itself is moved into Failed Cases instead, or linked to if
+/// it can't be moved
 pub fn handle_failed_file(
     file_path: &Path,
     failed_cases_dir: &Path,
     error: &anyhow::Error,
+    move_original: bool,
 ) -> Result<()> {
     // Get original filename
     let original_name = file_path
@@ -17,11 +23,24 @@ pub fn handle_failed_file(
         .unwrap_or("unknown");
 
     // Find available symlink name
-    let symlink_path = find_available_symlink_name(failed_cases_dir, original_name)?;
+    let symlink_path = find_available_name(failed_cases_dir, original_name)?;
+
+    // Read for the debug info while the file is still in its original place
+    let debug_info = generate_debug_info(file_path, error)?;
 
-    // Create symlink to original file
-    unix_fs::symlink(file_path, &symlink_path)
-        .with_context(|| format!("Failed to create symlink at {}", symlink_path.display()))?;
+    let moved = move_original
+        && match move_file(file_path, &symlink_path) {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Warning: Failed to move {} into Failed Cases, linking to it: {:#}", file_path.display(), e);
+                false
+            }
+        };
+    if !moved {
+        // Create symlink to original file
+        unix_fs::symlink(file_path, &symlink_path)
+            .with_context(|| format!("Failed to create symlink at {}", symlink_path.display()))?;
+    }
 
     // Create debug info file
     let debug_file_path = symlink_path.with_extension(
@@ -35,25 +54,50 @@ pub fn handle_failed_file(
         .trim_start_matches('.')
     );
 
-    let debug_info = generate_debug_info(file_path, error)?;
     fs::write(&debug_file_path, debug_info)
         .with_context(|| format!("Failed to write debug info to {}", debug_file_path.display()))?;
 
+    let moved_note = if moved { "moved into Failed Cases, " } else { "" };
     println!(
-        "Failed to process {}: {} (see {})",
+        "Failed to process {}: {} ({}see {})",
         file_path.display(),
         error,
+        moved_note,
         debug_file_path.display()
     );
 
     Ok(())
 }
 
-/// Find an available symlink name (add counter if needed)
-fn find_available_symlink_name(failed_cases_dir: &Path, original_name: &str) -> Result<PathBuf> {
-    let base_path = failed_cases_dir.join(original_name);
+/// Move a file, falling back to copy-and-delete across volumes. If that
+/// fails, the copy is removed again, leaving `target` free for a symlink
+pub fn move_file(source: &Path, target: &Path) -> Result<()> {
+    if fs::rename(source, target).is_ok() {
+        return Ok(());
+    }
+
+    if let Err(e) = fs::copy(source, target) {
+        let _ = fs::remove_file(target);
+        return Err(e).with_context(|| format!("Failed to copy {} to {}", source.display(), target.display()));
+    }
+    if let Err(e) = fs::remove_file(source) {
+        let _ = fs::remove_file(target);
+        return Err(e).with_context(|| format!("Failed to delete source file after copy: {}", source.display()));
+    }
+    Ok(())
+}
+
+/// Find an available name in a directory (add counter if needed)
+pub fn find_available_name(dir: &Path, original_name: &str) -> Result<PathBuf> {
+    available_name(dir, original_name, |path| path.exists())
+}
+
+/// [`find_available_name`], checking whether a name is taken with `exists`
+/// (such as a [`crate::fileops::FileOps`])
+pub fn available_name(dir: &Path, original_name: &str, exists: impl Fn(&Path) -> bool) -> Result<PathBuf> {
+    let base_path = dir.join(original_name);
 
-    if !base_path.exists() {
+    if !exists(&base_path) {
         return Ok(base_path);
     }
 
@@ -74,13 +118,13 @@ fn find_available_symlink_name(failed_cases_dir: &Path, original_name: &str) ->
             format!("{}-{}.{}", stem, counter, ext)
         };
 
-        let path = failed_cases_dir.join(&new_name);
-        if !path.exists() {
+        let path = dir.join(&new_name);
+        if !exists(&path) {
             return Ok(path);
         }
     }
 
-    anyhow::bail!("Could not find available symlink name for {}", original_name);
+    anyhow::bail!("Could not find available name for {}", original_name);
 }
 
 /// Generate debug information for a failed file
@@ -93,7 +137,7 @@ fn generate_debug_info(file_path: &Path, error: &anyhow::Error) -> Result<String
     if let Some(ext) = file_path.extension() {
         info.push_str(&format!("Extension: {}\n", ext.to_string_lossy()));
     }
-    info.push_str("\n");
+    info.push('\n');
 
     // File metadata (times)
     info.push_str("=== FILE TIMESTAMPS ===\n");
@@ -108,7 +152,7 @@ fn generate_debug_info(file_path: &Path, error: &anyhow::Error) -> Result<String
             info.push_str(&format!("Modified: {:?}\n", modified));
         }
     }
-    info.push_str("\n");
+    info.push('\n');
 
     // File command (MIME type)
     info.push_str("=== MIME TYPE (file command) ===\n");
@@ -124,7 +168,7 @@ fn generate_debug_info(file_path: &Path, error: &anyhow::Error) -> Result<String
             info.push_str(&format!("Error running file command: {}\n", e));
         }
     }
-    info.push_str("\n");
+    info.push('\n');
 
     // mdls command (macOS metadata)
     info.push_str("=== macOS METADATA (mdls) ===\n");
@@ -143,7 +187,15 @@ fn generate_debug_info(file_path: &Path, error: &anyhow::Error) -> Result<String
             info.push_str(&format!("Error running mdls command: {}\n", e));
         }
     }
-    info.push_str("\n");
+    info.push('\n');
+
+    // Outcome, to tell readable-but-dateless files from unreadable ones
+    info.push_str("=== OUTCOME ===\n");
+    match error.downcast_ref::<NoDateTags>() {
+        Some(_) => info.push_str("exiftool succeeded but no date tags\n"),
+        None => info.push_str("Failed\n"),
+    }
+    info.push('\n');
 
     // Error information
     info.push_str("=== ERROR ===\n");
@@ -155,9 +207,42 @@ fn generate_debug_info(file_path: &Path, error: &anyhow::Error) -> Result<String
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_find_available_name() {
+        let dir = TempDir::new("available_name");
+        assert_eq!(find_available_name(&dir, "IMG_0001.JPG").unwrap(), dir.join("IMG_0001.JPG"));
+
+        fs::write(dir.join("IMG_0001.JPG"), b"taken").unwrap();
+        fs::write(dir.join("IMG_0001-1.JPG"), b"taken").unwrap();
+        assert_eq!(find_available_name(&dir, "IMG_0001.JPG").unwrap(), dir.join("IMG_0001-2.JPG"));
+
+        fs::write(dir.join("README"), b"taken").unwrap();
+        assert_eq!(find_available_name(&dir, "README").unwrap(), dir.join("README-1"));
+    }
 
     #[test]
-    fn test_find_available_symlink_name() {
-        // This would need a temporary directory to test properly
+    fn test_handle_failed_file() {
+        let root = TempDir::new("failed");
+        let (source, failed) = (root.join("source"), root.join("Failed Cases"));
+        fs::create_dir_all(&source).unwrap();
+        fs::create_dir_all(&failed).unwrap();
+        fs::write(source.join("a.jpg"), b"a").unwrap();
+        fs::write(source.join("b.jpg"), b"b").unwrap();
+        let error = anyhow::anyhow!("No date tags");
+
+        // By default the original stays, linked from Failed Cases
+        handle_failed_file(&source.join("a.jpg"), &failed, &error, false).unwrap();
+        assert!(source.join("a.jpg").exists());
+        assert_eq!(fs::read_link(failed.join("a.jpg")).unwrap(), source.join("a.jpg"));
+        assert!(failed.join("a.jpg.txt").exists());
+
+        // --move-failed
+        handle_failed_file(&source.join("b.jpg"), &failed, &error, true).unwrap();
+        assert!(!source.join("b.jpg").exists());
+        assert!(!fs::symlink_metadata(failed.join("b.jpg")).unwrap().is_symlink());
+        assert_eq!(fs::read(failed.join("b.jpg")).unwrap(), b"b");
+        assert!(failed.join("b.jpg.txt").exists());
     }
 }
@@ -1,20 +1,180 @@
-use anyhow::Result;
-use chrono::{DateTime, Datelike, Timelike, Utc};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDateTime, Timelike, Utc};
+use std::fs::File;
 use std::path::{Path, PathBuf};
 
-use crate::metadata::MediaDates;
+use crate::hash::readers_equal;
+use crate::metadata::{MediaDates, PrimaryDate};
+
+/// Longest single path component most filesystems accept, in bytes
+const MAX_COMPONENT_BYTES: usize = 255;
+
+/// Longest full path accepted (PATH_MAX on macOS), in bytes
+const MAX_PATH_BYTES: usize = 1024;
+
+/// User options for how generated filenames are built
+#[derive(Debug, Clone, Default)]
+pub struct NameFormat {
+    /// Text placed before the first date
+    pub prefix: String,
+    /// Text placed after the counter, before the extension
+    pub suffix: String,
+    /// Which date comes first in the name
+    pub primary_date: PrimaryDate,
+    /// Time zone the dates in the name (and date folders) are shown in
+    pub timezone: OutputTimezone,
+    /// Floor both dates in the name to a multiple of this many milliseconds,
+    /// so files dated within one window share a base name (0 keeps them exact)
+    pub group_window_ms: u64,
+    /// When the `.NNN` milliseconds appear in the dates
+    pub millis: MillisMode,
+    /// Text between the two dates and before the counter (None: a space)
+    pub field_sep: Option<String>,
+    /// Whether the first file of a date pair gets a counter too
+    pub counter_style: CounterStyle,
+    /// Letter case of the extension
+    pub ext_case: ExtCase,
+}
+
+impl NameFormat {
+    fn field_sep(&self) -> &str {
+        self.field_sep.as_deref().unwrap_or(" ")
+    }
+}
+
+/// When a date in a generated name ends in its milliseconds
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MillisMode {
+    #[default]
+    Always,
+    /// Only when they aren't `.000`
+    Nonzero,
+    Never,
+}
+
+impl MillisMode {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "always" => Ok(MillisMode::Always),
+            "nonzero" => Ok(MillisMode::Nonzero),
+            "never" => Ok(MillisMode::Never),
+            other => bail!("--ms expects always, nonzero, or never, got '{}'", other),
+        }
+    }
+}
+
+/// When a generated name ends in its collision counter
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CounterStyle {
+    /// Every name, starting from 1
+    #[default]
+    Always,
+    /// Only from the second file with the same dates, so the first is bare
+    OnCollision,
+}
+
+impl CounterStyle {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "always" => Ok(CounterStyle::Always),
+            "on-collision" => Ok(CounterStyle::OnCollision),
+            other => bail!("--counter-style expects always or on-collision, got '{}'", other),
+        }
+    }
+}
+
+/// Letter case of a generated name's extension
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExtCase {
+    #[default]
+    Upper,
+    Lower,
+    /// As the source spelled it
+    Preserve,
+}
+
+impl ExtCase {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "upper" => Ok(ExtCase::Upper),
+            "lower" => Ok(ExtCase::Lower),
+            "preserve" => Ok(ExtCase::Preserve),
+            other => bail!("--ext-case expects upper, lower, or preserve, got '{}'", other),
+        }
+    }
+}
+
+/// The extension a generated name ends in: JPEG shortened to JPG, in the
+/// chosen case
+pub fn output_extension(ext: &str, case: ExtCase) -> String {
+    match case {
+        ExtCase::Upper => normalize_extension(ext),
+        ExtCase::Lower => normalize_extension(ext).to_lowercase(),
+        // Drop the E, keeping the other letters as written
+        ExtCase::Preserve if ext.eq_ignore_ascii_case("jpeg") => format!("{}{}", &ext[..2], &ext[3..]),
+        ExtCase::Preserve => ext.to_string(),
+    }
+}
+
+/// Time zone generated names and date folders are rendered in; dates are
+/// still validated and compared in UTC
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputTimezone {
+    #[default]
+    Utc,
+    /// The system time zone (honours the `TZ` environment variable)
+    Local,
+    /// A fixed offset from UTC, e.g. `+05:30`
+    Fixed(FixedOffset),
+}
+
+impl OutputTimezone {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "utc" | "UTC" => Ok(OutputTimezone::Utc),
+            "local" => Ok(OutputTimezone::Local),
+            offset => match offset.parse::<FixedOffset>() {
+                Ok(offset) => Ok(OutputTimezone::Fixed(offset)),
+                Err(_) => bail!(
+                    "--output-timezone expects utc, local, or an offset such as +05:30, got '{}' \
+                     (for a named zone, use local with TZ set, e.g. TZ=Europe/Paris)",
+                    offset
+                ),
+            },
+        }
+    }
+
+    /// The wall-clock time of a UTC date in this zone
+    pub fn convert(&self, date: &DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            OutputTimezone::Utc => date.naive_utc(),
+            OutputTimezone::Local => date.with_timezone(&Local).naive_local(),
+            OutputTimezone::Fixed(offset) => date.with_timezone(offset).naive_local(),
+        }
+    }
+}
 
 /// Generate a normalized filename based on creation and modification dates
+/// (in the order chosen by the format's primary date)
 pub fn generate_filename(
     dates: &MediaDates,
     original_extension: &str,
     counter: u32,
+    format: &NameFormat,
 ) -> String {
-    let creation = format_date(&dates.creation_date);
-    let modification = format_date(&dates.modify_date);
-    let ext = normalize_extension(original_extension);
+    let (first, second) = dates.ordered(format.primary_date);
+    let (first, second) = (name_date(first, format), name_date(second, format));
+    let ext = output_extension(original_extension, format.ext_case);
 
-    format!("{} {} {}.{}", creation, modification, counter, ext)
+    let sep = format.field_sep();
+
+    if counter == 1 && format.counter_style == CounterStyle::OnCollision {
+        return format!("{}{}{}{}{}.{}", format.prefix, first, sep, second, format.suffix, ext);
+    }
+    format!(
+        "{}{}{}{}{}{}{}.{}",
+        format.prefix, first, sep, second, sep, counter, format.suffix, ext
+    )
 }
 
 /// Generate filename without counter (for parallel processing)
@@ -22,26 +182,88 @@ pub fn generate_filename(
 pub fn generate_filename_without_counter(
     dates: &MediaDates,
     original_extension: &str,
+    format: &NameFormat,
 ) -> String {
-    let creation = format_date(&dates.creation_date);
-    let modification = format_date(&dates.modify_date);
-    let ext = normalize_extension(original_extension);
+    let (first, second) = dates.ordered(format.primary_date);
+    let (first, second) = (name_date(first, format), name_date(second, format));
+    let ext = output_extension(original_extension, format.ext_case);
 
-    format!("{} {}.{}", creation, modification, ext)
+    format!("{}{}{}{}{}.{}", format.prefix, first, format.field_sep(), second, format.suffix, ext)
 }
 
-/// Format a date as YYYY-MM-DD_HH.mm.SS.NNN
-fn format_date(date: &DateTime<Utc>) -> String {
-    format!(
-        "{:04}-{:02}-{:02}_{:02}.{:02}.{:02}.{:03}",
+/// Whether names generated for `dates` lead with an earlier date than `name`,
+/// a name generated with the same format (false for any other name)
+pub fn dates_precede_name(dates: &MediaDates, name: &str, format: &NameFormat) -> bool {
+    let (first, _) = dates.ordered(format.primary_date);
+    let date = name_date(first, format);
+    let Some(existing) = name.strip_prefix(format.prefix.as_str()).and_then(|rest| rest.get(..date.len())) else {
+        return false;
+    };
+    let is_date = |s: &str| NaiveDateTime::parse_from_str(s, "%Y-%m-%d_%H.%M.%S").is_ok();
+    // Dates are written most significant part first, so compare as text
+    existing.get(..19).is_some_and(is_date) && date.as_str() < existing
+}
+
+/// Make a user-supplied filename prefix or suffix safe to embed in a filename
+pub fn sanitize_affix(affix: &str) -> String {
+    affix
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect()
+}
+
+/// Floor a date to a multiple of `window_ms` milliseconds since the epoch
+/// (unchanged for a window of 0)
+fn quantize(date: &DateTime<Utc>, window_ms: u64) -> DateTime<Utc> {
+    let Ok(window) = i64::try_from(window_ms) else {
+        return *date;
+    };
+    if window == 0 {
+        return *date;
+    }
+    let millis = date.timestamp_millis();
+    DateTime::from_timestamp_millis(millis - millis.rem_euclid(window)).unwrap_or(*date)
+}
+
+/// A date as it appears in a generated name: grouped, in the output time
+/// zone, and formatted
+fn name_date(date: &DateTime<Utc>, format: &NameFormat) -> String {
+    format_date(&format.timezone.convert(&quantize(date, format.group_window_ms)), format.millis)
+}
+
+/// Format a date as YYYY-MM-DD_HH.mm.SS.NNN, leaving out the `.NNN` as
+/// `millis` says
+fn format_date(date: &NaiveDateTime, millis: MillisMode) -> String {
+    let seconds = format!(
+        "{:04}-{:02}-{:02}_{:02}.{:02}.{:02}",
         date.year(),
         date.month(),
         date.day(),
         date.hour(),
         date.minute(),
-        date.second(),
-        date.timestamp_subsec_millis()
-    )
+        date.second()
+    );
+    match (millis, date.and_utc().timestamp_subsec_millis()) {
+        (MillisMode::Never, _) | (MillisMode::Nonzero, 0) => seconds,
+        (_, ms) => format!("{}.{:03}", seconds, ms),
+    }
+}
+
+/// Spellings of the same file type's extension, after `normalize_extension`
+const EQUIVALENT_EXTENSIONS: &[(&str, &str)] = &[("TIFF", "TIF"), ("M2TS", "MTS"), ("MPEG", "MPG")];
+
+/// Check if two extensions name the same file type (`jpeg` and `JPG`,
+/// `TIFF` and `tif`)
+pub fn same_file_type(a: &str, b: &str) -> bool {
+    let (a, b) = (normalize_extension(a), normalize_extension(b));
+    a == b
+        || EQUIVALENT_EXTENSIONS
+            .iter()
+            .any(|&(x, y)| (a == x && b == y) || (a == y && b == x))
 }
 
 /// Normalize file extension: uppercase, JPEG -> JPG
@@ -61,17 +283,70 @@ pub fn get_extension(path: &Path) -> Option<String> {
         .map(|s| s.to_string())
 }
 
-/// Find the next available filename with incrementing counter
+/// Check that a destination path fits within filesystem length limits, so an
+/// over-long path fails with a clear message instead of a cryptic rename error
+pub fn check_path_length(path: &Path) -> Result<()> {
+    for component in path.components() {
+        let len = component.as_os_str().len();
+        if len > MAX_COMPONENT_BYTES {
+            bail!(
+                "Destination path component is {} bytes, longer than the {}-byte limit: {}",
+                len,
+                MAX_COMPONENT_BYTES,
+                component.as_os_str().to_string_lossy()
+            );
+        }
+    }
+
+    let len = path.as_os_str().len();
+    if len > MAX_PATH_BYTES {
+        bail!(
+            "Destination path is {} bytes, longer than the {}-byte limit: {}",
+            len,
+            MAX_PATH_BYTES,
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// Highest filename counter tried for one date pair before giving up,
+/// unless --max-collisions says otherwise
+pub const DEFAULT_MAX_COLLISIONS: u32 = 10000;
+
+/// The error for a date pair whose counters up to `max_collisions` are all
+/// taken, naming the pair so the burst can be found
+pub fn too_many_collisions(
+    dir: &Path,
+    dates: &MediaDates,
+    original_extension: &str,
+    max_collisions: u32,
+    format: &NameFormat,
+) -> anyhow::Error {
+    anyhow::anyhow!(
+        "Too many filename collisions: {} files already share the dates of '{}' in {} (raise --max-collisions)",
+        max_collisions,
+        generate_filename_without_counter(dates, original_extension, format),
+        dir.display()
+    )
+}
+
+/// Find the next available filename with incrementing counter, or the name
+/// of a file identical to `source` (compared in chunks, stopping at the
+/// first differing byte)
 pub fn find_available_filename(
     output_dir: &Path,
     dates: &MediaDates,
     original_extension: &str,
-    existing_content: Option<&[u8]>,
+    source: Option<&Path>,
+    max_collisions: u32,
+    format: &NameFormat,
 ) -> Result<(PathBuf, u32)> {
     let mut counter = 1;
 
     loop {
-        let filename = generate_filename(dates, original_extension, counter);
+        let filename = generate_filename(dates, original_extension, counter, format);
         let target_path = output_dir.join(&filename);
 
         if !target_path.exists() {
@@ -79,9 +354,8 @@ pub fn find_available_filename(
         }
 
         // File exists, check if it's the same content
-        if let Some(content) = existing_content {
-            let existing = std::fs::read(&target_path)?;
-            if existing == content {
+        if let Some(source) = source {
+            if readers_equal(File::open(source)?, File::open(&target_path)?)? {
                 // Same file already exists, no need to copy
                 return Ok((target_path, counter));
             }
@@ -90,8 +364,8 @@ pub fn find_available_filename(
         // Different file or we don't have content to compare, increment counter
         counter += 1;
 
-        if counter > 10000 {
-            anyhow::bail!("Too many filename collisions for the same date pair");
+        if counter > max_collisions {
+            return Err(too_many_collisions(output_dir, dates, original_extension, max_collisions, format));
         }
     }
 }
@@ -105,7 +379,41 @@ mod tests {
     fn test_format_date() {
         let date = Utc.with_ymd_and_hms(2025, 12, 17, 21, 58, 0).unwrap();
         let date = date + chrono::Duration::milliseconds(816);
-        assert_eq!(format_date(&date), "2025-12-17_21.58.00.816");
+        assert_eq!(format_date(&date.naive_utc(), MillisMode::Always), "2025-12-17_21.58.00.816");
+        assert_eq!(format_date(&date.naive_utc(), MillisMode::Nonzero), "2025-12-17_21.58.00.816");
+        assert_eq!(format_date(&date.naive_utc(), MillisMode::Never), "2025-12-17_21.58.00");
+
+        let whole = Utc.with_ymd_and_hms(2025, 12, 17, 21, 58, 0).unwrap().naive_utc();
+        assert_eq!(format_date(&whole, MillisMode::Always), "2025-12-17_21.58.00.000");
+        assert_eq!(format_date(&whole, MillisMode::Nonzero), "2025-12-17_21.58.00");
+    }
+
+    #[test]
+    fn test_output_timezone() {
+        let date = Utc.with_ymd_and_hms(2025, 12, 31, 22, 30, 0).unwrap();
+        let dates = MediaDates {
+            creation_date: date,
+            modify_date: date,
+        };
+        let format = NameFormat {
+            timezone: OutputTimezone::parse("+05:30").unwrap(),
+            ..NameFormat::default()
+        };
+        assert_eq!(
+            generate_filename(&dates, "jpg", 1, &format),
+            "2026-01-01_04.00.00.000 2026-01-01_04.00.00.000 1.JPG"
+        );
+        assert_eq!(OutputTimezone::parse("utc").unwrap(), OutputTimezone::Utc);
+        assert!(OutputTimezone::parse("Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn test_same_file_type() {
+        assert!(same_file_type("jpeg", "JPG"));
+        assert!(same_file_type("tif", "TIFF"));
+        assert!(same_file_type("mov", "MOV"));
+        assert!(!same_file_type("jpg", "heic"));
+        assert!(!same_file_type("mov", "mp4"));
     }
 
     #[test]
@@ -118,6 +426,50 @@ mod tests {
         assert_eq!(normalize_extension("heic"), "HEIC");
     }
 
+    #[test]
+    fn test_dates_precede_name() {
+        let at = |hour| {
+            let date = Utc.with_ymd_and_hms(2025, 8, 10, hour, 0, 0).unwrap();
+            MediaDates {
+                creation_date: date,
+                modify_date: date,
+            }
+        };
+        let format = NameFormat {
+            prefix: "canon_".to_string(),
+            ..NameFormat::default()
+        };
+        let name = generate_filename(&at(12), "jpg", 3, &format);
+        assert!(dates_precede_name(&at(11), &name, &format));
+        assert!(!dates_precede_name(&at(12), &name, &format));
+        assert!(!dates_precede_name(&at(13), &name, &format));
+        assert!(!dates_precede_name(&at(11), "IMG_0001.JPG", &format));
+        assert!(!dates_precede_name(&at(11), &name, &NameFormat::default()));
+    }
+
+    #[test]
+    fn test_output_extension() {
+        assert_eq!(output_extension("jpeg", ExtCase::Upper), "JPG");
+        assert_eq!(output_extension("Mov", ExtCase::Upper), "MOV");
+        assert_eq!(output_extension("JPEG", ExtCase::Lower), "jpg");
+        assert_eq!(output_extension("HEIC", ExtCase::Lower), "heic");
+        assert_eq!(output_extension("Jpeg", ExtCase::Preserve), "Jpg");
+        assert_eq!(output_extension("jpeg", ExtCase::Preserve), "jpg");
+        assert_eq!(output_extension("Mov", ExtCase::Preserve), "Mov");
+        assert!(ExtCase::parse("title").is_err());
+    }
+
+    #[test]
+    fn test_check_path_length() {
+        assert!(check_path_length(Path::new("/out/2025-08-10_03.43.16.000 1.MOV")).is_ok());
+
+        let long_component = format!("/out/{}.MOV", "a".repeat(300));
+        assert!(check_path_length(Path::new(&long_component)).is_err());
+
+        let long_path = format!("/out{}", "/abcdefgh".repeat(120));
+        assert!(check_path_length(Path::new(&long_path)).is_err());
+    }
+
     #[test]
     fn test_generate_filename() {
         let creation = Utc.with_ymd_and_hms(2025, 8, 10, 3, 43, 16).unwrap();
@@ -128,10 +480,86 @@ mod tests {
             modify_date: modification,
         };
 
-        let filename = generate_filename(&dates, "MOV", 1);
+        let filename = generate_filename(&dates, "MOV", 1, &NameFormat::default());
         assert_eq!(
             filename,
             "2025-08-10_03.43.16.000 2025-08-10_03.43.16.000 1.MOV"
         );
+
+        let format = NameFormat {
+            prefix: "canon_".to_string(),
+            suffix: "_edit".to_string(),
+            ..NameFormat::default()
+        };
+        let filename = generate_filename(&dates, "MOV", 2, &format);
+        assert_eq!(
+            filename,
+            "canon_2025-08-10_03.43.16.000 2025-08-10_03.43.16.000 2_edit.MOV"
+        );
+
+        let format = NameFormat {
+            field_sep: Some("__".to_string()),
+            ..NameFormat::default()
+        };
+        assert_eq!(
+            generate_filename(&dates, "MOV", 3, &format),
+            "2025-08-10_03.43.16.000__2025-08-10_03.43.16.000__3.MOV"
+        );
+
+        let dates = MediaDates {
+            creation_date: creation,
+            modify_date: Utc.with_ymd_and_hms(2025, 9, 1, 12, 0, 0).unwrap(),
+        };
+        let format = NameFormat {
+            primary_date: PrimaryDate::Modify,
+            ..NameFormat::default()
+        };
+        assert_eq!(
+            generate_filename(&dates, "pdf", 1, &format),
+            "2025-09-01_12.00.00.000 2025-08-10_03.43.16.000 1.PDF"
+        );
+
+        let format = NameFormat {
+            counter_style: CounterStyle::OnCollision,
+            suffix: "_edit".to_string(),
+            ..NameFormat::default()
+        };
+        assert_eq!(
+            generate_filename(&dates, "jpg", 1, &format),
+            "2025-08-10_03.43.16.000 2025-09-01_12.00.00.000_edit.JPG"
+        );
+        assert_eq!(
+            generate_filename(&dates, "jpg", 2, &format),
+            "2025-08-10_03.43.16.000 2025-09-01_12.00.00.000 2_edit.JPG"
+        );
+    }
+
+    #[test]
+    fn test_group_window() {
+        let base = Utc.with_ymd_and_hms(2025, 8, 10, 3, 43, 16).unwrap();
+        let format = NameFormat {
+            group_window_ms: 2000,
+            ..NameFormat::default()
+        };
+        let scan = |creation_ms, modify_ms| MediaDates {
+            creation_date: base + chrono::Duration::milliseconds(creation_ms),
+            modify_date: base + chrono::Duration::milliseconds(modify_ms),
+        };
+
+        // 03:43:16.000 to 03:43:17.999 all land in the same window
+        let first = generate_filename_without_counter(&scan(120, 480), "jpg", &format);
+        assert_eq!(first, "2025-08-10_03.43.16.000 2025-08-10_03.43.16.000.JPG");
+        assert_eq!(generate_filename_without_counter(&scan(1700, 1950), "jpg", &format), first);
+        assert_ne!(generate_filename_without_counter(&scan(2100, 2300), "jpg", &format), first);
+        assert_eq!(
+            generate_filename(&scan(120, 480), "jpg", 1, &NameFormat::default()),
+            "2025-08-10_03.43.16.120 2025-08-10_03.43.16.480 1.JPG"
+        );
+    }
+
+    #[test]
+    fn test_sanitize_affix() {
+        assert_eq!(sanitize_affix("canon_"), "canon_");
+        assert_eq!(sanitize_affix("a/b\\c:d\n"), "a_b_c_d_");
     }
 }
@@ -0,0 +1,322 @@
+use anyhow::{Context, Result};
+use filetime::FileTime;
+use std::ffi::CString;
+use std::fs;
+use std::io::{self, Read};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::copy::copy_file;
+use crate::throttle::RateLimiter;
+
+/// The file details the transfer layer looks at
+#[derive(Debug, Clone, Copy)]
+pub struct FileMeta {
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+/// Filesystem operations used by the duplicate checks and transfers, so they
+/// can be run against an in-memory filesystem in tests
+pub trait FileOps: Send + Sync {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    /// Open a file for reading in chunks
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>>;
+    fn copy(&self, from: &Path, to: &Path) -> Result<u64>;
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    /// Give `from`'s data the additional name `to`
+    fn hard_link(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn remove_file(&self, path: &Path) -> io::Result<()>;
+    /// Move a file to the system trash instead of deleting it
+    fn trash(&self, path: &Path) -> io::Result<()>;
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+    /// Create or replace a file holding `content`
+    fn write_file(&self, path: &Path, content: &[u8]) -> io::Result<()>;
+    fn exists(&self, path: &Path) -> bool;
+    fn metadata(&self, path: &Path) -> io::Result<FileMeta>;
+    /// Check if two existing paths are on the same filesystem volume
+    fn same_volume(&self, a: &Path, b: &Path) -> Result<bool>;
+    /// Check if `path` is on a volume mounted read-only (a locked SD card)
+    fn is_read_only(&self, path: &Path) -> io::Result<bool>;
+    /// Check that files can be created in the directory `dir` by creating
+    /// and deleting one
+    fn probe_writable(&self, dir: &Path) -> io::Result<()>;
+    /// Give `to` the access and modification times of `from`
+    fn copy_times(&self, from: &Path, to: &Path) -> io::Result<()>;
+}
+
+/// The real filesystem, copying with the --copy-buffer-size and --max-rate settings
+#[derive(Debug, Default)]
+pub struct RealFs {
+    copy_buffer_size: Option<usize>,
+    rate_limiter: Option<Mutex<RateLimiter>>,
+}
+
+impl RealFs {
+    pub fn new(copy_buffer_size: Option<usize>, max_rate: Option<u64>) -> Self {
+        RealFs {
+            copy_buffer_size,
+            rate_limiter: max_rate.map(|rate| Mutex::new(RateLimiter::new(rate))),
+        }
+    }
+}
+
+impl FileOps for RealFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+        Ok(Box::new(fs::File::open(path)?))
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<u64> {
+        copy_file(from, to, self.copy_buffer_size, self.rate_limiter.as_ref())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn hard_link(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::hard_link(from, to)
+    }
+
+    fn remove_file(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn trash(&self, path: &Path) -> io::Result<()> {
+        trash::delete(path).map_err(io::Error::other)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn write_file(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+        fs::write(path, content)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn metadata(&self, path: &Path) -> io::Result<FileMeta> {
+        let metadata = fs::metadata(path)?;
+        Ok(FileMeta {
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    fn same_volume(&self, a: &Path, b: &Path) -> Result<bool> {
+        let meta_a = fs::metadata(a).with_context(|| format!("Failed to get metadata for {}", a.display()))?;
+        let meta_b = fs::metadata(b).with_context(|| format!("Failed to get metadata for {}", b.display()))?;
+
+        // Compare device IDs (st_dev on Unix)
+        Ok(meta_a.dev() == meta_b.dev())
+    }
+
+    fn is_read_only(&self, path: &Path) -> io::Result<bool> {
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        let mut stat = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+        // SAFETY: c_path is a valid NUL-terminated string, and statvfs fills
+        // in `stat` whenever it returns 0
+        let stat = unsafe {
+            if libc::statvfs(c_path.as_ptr(), stat.as_mut_ptr()) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            stat.assume_init()
+        };
+        Ok(stat.f_flag & libc::ST_RDONLY != 0)
+    }
+
+    fn probe_writable(&self, dir: &Path) -> io::Result<()> {
+        let probe = dir.join(format!(".collect_media_write_probe.{}", std::process::id()));
+        fs::OpenOptions::new().write(true).create_new(true).open(&probe)?;
+        fs::remove_file(&probe)
+    }
+
+    fn copy_times(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let metadata = fs::metadata(from)?;
+        filetime::set_file_times(
+            to,
+            FileTime::from_last_access_time(&metadata),
+            FileTime::from_last_modification_time(&metadata),
+        )
+    }
+}
+
+#[cfg(test)]
+pub use memory::MemoryFs;
+
+#[cfg(test)]
+mod memory {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+    use std::path::PathBuf;
+
+    /// In-memory filesystem for tests
+    #[derive(Debug)]
+    pub struct MemoryFs {
+        files: Mutex<HashMap<PathBuf, (Vec<u8>, SystemTime)>>,
+        dirs: Mutex<HashSet<PathBuf>>,
+        same_volume: bool,
+        /// Folders whose files can be read but not moved or deleted
+        read_only_dirs: Mutex<Vec<PathBuf>>,
+        /// Paths of the files moved to the trash, in order
+        trashed: Mutex<Vec<PathBuf>>,
+    }
+
+    impl Default for MemoryFs {
+        fn default() -> Self {
+            MemoryFs::new(true)
+        }
+    }
+
+    fn not_found(path: &Path) -> io::Error {
+        io::Error::new(io::ErrorKind::NotFound, format!("{} not found", path.display()))
+    }
+
+    impl MemoryFs {
+        /// An empty filesystem; with `same_volume` false, every pair of paths
+        /// is on different volumes, so transfers are copies
+        pub fn new(same_volume: bool) -> Self {
+            MemoryFs {
+                files: Mutex::new(HashMap::new()),
+                dirs: Mutex::new(HashSet::new()),
+                same_volume,
+                read_only_dirs: Mutex::new(Vec::new()),
+                trashed: Mutex::new(Vec::new()),
+            }
+        }
+
+        /// Treat everything under `dir` as on a read-only volume
+        pub fn mount_read_only(&self, dir: &Path) {
+            self.read_only_dirs.lock().unwrap().push(dir.to_path_buf());
+        }
+
+        fn check_writable(&self, path: &Path) -> io::Result<()> {
+            if self.is_read_only(path)? {
+                return Err(io::Error::from(io::ErrorKind::ReadOnlyFilesystem));
+            }
+            Ok(())
+        }
+
+        pub fn write(&self, path: &Path, content: &[u8]) {
+            self.files
+                .lock()
+                .unwrap()
+                .insert(path.to_path_buf(), (content.to_vec(), SystemTime::now()));
+        }
+
+        /// Paths of the files moved to the trash, in order
+        pub fn trashed(&self) -> Vec<PathBuf> {
+            self.trashed.lock().unwrap().clone()
+        }
+
+        /// Paths of every file, sorted
+        pub fn files(&self) -> Vec<PathBuf> {
+            let mut files: Vec<PathBuf> = self.files.lock().unwrap().keys().cloned().collect();
+            files.sort();
+            files
+        }
+    }
+
+    impl FileOps for MemoryFs {
+        fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+            let files = self.files.lock().unwrap();
+            files.get(path).map(|(content, _)| content.clone()).ok_or_else(|| not_found(path))
+        }
+
+        fn open(&self, path: &Path) -> io::Result<Box<dyn Read + Send>> {
+            Ok(Box::new(io::Cursor::new(self.read(path)?)))
+        }
+
+        fn copy(&self, from: &Path, to: &Path) -> Result<u64> {
+            let content = self.read(from)?;
+            self.write(to, &content);
+            Ok(content.len() as u64)
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            self.check_writable(from)?;
+            let mut files = self.files.lock().unwrap();
+            let file = files.remove(from).ok_or_else(|| not_found(from))?;
+            files.insert(to.to_path_buf(), file);
+            Ok(())
+        }
+
+        /// A copy, as the test filesystem has no shared data; fails as a
+        /// link between devices would unless created as one volume
+        fn hard_link(&self, from: &Path, to: &Path) -> io::Result<()> {
+            if !self.same_volume {
+                return Err(io::Error::from(io::ErrorKind::CrossesDevices));
+            }
+            let mut files = self.files.lock().unwrap();
+            let file = files.get(from).cloned().ok_or_else(|| not_found(from))?;
+            files.insert(to.to_path_buf(), file);
+            Ok(())
+        }
+
+        fn remove_file(&self, path: &Path) -> io::Result<()> {
+            self.check_writable(path)?;
+            let mut files = self.files.lock().unwrap();
+            files.remove(path).map(|_| ()).ok_or_else(|| not_found(path))
+        }
+
+        fn trash(&self, path: &Path) -> io::Result<()> {
+            self.remove_file(path)?;
+            self.trashed.lock().unwrap().push(path.to_path_buf());
+            Ok(())
+        }
+
+        fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+            self.dirs.lock().unwrap().insert(path.to_path_buf());
+            Ok(())
+        }
+
+        fn write_file(&self, path: &Path, content: &[u8]) -> io::Result<()> {
+            self.check_writable(path)?;
+            self.write(path, content);
+            Ok(())
+        }
+
+        fn exists(&self, path: &Path) -> bool {
+            self.files.lock().unwrap().contains_key(path) || self.dirs.lock().unwrap().contains(path)
+        }
+
+        fn metadata(&self, path: &Path) -> io::Result<FileMeta> {
+            let files = self.files.lock().unwrap();
+            let (content, modified) = files.get(path).ok_or_else(|| not_found(path))?;
+            Ok(FileMeta {
+                len: content.len() as u64,
+                modified: Some(*modified),
+            })
+        }
+
+        fn same_volume(&self, _a: &Path, _b: &Path) -> Result<bool> {
+            Ok(self.same_volume)
+        }
+
+        fn is_read_only(&self, path: &Path) -> io::Result<bool> {
+            Ok(self.read_only_dirs.lock().unwrap().iter().any(|dir| path.starts_with(dir)))
+        }
+
+        fn probe_writable(&self, dir: &Path) -> io::Result<()> {
+            self.check_writable(dir)
+        }
+
+        /// Only the modification time, as that is all the test filesystem keeps
+        fn copy_times(&self, from: &Path, to: &Path) -> io::Result<()> {
+            let mut files = self.files.lock().unwrap();
+            let (_, modified) = *files.get(from).ok_or_else(|| not_found(from))?;
+            files.get_mut(to).ok_or_else(|| not_found(to))?.1 = modified;
+            Ok(())
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use anyhow::{bail, Context, Result};
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Size of the chunks files are read in while hashing
+const HASH_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Size of the first chunk compared by `readers_equal`: files that aren't
+/// duplicates usually differ in their headers, so a full chunk isn't read
+/// from each just to find that out
+const FIRST_COMPARE_SIZE: usize = 64 * 1024;
+
+/// Hash function for duplicate checks and manifests (--checksum-algo), so
+/// callers never depend on a particular algorithm
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Hasher {
+    Blake3,
+    /// Verifiable with `shasum -a 256`, so the default
+    #[default]
+    Sha256,
+    Md5,
+}
+
+/// An in-progress digest of one of the `Hasher` algorithms
+enum State {
+    Blake3(Box<blake3::Hasher>),
+    Sha256(Sha256),
+    Md5(Md5),
+}
+
+impl Hasher {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "blake3" => Ok(Hasher::Blake3),
+            "sha256" => Ok(Hasher::Sha256),
+            "md5" => Ok(Hasher::Md5),
+            other => bail!("--checksum-algo expects blake3, sha256, or md5, got '{}'", other),
+        }
+    }
+
+    /// The algorithm's name, as written in reports
+    pub fn name(self) -> &'static str {
+        match self {
+            Hasher::Blake3 => "blake3",
+            Hasher::Sha256 => "sha256",
+            Hasher::Md5 => "md5",
+        }
+    }
+
+    /// Compute the digest of a file as lowercase hex, reading it in chunks
+    /// so large videos are never held in memory whole
+    pub fn hash_file(self, path: &Path) -> Result<String> {
+        let file = File::open(path)
+            .with_context(|| format!("Failed to open {} for hashing", path.display()))?;
+        self.hash_reader(file)
+            .with_context(|| format!("Failed to read {} for hashing", path.display()))
+    }
+
+    /// Compute the digest of everything `reader` yields, in chunks
+    pub fn hash_reader(self, mut reader: impl Read) -> io::Result<String> {
+        let mut state = self.start();
+        let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
+
+        loop {
+            let read = reader.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            state.update(&buffer[..read]);
+        }
+
+        Ok(state.finish())
+    }
+
+    /// Compute the digest of in-memory content as lowercase hex
+    pub fn hash_bytes(self, content: &[u8]) -> String {
+        let mut state = self.start();
+        state.update(content);
+        state.finish()
+    }
+
+    fn start(self) -> State {
+        match self {
+            Hasher::Blake3 => State::Blake3(Box::default()),
+            Hasher::Sha256 => State::Sha256(Sha256::new()),
+            Hasher::Md5 => State::Md5(Md5::new()),
+        }
+    }
+}
+
+impl State {
+    fn update(&mut self, bytes: &[u8]) {
+        match self {
+            State::Blake3(hasher) => {
+                hasher.update(bytes);
+            }
+            State::Sha256(hasher) => hasher.update(bytes),
+            State::Md5(hasher) => hasher.update(bytes),
+        }
+    }
+
+    fn finish(self) -> String {
+        match self {
+            State::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            State::Sha256(hasher) => to_hex(&hasher.finalize()),
+            State::Md5(hasher) => to_hex(&hasher.finalize()),
+        }
+    }
+}
+
+/// Check whether two streams yield the same bytes, reading both in chunks
+/// (a small one first) and stopping at the first chunk that differs
+pub fn readers_equal(mut a: impl Read, mut b: impl Read) -> io::Result<bool> {
+    let mut buffer_a = vec![0u8; HASH_BUFFER_SIZE];
+    let mut buffer_b = vec![0u8; HASH_BUFFER_SIZE];
+    let mut chunk = FIRST_COMPARE_SIZE;
+
+    loop {
+        let read_a = fill(&mut a, &mut buffer_a[..chunk])?;
+        let read_b = fill(&mut b, &mut buffer_b[..chunk])?;
+        if buffer_a[..read_a] != buffer_b[..read_b] {
+            return Ok(false);
+        }
+        if read_a == 0 {
+            return Ok(true);
+        }
+        chunk = HASH_BUFFER_SIZE;
+    }
+}
+
+/// Read until `buffer` is full or the stream ends, returning the bytes read
+fn fill(reader: &mut impl Read, buffer: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => filled += read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(filled)
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_hash_file() {
+        let dir = TempDir::new("hash");
+        let path = dir.join("abc");
+        std::fs::write(&path, b"abc").unwrap();
+        let hash = Hasher::Sha256.hash_file(&path).unwrap();
+        let md5 = Hasher::Md5.hash_file(&path).unwrap();
+        let blake3 = Hasher::Blake3.hash_file(&path).unwrap();
+
+        assert_eq!(
+            hash,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(Hasher::Sha256.hash_bytes(b"abc"), hash);
+        assert_eq!(md5, "900150983cd24fb0d6963f7d28e17f72");
+        assert_eq!(blake3, "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85");
+        assert_eq!(Hasher::Blake3.hash_bytes(b"abc"), blake3);
+    }
+
+    #[test]
+    fn test_readers_equal() {
+        assert!(readers_equal(&b"abc"[..], &b"abc"[..]).unwrap());
+        assert!(!readers_equal(&b"abc"[..], &b"abd"[..]).unwrap());
+        assert!(!readers_equal(&b"abc"[..], &b"abcd"[..]).unwrap());
+        // Short reads on one side don't matter
+        assert!(readers_equal(io::Read::chain(&b"a"[..], &b"bc"[..]), &b"abc"[..]).unwrap());
+
+        // Past the first chunk, into the full-size ones
+        let long = vec![7u8; FIRST_COMPARE_SIZE + HASH_BUFFER_SIZE + 1];
+        let mut changed = long.clone();
+        assert!(readers_equal(&long[..], &changed[..]).unwrap());
+        *changed.last_mut().unwrap() = 8;
+        assert!(!readers_equal(&long[..], &changed[..]).unwrap());
+    }
+}
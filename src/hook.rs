@@ -0,0 +1,126 @@
+use anyhow::{bail, Result};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+
+/// An external command run on each archived file (`--exec`). Commands run in
+/// the background, with at most `max_running` at a time; failures are logged
+/// and counted but never stop the run
+#[derive(Debug)]
+pub struct ExecHook {
+    program: String,
+    args: Vec<String>,
+    max_running: usize,
+    running: Mutex<VecDeque<(Child, PathBuf)>>,
+    failures: Mutex<usize>,
+}
+
+impl ExecHook {
+    /// Split `command` on whitespace into a program and its arguments. `{}`
+    /// in an argument is replaced by the destination path; if no argument
+    /// contains it, the path is appended as the last argument
+    pub fn new(command: &str, max_running: usize) -> Result<Self> {
+        let mut words = command.split_whitespace().map(str::to_string);
+        let Some(program) = words.next() else {
+            bail!("--exec expects a command");
+        };
+
+        Ok(ExecHook {
+            program,
+            args: words.collect(),
+            max_running: max_running.max(1),
+            running: Mutex::new(VecDeque::new()),
+            failures: Mutex::new(0),
+        })
+    }
+
+    /// The arguments for one destination path
+    fn args_for(&self, path: &Path) -> Vec<String> {
+        let path = path.to_string_lossy();
+        let mut args: Vec<String> = self.args.iter().map(|arg| arg.replace("{}", &path)).collect();
+        if !self.args.iter().any(|arg| arg.contains("{}")) {
+            args.push(path.into_owned());
+        }
+        args
+    }
+
+    /// Start the command for a newly archived file, first waiting for the
+    /// oldest running command if the concurrency limit is reached
+    pub fn run(&self, path: &Path) {
+        let mut running = self.running.lock().unwrap();
+        while running.len() >= self.max_running {
+            let (child, path) = running.pop_front().unwrap();
+            self.finish(child, &path);
+        }
+
+        match Command::new(&self.program)
+            .args(self.args_for(path))
+            .stdin(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => running.push_back((child, path.to_path_buf())),
+            Err(e) => {
+                eprintln!("Warning: --exec failed to start for {}: {}", path.display(), e);
+                *self.failures.lock().unwrap() += 1;
+            }
+        }
+    }
+
+    /// Wait for every command still running, returning how many failed in total
+    pub fn wait_all(&self) -> usize {
+        let mut running = self.running.lock().unwrap();
+        while let Some((child, path)) = running.pop_front() {
+            self.finish(child, &path);
+        }
+        *self.failures.lock().unwrap()
+    }
+
+    fn finish(&self, mut child: Child, path: &Path) {
+        let failed = match child.wait() {
+            Ok(status) if status.success() => false,
+            Ok(status) => {
+                eprintln!("Warning: --exec for {} exited with {}", path.display(), status);
+                true
+            }
+            Err(e) => {
+                eprintln!("Warning: --exec for {} could not be waited on: {}", path.display(), e);
+                true
+            }
+        };
+        if failed {
+            *self.failures.lock().unwrap() += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exec_hook_substitutes_path() {
+        let hook = ExecHook::new("index --file={} --quiet", 2).unwrap();
+        assert_eq!(
+            hook.args_for(Path::new("/lib/a b.JPG")),
+            vec!["--file=/lib/a b.JPG", "--quiet"]
+        );
+
+        let hook = ExecHook::new("touch", 2).unwrap();
+        assert_eq!(hook.args_for(Path::new("/lib/a.JPG")), vec!["/lib/a.JPG"]);
+
+        assert!(ExecHook::new("   ", 2).is_err());
+    }
+
+    #[test]
+    fn test_exec_hook_counts_failures() {
+        let hook = ExecHook::new("false", 1).unwrap();
+        hook.run(Path::new("one"));
+        hook.run(Path::new("two"));
+        assert_eq!(hook.wait_all(), 2);
+
+        let hook = ExecHook::new("true", 1).unwrap();
+        hook.run(Path::new("one"));
+        assert_eq!(hook.wait_all(), 0);
+    }
+}
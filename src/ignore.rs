@@ -0,0 +1,72 @@
+use anyhow::{anyhow, Result};
+use glob::{MatchOptions, Pattern};
+use std::path::Path;
+
+/// File names that are system metadata rather than media, skipped wherever
+/// they turn up: macOS AppleDouble and Finder files, and Windows thumbnail
+/// caches and folder settings. Apple's `.AAE` edit sidecars aren't on it, as
+/// they hold the only copy of a photo's edits (`--ignore-name '*.AAE'`)
+pub const DEFAULT_IGNORED_NAMES: &[&str] = &["._*", ".DS_Store", "Thumbs.db", "ehthumbs.db", "desktop.ini"];
+
+/// Names are compared ignoring case, since SMB and Windows round-trips
+/// produce `.DS_STORE` and `THUMBS.DB`
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: false,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Deny-list of file name patterns that are never archived
+#[derive(Debug)]
+pub struct IgnoreList {
+    patterns: Vec<Pattern>,
+}
+
+impl IgnoreList {
+    /// The default names plus `extra` glob patterns (`*`, `?`, `[...]`)
+    pub fn new(extra: &[String]) -> Result<Self> {
+        let patterns = DEFAULT_IGNORED_NAMES
+            .iter()
+            .copied()
+            .chain(extra.iter().map(String::as_str))
+            .map(|name| Pattern::new(name).map_err(|e| anyhow!("Invalid --ignore-name pattern '{}': {}", name, e)))
+            .collect::<Result<_>>()?;
+        Ok(IgnoreList { patterns })
+    }
+
+    /// Check if a file's name is on the list
+    pub fn matches(&self, path: &Path) -> bool {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            return false;
+        };
+        self.patterns.iter().any(|pattern| pattern.matches_with(name, MATCH_OPTIONS))
+    }
+}
+
+impl Default for IgnoreList {
+    fn default() -> Self {
+        IgnoreList::new(&[]).expect("default ignored names are valid patterns")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ignore_list() {
+        let ignored = IgnoreList::default();
+        for name in ["._IMG_0001.JPG", ".DS_Store", ".DS_STORE", "Thumbs.db", "THUMBS.DB", "Desktop.ini"] {
+            assert!(ignored.matches(&Path::new("/card/DCIM").join(name)), "{}", name);
+        }
+        for name in ["IMG_0001.JPG", "IMG_0001.AAE", "DS_Store.jpg", "desktop.ini.jpg"] {
+            assert!(!ignored.matches(&Path::new("/card/DCIM").join(name)), "{}", name);
+        }
+
+        let ignored = IgnoreList::new(&["*.tmp".to_string(), "~$*".to_string(), "*.AAE".to_string()]).unwrap();
+        assert!(ignored.matches(Path::new("/card/IMG_0001.aae")));
+        assert!(ignored.matches(Path::new("/card/upload.TMP")));
+        assert!(ignored.matches(Path::new("/card/~$notes.docx")));
+        assert!(IgnoreList::new(&["[".to_string()]).is_err());
+    }
+}
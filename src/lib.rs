@@ -1,5 +1,29 @@
+pub mod archive;
 pub mod args;
+pub mod config;
+pub mod copy;
+pub mod decode;
+pub mod events;
 pub mod failed;
+pub mod fileops;
 pub mod filename;
+pub mod hash;
+pub mod hook;
+pub mod ignore;
+pub mod library;
 pub mod metadata;
+pub mod mtp;
+pub mod organize;
+pub mod orient;
+pub mod pairs;
+pub mod phash;
 pub mod processor;
+pub mod provenance;
+pub mod resume;
+pub mod settings;
+pub mod sidecar;
+pub mod stay_open;
+pub mod style;
+#[doc(hidden)]
+pub mod test_util;
+pub mod throttle;
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::fileops::FileOps;
+use crate::hash::{readers_equal, Hasher};
+
+/// Content index of every file in the library, for --dedupe-scope global.
+/// Files are grouped by size, and a library file is only hashed once a
+/// source of the same size turns up, so indexing costs one directory walk
+#[derive(Debug, Default)]
+pub struct LibraryIndex {
+    by_len: HashMap<u64, Vec<IndexedFile>>,
+    /// Size of each indexed file, for finding its entry in `by_len`
+    sizes: HashMap<PathBuf, u64>,
+    hasher: Hasher,
+}
+
+#[derive(Debug)]
+struct IndexedFile {
+    path: PathBuf,
+    /// Content hash, computed on first comparison
+    hash: Option<String>,
+}
+
+impl LibraryIndex {
+    /// Index the regular files under `root`, leaving out the `skip` folders
+    /// (such as Failed Cases, whose entries point back at sources). Files
+    /// are compared by their `hasher` digests
+    pub fn scan(root: &Path, skip: &[PathBuf], hasher: Hasher) -> Self {
+        let mut index = LibraryIndex {
+            hasher,
+            ..LibraryIndex::default()
+        };
+        let files = WalkDir::new(root)
+            .into_iter()
+            .filter_entry(|entry| !skip.iter().any(|dir| entry.path() == dir))
+            .flatten()
+            .filter(|entry| entry.file_type().is_file());
+        for entry in files {
+            if let Ok(metadata) = entry.metadata() {
+                index.insert(entry.into_path(), metadata.len(), None);
+            }
+        }
+        index
+    }
+
+    /// Add a file, or update its entry if it is already indexed
+    pub fn insert(&mut self, path: PathBuf, len: u64, hash: Option<String>) {
+        self.remove(&path);
+        self.sizes.insert(path.clone(), len);
+        self.by_len.entry(len).or_default().push(IndexedFile { path, hash });
+    }
+
+    /// Point the entry for `from` at `to`, after the file was renamed
+    pub fn rename(&mut self, from: &Path, to: &Path) {
+        let Some(len) = self.sizes.get(from).copied() else {
+            return;
+        };
+        self.remove(to);
+        self.sizes.remove(from);
+        self.sizes.insert(to.to_path_buf(), len);
+        for file in self.by_len.get_mut(&len).into_iter().flatten().filter(|file| file.path == from) {
+            file.path = to.to_path_buf();
+        }
+    }
+
+    /// Drop the entry for `path`, if it is indexed
+    fn remove(&mut self, path: &Path) {
+        let Some(len) = self.sizes.remove(path) else {
+            return;
+        };
+        if let Some(files) = self.by_len.get_mut(&len) {
+            files.retain(|file| file.path != path);
+        }
+    }
+
+    /// Find an indexed file with the same bytes as `source` (of length `len`
+    /// and hash `hash`), comparing byte for byte once the hashes match.
+    /// Unreadable library files are skipped
+    pub fn find(
+        &mut self,
+        file_ops: &dyn FileOps,
+        source: &Path,
+        len: u64,
+        hash: &str,
+    ) -> io::Result<Option<PathBuf>> {
+        let Some(files) = self.by_len.get_mut(&len) else {
+            return Ok(None);
+        };
+
+        for file in files.iter_mut().filter(|file| file.path != source) {
+            if file.hash.is_none() {
+                let hasher = self.hasher;
+                file.hash = file_ops.open(&file.path).and_then(|reader| hasher.hash_reader(reader)).ok();
+            }
+            if file.hash.as_deref() != Some(hash) {
+                continue;
+            }
+            let Ok(existing) = file_ops.open(&file.path) else {
+                continue;
+            };
+            if readers_equal(file_ops.open(source)?, existing)? {
+                return Ok(Some(file.path.clone()));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Groups of byte-identical files under `root` (leaving out the `skip`
+/// folders), each sorted by path. Only files sharing a size are hashed, and
+/// files sharing a hash are compared byte for byte with the group's first
+pub fn find_duplicate_groups(root: &Path, skip: &[PathBuf], hasher: Hasher) -> Vec<Vec<PathBuf>> {
+    let mut by_len: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    let files = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| !skip.iter().any(|dir| entry.path() == dir))
+        .flatten()
+        .filter(|entry| entry.file_type().is_file());
+    for entry in files {
+        match entry.metadata() {
+            Ok(metadata) if metadata.len() > 0 => by_len.entry(metadata.len()).or_default().push(entry.into_path()),
+            _ => {}
+        }
+    }
+
+    let mut groups = Vec::new();
+    for paths in by_len.into_values().filter(|paths| paths.len() > 1) {
+        let mut by_hash: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for path in paths {
+            match hasher.hash_file(&path) {
+                Ok(hash) => by_hash.entry(hash).or_default().push(path),
+                Err(e) => eprintln!("Warning: {:#}", e),
+            }
+        }
+        for mut paths in by_hash.into_values().filter(|paths| paths.len() > 1) {
+            paths.sort();
+            let first = paths[0].clone();
+            paths.retain(|path| {
+                *path == first
+                    || File::open(&first)
+                        .and_then(|a| readers_equal(a, File::open(path)?))
+                        .unwrap_or(false)
+            });
+            if paths.len() > 1 {
+                groups.push(paths);
+            }
+        }
+    }
+    groups.sort();
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fileops::RealFs;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_library_index() {
+        let root = TempDir::new("library");
+        let library = root.join("Library");
+        let failed = library.join("Failed Cases");
+        fs::create_dir_all(library.join("2021")).unwrap();
+        fs::create_dir_all(&failed).unwrap();
+        fs::write(library.join("2021/2021-06-01 first.jpg"), b"same bytes").unwrap();
+        fs::write(library.join("other.jpg"), b"diff bytes").unwrap();
+        fs::write(failed.join("IMG_0001.JPG"), b"fail bytes").unwrap();
+
+        let file_ops = RealFs::new(None, None);
+        let mut index = LibraryIndex::scan(&library, std::slice::from_ref(&failed), Hasher::Sha256);
+        let find = |index: &mut LibraryIndex, content: &[u8]| {
+            let source = root.join("source.jpg");
+            fs::write(&source, content).unwrap();
+            let hash = Hasher::Sha256.hash_file(&source).unwrap();
+            index.find(&file_ops, &source, content.len() as u64, &hash).unwrap()
+        };
+
+        // Same size as both library files, but only one has the same bytes
+        assert_eq!(find(&mut index, b"same bytes"), Some(library.join("2021/2021-06-01 first.jpg")));
+        assert_eq!(find(&mut index, b"fail bytes"), None);
+
+        let archived = library.join("2022-01-01 new.jpg");
+        fs::write(&archived, b"new").unwrap();
+        assert_eq!(find(&mut index, b"new"), None);
+        index.insert(archived.clone(), 3, None);
+        assert_eq!(find(&mut index, b"new"), Some(archived.clone()));
+
+        // Re-inserting a file at a new size moves it out of its old size's group
+        fs::write(&archived, b"newer").unwrap();
+        index.insert(archived.clone(), 5, None);
+        assert_eq!(find(&mut index, b"new"), None);
+        assert_eq!(find(&mut index, b"newer"), Some(archived.clone()));
+        let renamed = library.join("2022-01-01 renamed.jpg");
+        fs::rename(&archived, &renamed).unwrap();
+        index.rename(&archived, &renamed);
+        assert_eq!(find(&mut index, b"newer"), Some(renamed.clone()));
+        fs::rename(&renamed, &archived).unwrap();
+
+        fs::write(library.join("2021/copy.jpg"), b"same bytes").unwrap();
+        fs::write(failed.join("new.jpg"), b"new").unwrap();
+        assert_eq!(
+            find_duplicate_groups(&library, std::slice::from_ref(&failed), Hasher::Blake3),
+            vec![vec![library.join("2021/2021-06-01 first.jpg"), library.join("2021/copy.jpg")]]
+        );
+    }
+}
@@ -1,22 +1,52 @@
-use collect_media::args::Args;
-use collect_media::processor::Processor;
+use anyhow::Context;
+use collect_media::args::{Args, Command};
+use collect_media::metadata::inspect_file;
+use collect_media::processor::{run_collision_selftest, Processor, RunOutcome};
+use collect_media::settings::SummaryFormat;
 
 fn main() {
-    if let Err(e) = run() {
-        eprintln!("Error: {:#}", e);
-        std::process::exit(1);
+    match run() {
+        Ok(outcome) => std::process::exit(outcome.exit_code()),
+        Err(e) => {
+            eprintln!("Error: {:#}", e);
+            std::process::exit(1);
+        }
     }
 }
 
-fn run() -> anyhow::Result<()> {
+fn run() -> anyhow::Result<RunOutcome> {
     // Parse command line arguments
-    let args = Args::parse()?;
+    match Command::parse()? {
+        Command::Archive(args) => archive(*args),
+        Command::Inspect { file, extract } => {
+            let report = inspect_file(&file, &extract)?;
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            Ok(RunOutcome::Success)
+        }
+        Command::SelfTest { count } => run_collision_selftest(count).map(|_| RunOutcome::Success),
+    }
+}
+
+fn archive(args: Args) -> anyhow::Result<RunOutcome> {
+    collect_media::style::set_color(args.settings.color);
+
+    // With a JSON summary, stdout carries nothing else
+    let summary_out = match args.settings.summary_format {
+        SummaryFormat::Json => Some(collect_media::style::divert_stdout().context("Failed to redirect stdout")?),
+        _ => None,
+    };
 
     // Create processor
-    let mut processor = Processor::new(args.output_dir)?;
+    let mut processor = Processor::new(args.output_dir, args.settings)?;
+    if let Some(out) = summary_out {
+        processor.set_summary_output(out);
+    }
 
-    // Process all input directories
-    processor.process_directories(&args.input_dirs)?;
+    // Process the listed files, or all input directories
+    match &args.from_file {
+        Some(list_path) => processor.process_file_list(list_path)?,
+        None => processor.process_directories(&args.input_dirs)?,
+    }
 
-    Ok(())
+    Ok(processor.outcome())
 }
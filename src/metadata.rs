@@ -1,10 +1,12 @@
 use anyhow::{anyhow, Context, Result};
-use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono::{DateTime, Duration, NaiveDateTime, TimeZone, Timelike, Utc};
 use exiftool::ExifTool;
-use serde_json::Value;
+use serde_json::{json, Value};
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 
+use crate::stay_open;
+
 /// Order of preference for creation date extraction
 const CREATION_DATE_TAGS: &[&str] = &[
     "DateTimeOriginal",
@@ -33,6 +35,14 @@ const MODIFY_DATE_TAGS: &[&str] = &[
     "FileModifyDate",
 ];
 
+/// EXIF tags holding the fractional seconds of a date tag, which cameras
+/// store separately from the whole-second date
+const SUBSEC_TAGS: &[(&str, &str)] = &[
+    ("DateTimeOriginal", "SubSecTimeOriginal"),
+    ("CreateDate", "SubSecTimeDigitized"),
+    ("ModifyDate", "SubSecTime"),
+];
+
 /// Epoch timestamps to reject (as Unix timestamps)
 const REJECTED_EPOCHS: &[i64] = &[
     0,                    // Zero/Unix epoch
@@ -44,94 +54,465 @@ const REJECTED_EPOCHS: &[i64] = &[
 
 const YEAR_2010: i64 = 1262304000; // 2010-01-01 00:00:00 UTC
 
-#[derive(Debug, Clone)]
+/// Which dates count as real, and which are real but worth a warning
+///
+/// The defaults reject the well-known zero epochs and anything in the future,
+/// and flag dates before 2010. Library consumers can build their own and set
+/// it as [`ExtractOptions::date_policy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatePolicy {
+    /// Unix timestamps (zero points of camera and OS clocks) whose day is
+    /// treated as "no date set"
+    pub rejected_epochs: Vec<i64>,
+    /// Dates before this are accepted but warned about, and are what
+    /// --review-suspicious asks about
+    pub suspicious_before: DateTime<Utc>,
+    /// Accept dates up to this far past the current time (for fast device clocks)
+    pub future_tolerance: Duration,
+}
+
+impl Default for DatePolicy {
+    fn default() -> Self {
+        DatePolicy {
+            rejected_epochs: REJECTED_EPOCHS.to_vec(),
+            suspicious_before: DateTime::from_timestamp(YEAR_2010, 0).unwrap(),
+            future_tolerance: Duration::zero(),
+        }
+    }
+}
+
+impl DatePolicy {
+    /// Whether a date parsed from a tag should be used at all
+    pub fn is_valid(&self, date: &DateTime<Utc>) -> bool {
+        // Check if date is in the future (beyond the --future-tolerance allowance);
+        // a tolerance reaching past the last representable date has no limit
+        if Utc::now().checked_add_signed(self.future_tolerance).is_some_and(|latest| *date > latest) {
+            return false;
+        }
+
+        // Check if date is within a day of a rejected epoch
+        let timestamp = date.timestamp();
+        !self.rejected_epochs.iter().any(|&epoch| (timestamp - epoch).abs() < 86400)
+    }
+
+    /// Check if a date is before the plausible-date threshold
+    pub fn is_suspicious(&self, date: &DateTime<Utc>) -> bool {
+        *date < self.suspicious_before
+    }
+
+    fn warn_if_suspicious(&self, file_path: &Path, which: &str, date: &DateTime<Utc>) {
+        if self.is_suspicious(date) {
+            eprintln!(
+                "Warning: File {} has {} date before {}: {}",
+                file_path.display(),
+                which,
+                self.suspicious_before.format("%Y-%m-%d"),
+                date
+            );
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MediaDates {
     pub creation_date: DateTime<Utc>,
     pub modify_date: DateTime<Utc>,
 }
 
+/// Which of a file's two dates leads its filename and picks its date folder
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PrimaryDate {
+    #[default]
+    Creation,
+    Modify,
+}
+
+impl PrimaryDate {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "creation" => Ok(PrimaryDate::Creation),
+            "modify" => Ok(PrimaryDate::Modify),
+            other => Err(anyhow!("--primary-date expects creation or modify, got '{}'", other)),
+        }
+    }
+}
+
+impl MediaDates {
+    /// The (primary, secondary) dates under the given choice
+    pub fn ordered(&self, primary: PrimaryDate) -> (&DateTime<Utc>, &DateTime<Utc>) {
+        match primary {
+            PrimaryDate::Creation => (&self.creation_date, &self.modify_date),
+            PrimaryDate::Modify => (&self.modify_date, &self.creation_date),
+        }
+    }
+}
+
+/// What to do when a file's creation date is later than its modification date
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateOrder {
+    /// Keep the dates as found and print a warning
+    #[default]
+    Warn,
+    /// Swap the two dates so creation comes first
+    Swap,
+    /// Treat the file as failed
+    Fail,
+}
+
+/// How to pick one date out of a tag holding several (Track/Media dates)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ArrayDate {
+    /// The most common date, the earliest of those on a tie
+    #[default]
+    ModeEarliest,
+    /// The most common date, the latest of those on a tie
+    ModeLatest,
+    /// The earliest date
+    Earliest,
+    /// The latest date
+    Latest,
+}
+
+impl ArrayDate {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "mode-earliest" => Ok(ArrayDate::ModeEarliest),
+            "mode-latest" => Ok(ArrayDate::ModeLatest),
+            "earliest" => Ok(ArrayDate::Earliest),
+            "latest" => Ok(ArrayDate::Latest),
+            other => Err(anyhow!(
+                "--array-date expects mode-earliest, mode-latest, earliest, or latest, got '{}'",
+                other
+            )),
+        }
+    }
+}
+
+/// Options controlling how dates are extracted from metadata
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    pub date_order: DateOrder,
+    /// Use the one date that resolved for both creation and modification
+    pub single_date: bool,
+    /// Which dates to reject or warn about
+    pub date_policy: DatePolicy,
+    /// How to choose among the dates in an array-valued tag
+    pub array_date: ArrayDate,
+    /// Extra exiftool arguments (--exiftool-arg), added after `-G`/`-ee`
+    pub exiftool_args: Vec<String>,
+    /// Give up on a batch exiftool hasn't finished reading in this long
+    /// (--exif-timeout)
+    pub timeout: Option<std::time::Duration>,
+    /// The exiftool executable workers run, instead of `exiftool` on PATH
+    pub exiftool_path: Option<PathBuf>,
+}
+
+/// exiftool options that would change the JSON output this crate parses, the
+/// stay-open protocol, or write to files, so can't be passed through
+const RESERVED_EXIFTOOL_ARGS: &[&str] = &[
+    "-j", "-json", "-xml", "-xmlformat", "-csv", "-php", "-h", "-htmlformat", "-t", "-tab",
+    "-s", "-short", "-s2", "-s3", "-g", "-groupheadings", "-d", "-dateformat", "-@", "-stay_open",
+    "-execute", "-common_args", "-config", "-o", "-out", "-w", "-textout", "-tagsfromfile",
+    "-overwrite_original", "-overwrite_original_in_place", "-delete_original", "-restore_original",
+];
+
+/// exiftool options that take the next argument as their value
+const EXIFTOOL_VALUE_ARGS: &[&str] = &["-api", "-charset", "-fileorder", "-if", "-lang", "-userparam", "-x", "-exclude"];
+
+/// Check --exiftool-arg values: each must be an option (not a file path), not
+/// one of the options this crate relies on, and not a tag assignment
+pub fn validate_exiftool_args(args: &[String]) -> Result<()> {
+    let mut expects_value = false;
+    for arg in args {
+        if std::mem::take(&mut expects_value) {
+            continue;
+        }
+        let Some(name) = arg.strip_prefix('-') else {
+            return Err(anyhow!(
+                "--exiftool-arg '{}' is not an option; file paths can't be passed to exiftool",
+                arg
+            ));
+        };
+        let option = format!("-{}", name.trim_start_matches('-')).to_lowercase();
+        let base = option.trim_end_matches(|c: char| c.is_ascii_digit() || c == ':');
+        if option == "-config" {
+            return Err(anyhow!(
+                "--exiftool-arg -config can't be used with exiftool's stay-open mode; \
+                 put the definitions in ~/.ExifTool_config (or $EXIFTOOL_HOME/.ExifTool_config) instead"
+            ));
+        }
+        // exiftool options ignore case, except -X (XML output) vs -x (exclude a tag)
+        if arg == "-X" || RESERVED_EXIFTOOL_ARGS.contains(&option.as_str()) || RESERVED_EXIFTOOL_ARGS.contains(&base) {
+            return Err(anyhow!("--exiftool-arg {} would change exiftool output this crate relies on", arg));
+        }
+        if option.contains('=') || option.contains('<') {
+            return Err(anyhow!("--exiftool-arg {} would write tags", arg));
+        }
+        expects_value = EXIFTOOL_VALUE_ARGS.contains(&option.as_str());
+    }
+    if expects_value {
+        return Err(anyhow!("--exiftool-arg {} expects a value in another --exiftool-arg", args[args.len() - 1]));
+    }
+    Ok(())
+}
+
+/// Error for a file exiftool read without trouble but that has no date tags
+/// at all, as opposed to one whose dates were present but unusable
+#[derive(Debug)]
+pub struct NoDateTags {
+    /// How many other tags exiftool reported (0 when only SourceFile came back)
+    pub tag_count: usize,
+}
+
+impl std::fmt::Display for NoDateTags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "exiftool succeeded but no date tags ({} other tags)", self.tag_count)
+    }
+}
+
+impl std::error::Error for NoDateTags {}
+
+/// Check whether any tag this crate reads dates from is present, valid or not
+fn has_date_tags(metadata: &HashMap<String, Value>) -> bool {
+    metadata.keys().any(|key| {
+        let tag = key.rsplit(':').next().unwrap_or(key);
+        CREATION_DATE_TAGS.contains(&tag) || MODIFY_DATE_TAGS.contains(&tag)
+    })
+}
+
+/// Dates plus the file type details exiftool reported for a file
+#[derive(Debug, Clone)]
+pub struct MediaInfo {
+    pub dates: MediaDates,
+    /// exiftool's detected FileType (e.g. "JPEG", "HEIC", "MOV")
+    pub file_type: Option<String>,
+    /// exiftool's detected MIMEType (e.g. "image/jpeg")
+    pub mime_type: Option<String>,
+    /// GPS position as (latitude, longitude) in signed decimal degrees
+    pub gps: Option<(f64, f64)>,
+    /// exiftool's printed EXIF Orientation (e.g. "Rotate 90 CW")
+    pub orientation: Option<String>,
+    /// exiftool's usual extension for the detected type (e.g. "heic", "mp4")
+    pub file_type_extension: Option<String>,
+    /// Which shot the file is, for --dedupe-by identity
+    pub identity: Option<ShotIdentity>,
+    /// The tags the creation and modification dates were read from (None
+    /// for a date filled in from the other one)
+    pub creation_tag: Option<String>,
+    pub modify_tag: Option<String>,
+}
+
+/// What identifies a shot across exports whose bytes differ (an edited copy
+/// of the same RAW): when it was taken, on which camera body, at what size
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ShotIdentity {
+    pub date_time_original: String,
+    pub serial_number: String,
+    pub width: String,
+    pub height: String,
+}
+
+/// Broad media category used for --split-media
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Image,
+    Video,
+    Other,
+}
+
+impl MediaKind {
+    /// Key used to override this category's folder name
+    pub fn key(&self) -> &'static str {
+        match self {
+            MediaKind::Image => "image",
+            MediaKind::Video => "video",
+            MediaKind::Other => "other",
+        }
+    }
+
+    /// Default folder name for this category
+    pub fn default_folder(&self) -> &'static str {
+        match self {
+            MediaKind::Image => "Photos",
+            MediaKind::Video => "Videos",
+            MediaKind::Other => "Other",
+        }
+    }
+}
+
+impl MediaInfo {
+    /// Categorize the file from its MIME type
+    pub fn kind(&self) -> MediaKind {
+        match self.mime_type.as_deref() {
+            Some(mime) if mime.starts_with("image/") => MediaKind::Image,
+            Some(mime) if mime.starts_with("video/") => MediaKind::Video,
+            _ => MediaKind::Other,
+        }
+    }
+
+    /// Pick the --split-media subfolder, preferring a FileType override,
+    /// then a category override, then the category's default name
+    pub fn media_folder(&self, overrides: &HashMap<String, String>) -> String {
+        let kind = self.kind();
+        self.file_type
+            .as_ref()
+            .and_then(|file_type| overrides.get(&file_type.to_uppercase()))
+            .or_else(|| overrides.get(kind.key()))
+            .cloned()
+            .unwrap_or_else(|| kind.default_folder().to_string())
+    }
+}
+
 /// Extract metadata from a file using exiftool
-pub fn extract_dates(file_path: &Path) -> Result<MediaDates> {
+pub fn extract_dates(file_path: &Path, options: &ExtractOptions) -> Result<MediaDates> {
     // First try fast extraction
-    let metadata = extract_with_exiftool(file_path, false)?;
+    let metadata = extract_with_exiftool(file_path, false, &options.exiftool_args)?;
 
     // Extract dates
-    let creation_date = extract_creation_date(&metadata)?;
-    let modify_date = extract_modify_date(&metadata)?;
+    let creation_date = extract_creation_date(&metadata, options)?;
+    let modify_date = extract_modify_date(&metadata, options)?;
 
     // If we found valid dates, return them
     if let (Some(creation), Some(modify)) = (creation_date, modify_date) {
-        // Warn if dates are before 2010
-        if creation.timestamp() < YEAR_2010 {
-            eprintln!(
-                "Warning: File {} has creation date before 2010: {}",
-                file_path.display(),
-                creation
-            );
-        }
-        if modify.timestamp() < YEAR_2010 {
-            eprintln!(
-                "Warning: File {} has modification date before 2010: {}",
-                file_path.display(),
-                modify
-            );
-        }
+        options.date_policy.warn_if_suspicious(file_path, "creation", &creation);
+        options.date_policy.warn_if_suspicious(file_path, "modification", &modify);
 
-        return Ok(MediaDates {
-            creation_date: creation,
-            modify_date: modify,
-        });
+        return check_date_order(file_path, creation, modify, options.date_order);
     }
 
     // Fallback to ExtractEmbedded
-    let metadata = extract_with_exiftool(file_path, true)?;
-    let creation_date = extract_creation_date(&metadata)?
-        .ok_or_else(|| anyhow!("No valid creation date found"))?;
-    let modify_date = extract_modify_date(&metadata)?
-        .ok_or_else(|| anyhow!("No valid modification date found"))?;
-
-    // Warn if dates are before 2010
-    if creation_date.timestamp() < YEAR_2010 {
-        eprintln!(
-            "Warning: File {} has creation date before 2010: {}",
-            file_path.display(),
-            creation_date
-        );
-    }
-    if modify_date.timestamp() < YEAR_2010 {
-        eprintln!(
-            "Warning: File {} has modification date before 2010: {}",
-            file_path.display(),
-            modify_date
-        );
-    }
+    let metadata = extract_with_exiftool(file_path, true, &options.exiftool_args)?;
+    extract_dates_from_metadata(file_path, &metadata, options)
+}
 
-    Ok(MediaDates {
-        creation_date,
-        modify_date,
-    })
+/// Run this crate's date extraction on a single file and describe the result
+/// as JSON: the tag each raw date came from, and the dates that would be used
+/// after the --single-date and --date-order policies are applied
+pub fn inspect_file(file_path: &Path, options: &ExtractOptions) -> Result<Value> {
+    let metadata = extract_with_exiftool(file_path, true, &options.exiftool_args)?;
+    let creation = extract_date_by_priority(&metadata, CREATION_DATE_TAGS, options)?;
+    let modify = extract_date_by_priority(&metadata, MODIFY_DATE_TAGS, options)?;
+
+    let candidate = |found: &Option<(String, DateTime<Utc>)>| match found {
+        Some((tag, date)) => json!({ "tag": tag, "date": date.to_rfc3339() }),
+        None => Value::Null,
+    };
+
+    let resolved = match extract_dates_from_metadata(file_path, &metadata, options) {
+        Ok(dates) => json!({
+            "creation_date": dates.creation_date.to_rfc3339(),
+            "modify_date": dates.modify_date.to_rfc3339(),
+        }),
+        Err(e) => json!({ "error": format!("{:#}", e) }),
+    };
+
+    Ok(json!({
+        "file": file_path.display().to_string(),
+        "file_type": find_string_tag(&metadata, "FileType"),
+        "file_type_extension": find_string_tag(&metadata, "FileTypeExtension"),
+        "mime_type": find_string_tag(&metadata, "MIMEType"),
+        "gps": extract_gps(&metadata).map(|(lat, lon)| json!([lat, lon])),
+        "identity": extract_identity(&metadata).map(|identity| json!({
+            "date_time_original": identity.date_time_original,
+            "serial_number": identity.serial_number,
+            "width": identity.width,
+            "height": identity.height,
+        })),
+        "creation": candidate(&creation),
+        "modify": candidate(&modify),
+        "resolved": resolved,
+    }))
 }
 
 /// Extract metadata from multiple files in batch using exiftool
 /// Returns a HashMap mapping file paths to their extracted dates or errors
 /// Uses adaptive batch sizing: if a batch fails, splits it in half and retries
-pub fn extract_dates_batch(exiftool: &mut ExifTool, file_paths: &[PathBuf]) -> HashMap<PathBuf, Result<MediaDates>> {
-    extract_dates_batch_adaptive(exiftool, file_paths)
+pub fn extract_dates_batch(
+    reader: &mut MetadataReader,
+    file_paths: &[PathBuf],
+    options: &ExtractOptions,
+) -> HashMap<PathBuf, Result<MediaInfo>> {
+    extract_dates_batch_adaptive(reader, file_paths, options)
+}
+
+/// A worker's exiftool process. With a timeout (--exif-timeout) each batch is
+/// read on a helper thread and given the timeout once per file; a batch that
+/// takes longer has its process killed and replaced with a fresh one
+pub struct MetadataReader {
+    /// Left empty if restarting after a timeout failed
+    exiftool: Option<(stay_open::Process, stay_open::Session)>,
+    executable: PathBuf,
+    timeout: Option<std::time::Duration>,
+}
+
+impl MetadataReader {
+    pub fn new(options: &ExtractOptions) -> Result<Self> {
+        let executable = options.exiftool_path.clone().unwrap_or_else(|| PathBuf::from("exiftool"));
+        Ok(MetadataReader {
+            exiftool: Some(stay_open::start(&executable)?),
+            executable,
+            timeout: options.timeout,
+        })
+    }
+
+    /// Read one batch, failing the whole batch on a timeout
+    fn read_batch(
+        &mut self,
+        file_paths: &[PathBuf],
+        options: &ExtractOptions,
+    ) -> Result<HashMap<PathBuf, Result<MediaInfo>>> {
+        let (process, mut session) = match self.exiftool.take() {
+            Some(exiftool) => exiftool,
+            None => stay_open::start(&self.executable).context("Failed to restart exiftool")?,
+        };
+
+        let Some(timeout) = self.timeout else {
+            let result = try_extract_batch(&mut session, file_paths, options);
+            self.exiftool = Some((process, session));
+            return result;
+        };
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (paths, batch_options) = (file_paths.to_vec(), options.clone());
+        std::thread::spawn(move || {
+            let result = try_extract_batch(&mut session, &paths, &batch_options);
+            let _ = sender.send((session, result));
+        });
+
+        let deadline = timeout.saturating_mul(u32::try_from(file_paths.len()).unwrap_or(u32::MAX));
+        match receiver.recv_timeout(deadline) {
+            Ok((session, result)) => {
+                self.exiftool = Some((process, session));
+                result
+            }
+            Err(_) => {
+                // Killing the stuck process also ends the helper thread's read
+                drop(process);
+                self.exiftool = stay_open::start(&self.executable).ok();
+                Err(anyhow!(
+                    "exiftool took over {}s per file reading {} file(s), so was restarted",
+                    timeout.as_secs_f64(),
+                    file_paths.len()
+                ))
+            }
+        }
+    }
 }
 
 /// Adaptive batch processing: tries to process files in batches, splitting on failure
 fn extract_dates_batch_adaptive(
-    exiftool: &mut ExifTool,
+    reader: &mut MetadataReader,
     file_paths: &[PathBuf],
-) -> HashMap<PathBuf, Result<MediaDates>> {
-    let mut results: HashMap<PathBuf, Result<MediaDates>> = HashMap::new();
+    options: &ExtractOptions,
+) -> HashMap<PathBuf, Result<MediaInfo>> {
+    let mut results: HashMap<PathBuf, Result<MediaInfo>> = HashMap::new();
 
     if file_paths.is_empty() {
         return results;
     }
 
     // Try extracting the full batch
-    match try_extract_batch(exiftool, file_paths) {
+    match reader.read_batch(file_paths, options) {
         Ok(batch_results) => {
             // Batch succeeded, add all results
             results.extend(batch_results);
@@ -155,8 +536,8 @@ fn extract_dates_batch_adaptive(
                 right.len()
             );
 
-            results.extend(extract_dates_batch_adaptive(exiftool, left));
-            results.extend(extract_dates_batch_adaptive(exiftool, right));
+            results.extend(extract_dates_batch_adaptive(reader, left, options));
+            results.extend(extract_dates_batch_adaptive(reader, right, options));
         }
     }
 
@@ -166,16 +547,29 @@ fn extract_dates_batch_adaptive(
 /// Try to extract dates from a batch of files
 /// Returns Err if the exiftool batch operation fails (allows retry with smaller batch)
 fn try_extract_batch(
-    exiftool: &mut ExifTool,
+    exiftool: &mut stay_open::Session,
     file_paths: &[PathBuf],
-) -> Result<HashMap<PathBuf, Result<MediaDates>>> {
+    options: &ExtractOptions,
+) -> Result<HashMap<PathBuf, Result<MediaInfo>>> {
     // Always use -ee (ExtractEmbedded) for thorough metadata extraction
-    let metadata_map = extract_batch_with_exiftool(exiftool, file_paths, true)?;
+    let metadata_map = extract_batch_with_exiftool(exiftool, file_paths, true, &options.exiftool_args)?;
 
     let mut results = HashMap::new();
     for (path, metadata_result) in metadata_map {
-        let result = metadata_result
-            .and_then(|metadata| extract_dates_from_metadata(&path, &metadata));
+        let result = metadata_result.and_then(|metadata| {
+            let dates = extract_dates_from_metadata(&path, &metadata, options)?;
+            Ok(MediaInfo {
+                dates,
+                file_type: find_string_tag(&metadata, "FileType"),
+                mime_type: find_string_tag(&metadata, "MIMEType"),
+                gps: extract_gps(&metadata),
+                orientation: metadata.get("EXIF:Orientation").and_then(|v| v.as_str()).map(str::to_string),
+                file_type_extension: find_string_tag(&metadata, "FileTypeExtension"),
+                identity: extract_identity(&metadata),
+                creation_tag: date_tag(&metadata, CREATION_DATE_TAGS, options),
+                modify_tag: date_tag(&metadata, MODIFY_DATE_TAGS, options),
+            })
+        });
         results.insert(path, result);
     }
 
@@ -183,40 +577,109 @@ fn try_extract_batch(
 }
 
 /// Helper to extract dates from already-parsed metadata
-fn extract_dates_from_metadata(file_path: &Path, metadata: &HashMap<String, Value>) -> Result<MediaDates> {
-    let creation_date = extract_creation_date(metadata)?
-        .ok_or_else(|| anyhow!("No valid creation date found"))?;
-    let modify_date = extract_modify_date(metadata)?
-        .ok_or_else(|| anyhow!("No valid modification date found"))?;
-
-    // Warn if dates are before 2010
-    if creation_date.timestamp() < YEAR_2010 {
-        eprintln!(
-            "Warning: File {} has creation date before 2010: {}",
-            file_path.display(),
-            creation_date
-        );
+fn extract_dates_from_metadata(
+    file_path: &Path,
+    metadata: &HashMap<String, Value>,
+    options: &ExtractOptions,
+) -> Result<MediaDates> {
+    let creation_date = extract_creation_date(metadata, options)?;
+    let modify_date = extract_modify_date(metadata, options)?;
+    if creation_date.is_none() && modify_date.is_none() && !has_date_tags(metadata) {
+        let tag_count = metadata.keys().filter(|key| key.as_str() != "SourceFile").count();
+        return Err(NoDateTags { tag_count }.into());
     }
-    if modify_date.timestamp() < YEAR_2010 {
-        eprintln!(
-            "Warning: File {} has modification date before 2010: {}",
-            file_path.display(),
-            modify_date
-        );
+    let (creation_date, modify_date) = resolve_date_pair(file_path, creation_date, modify_date, options)?;
+
+    options.date_policy.warn_if_suspicious(file_path, "creation", &creation_date);
+    options.date_policy.warn_if_suspicious(file_path, "modification", &modify_date);
+
+    check_date_order(file_path, creation_date, modify_date, options.date_order)
+}
+
+/// Require both dates, or with --single-date use whichever one resolved for both
+fn resolve_date_pair(
+    file_path: &Path,
+    creation_date: Option<DateTime<Utc>>,
+    modify_date: Option<DateTime<Utc>>,
+    options: &ExtractOptions,
+) -> Result<(DateTime<Utc>, DateTime<Utc>)> {
+    match (creation_date, modify_date) {
+        (Some(creation), Some(modify)) => Ok((creation, modify)),
+        (Some(creation), None) if options.single_date => {
+            eprintln!(
+                "Warning: File {} has no valid modification date, synthesized it from the creation date {}",
+                file_path.display(),
+                creation
+            );
+            Ok((creation, creation))
+        }
+        (None, Some(modify)) if options.single_date => {
+            eprintln!(
+                "Warning: File {} has no valid creation date, synthesized it from the modification date {}",
+                file_path.display(),
+                modify
+            );
+            Ok((modify, modify))
+        }
+        (None, _) => Err(anyhow!("No valid creation date found")),
+        (Some(_), None) => Err(anyhow!("No valid modification date found")),
     }
+}
 
-    Ok(MediaDates {
-        creation_date,
-        modify_date,
-    })
+/// Apply the --date-order policy when the creation date is after the modification date
+fn check_date_order(
+    file_path: &Path,
+    creation_date: DateTime<Utc>,
+    modify_date: DateTime<Utc>,
+    order: DateOrder,
+) -> Result<MediaDates> {
+    if creation_date <= modify_date {
+        return Ok(MediaDates {
+            creation_date,
+            modify_date,
+        });
+    }
+
+    match order {
+        DateOrder::Warn => {
+            eprintln!(
+                "Warning: File {} has creation date {} after modification date {}",
+                file_path.display(),
+                creation_date,
+                modify_date
+            );
+            Ok(MediaDates {
+                creation_date,
+                modify_date,
+            })
+        }
+        DateOrder::Swap => {
+            eprintln!(
+                "Warning: File {} has creation date {} after modification date {}, swapping them",
+                file_path.display(),
+                creation_date,
+                modify_date
+            );
+            Ok(MediaDates {
+                creation_date: modify_date,
+                modify_date: creation_date,
+            })
+        }
+        DateOrder::Fail => Err(anyhow!(
+            "Creation date {} is after modification date {}",
+            creation_date,
+            modify_date
+        )),
+    }
 }
 
-/// Extract metadata for multiple files using exiftool json_batch
+/// Extract metadata for multiple files in one exiftool command
 /// Returns Result to allow adaptive retry on batch-level failures
 fn extract_batch_with_exiftool(
-    exiftool: &mut ExifTool,
+    exiftool: &mut stay_open::Session,
     file_paths: &[PathBuf],
     extract_embedded: bool,
+    extra_args: &[String],
 ) -> Result<HashMap<PathBuf, Result<HashMap<String, Value>>>> {
     let mut results = HashMap::new();
 
@@ -225,10 +688,15 @@ fn extract_batch_with_exiftool(
     if extract_embedded {
         args.push("-ee");
     }
+    args.extend(extra_args.iter().map(String::as_str));
+    let path_strs: Vec<String> = file_paths.iter().map(|path| path.to_string_lossy().into_owned()).collect();
+    args.extend(path_strs.iter().map(String::as_str));
 
-    // Call json_batch - bubble up batch-level errors for retry
-    let metadata_array = exiftool.json_batch(file_paths, &args)
-        .context("Exiftool batch execution failed")?;
+    // Bubble up batch-level errors for retry
+    let output = exiftool.json(&args).context("Exiftool batch execution failed")?;
+    let Value::Array(metadata_array) = output else {
+        return Err(anyhow!("Exiftool batch output is not a JSON array"));
+    };
 
     // Each element in the array corresponds to a file in file_paths
     for (i, metadata_value) in metadata_array.into_iter().enumerate() {
@@ -255,7 +723,11 @@ fn extract_batch_with_exiftool(
     Ok(results)
 }
 
-fn extract_with_exiftool(file_path: &Path, extract_embedded: bool) -> Result<HashMap<String, Value>> {
+fn extract_with_exiftool(
+    file_path: &Path,
+    extract_embedded: bool,
+    extra_args: &[String],
+) -> Result<HashMap<String, Value>> {
     let mut exiftool = ExifTool::new()?;
 
     // Build arguments - include the file path and flags
@@ -266,6 +738,7 @@ fn extract_with_exiftool(file_path: &Path, extract_embedded: bool) -> Result<Has
     if extract_embedded {
         args.push("-ee");
     }
+    args.extend(extra_args.iter().map(String::as_str));
     args.push(file_path_str);
 
     // Use json_execute to get metadata with custom args
@@ -282,18 +755,158 @@ fn extract_with_exiftool(file_path: &Path, extract_embedded: bool) -> Result<Has
         .ok_or_else(|| anyhow!("No metadata returned from exiftool"))
 }
 
-fn extract_creation_date(metadata: &HashMap<String, Value>) -> Result<Option<DateTime<Utc>>> {
-    extract_date_by_priority(metadata, CREATION_DATE_TAGS)
+/// Look up a plain string tag, with or without its group prefix
+fn find_string_tag(metadata: &HashMap<String, Value>, tag_name: &str) -> Option<String> {
+    let possible_keys = [tag_name.to_string(), format!("File:{}", tag_name)];
+
+    possible_keys
+        .iter()
+        .find_map(|key| metadata.get(key).and_then(|v| v.as_str()))
+        .map(|s| s.to_string())
+}
+
+/// The shot identity from DateTimeOriginal, SerialNumber and ImageWidth/Height,
+/// or None when any of them is missing
+fn extract_identity(metadata: &HashMap<String, Value>) -> Option<ShotIdentity> {
+    Some(ShotIdentity {
+        date_time_original: find_tag_text(metadata, "DateTimeOriginal", &["EXIF", "XMP"])?,
+        serial_number: find_tag_text(metadata, "SerialNumber", &["EXIF", "MakerNotes"])?,
+        width: find_tag_text(metadata, "ImageWidth", &["File", "EXIF"])?,
+        height: find_tag_text(metadata, "ImageHeight", &["File", "EXIF"])?,
+    })
 }
 
-fn extract_modify_date(metadata: &HashMap<String, Value>) -> Result<Option<DateTime<Utc>>> {
-    extract_date_by_priority(metadata, MODIFY_DATE_TAGS)
+/// A tag's value as text, from the first of `groups` that has it, otherwise
+/// from whichever group sorts first
+fn find_tag_text(metadata: &HashMap<String, Value>, tag_name: &str, groups: &[&str]) -> Option<String> {
+    let value = groups
+        .iter()
+        .find_map(|group| metadata.get(&format!("{}:{}", group, tag_name)))
+        .or_else(|| {
+            metadata
+                .iter()
+                .filter(|(key, _)| key.split_once(':').is_some_and(|(_, tag)| tag == tag_name))
+                .min_by_key(|(key, _)| key.as_str())
+                .map(|(_, value)| value)
+        })?;
+    let text = match value {
+        Value::String(s) => s.trim().to_string(),
+        Value::Number(n) => n.to_string(),
+        _ => return None,
+    };
+    Some(text).filter(|text| !text.is_empty())
 }
 
+/// Read the GPS position, preferring exiftool's signed Composite tags and
+/// falling back to the EXIF tags with their separate hemisphere references
+fn extract_gps(metadata: &HashMap<String, Value>) -> Option<(f64, f64)> {
+    let coordinate = |tag: &str| -> Option<f64> {
+        for key in [format!("Composite:{}", tag), tag.to_string(), format!("EXIF:{}", tag)] {
+            let value = match metadata.get(&key) {
+                Some(Value::Number(n)) => n.as_f64(),
+                Some(Value::String(s)) => parse_gps_coordinate(s),
+                _ => None,
+            };
+            if let Some(mut value) = value {
+                // EXIF coordinates are unsigned; the Ref tag carries the hemisphere
+                if let Some(Value::String(reference)) = metadata.get(&format!("EXIF:{}Ref", tag)) {
+                    if reference.starts_with('S') || reference.starts_with('W') {
+                        value = -value.abs();
+                    }
+                }
+                return Some(value);
+            }
+        }
+        None
+    };
+
+    Some((coordinate("GPSLatitude")?, coordinate("GPSLongitude")?))
+}
+
+/// Parse an exiftool coordinate such as `37 deg 46' 29.64" N` or `-122.4194`
+fn parse_gps_coordinate(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let negative = s.starts_with('-') || s.ends_with('S') || s.ends_with('W');
+
+    let numbers: Vec<f64> = s
+        .split(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse::<f64>())
+        .collect::<std::result::Result<_, _>>()
+        .ok()?;
+
+    let (degrees, minutes, seconds) = match numbers.as_slice() {
+        [d] => (*d, 0.0, 0.0),
+        [d, m] => (*d, *m, 0.0),
+        [d, m, s] => (*d, *m, *s),
+        _ => return None,
+    };
+
+    let value = degrees + minutes / 60.0 + seconds / 3600.0;
+    Some(if negative { -value } else { value })
+}
+
+/// The metadata key the first valid date in `priority_list` comes from
+fn date_tag(metadata: &HashMap<String, Value>, priority_list: &[&str], options: &ExtractOptions) -> Option<String> {
+    extract_date_by_priority(metadata, priority_list, options).ok().flatten().map(|(key, _)| key)
+}
+
+fn extract_creation_date(
+    metadata: &HashMap<String, Value>,
+    options: &ExtractOptions,
+) -> Result<Option<DateTime<Utc>>> {
+    Ok(extract_date_by_priority(metadata, CREATION_DATE_TAGS, options)?.map(|(_, date)| date))
+}
+
+fn extract_modify_date(
+    metadata: &HashMap<String, Value>,
+    options: &ExtractOptions,
+) -> Result<Option<DateTime<Utc>>> {
+    Ok(extract_date_by_priority(metadata, MODIFY_DATE_TAGS, options)?.map(|(_, date)| date))
+}
+
+/// Groups holding a Motion Photo's own still-image dates, as opposed to the
+/// QuickTime dates of the video embedded after it (read with -ee)
+const STILL_IMAGE_GROUPS: &[&str] = &["EXIF", "XMP"];
+
+/// Find the first valid date in priority order, along with the metadata key it came from
+///
+/// For a Motion Photo the still image's EXIF/XMP dates are tried first, so a
+/// date from the embedded video can't win over the photo's own; the video's
+/// dates are still used if the image has none.
 fn extract_date_by_priority(
     metadata: &HashMap<String, Value>,
     priority_list: &[&str],
-) -> Result<Option<DateTime<Utc>>> {
+    options: &ExtractOptions,
+) -> Result<Option<(String, DateTime<Utc>)>> {
+    if is_motion_photo(metadata) {
+        if let Some(found) = first_valid_date(metadata, priority_list, options, Some(STILL_IMAGE_GROUPS))? {
+            return Ok(Some(found));
+        }
+    }
+    first_valid_date(metadata, priority_list, options, None)
+}
+
+/// Whether exiftool found a Motion Photo marker: Google's XMP MotionPhoto
+/// (or the older MicroVideo) flag, or Samsung's trailer of type MotionPhoto_Data
+fn is_motion_photo(metadata: &HashMap<String, Value>) -> bool {
+    metadata.iter().any(|(key, value)| {
+        let tag = key.rsplit_once(':').map_or(key.as_str(), |(_, tag)| tag);
+        match tag {
+            "MotionPhoto" | "MicroVideo" => value.as_i64() == Some(1) || value.as_str() == Some("1"),
+            "EmbeddedVideoType" => value.as_str().is_some_and(|s| s.starts_with("MotionPhoto")),
+            _ => false,
+        }
+    })
+}
+
+/// The first valid date in priority order, looking only in `groups` if given
+fn first_valid_date(
+    metadata: &HashMap<String, Value>,
+    priority_list: &[&str],
+    options: &ExtractOptions,
+    groups: Option<&[&str]>,
+) -> Result<Option<(String, DateTime<Utc>)>> {
     // Get timezone offset if available
     let timezone_offset = extract_timezone_offset(metadata);
 
@@ -301,16 +914,17 @@ fn extract_date_by_priority(
         if *tag_name == "UserComment" {
             // Special handling for UserComment JSON field
             if let Some(date) = extract_date_from_user_comment(metadata)? {
-                if is_valid_date(date) {
-                    return Ok(Some(date));
+                if options.date_policy.is_valid(&date) {
+                    return Ok(Some(("UserComment.orgFileModifiedDate".to_string(), date)));
                 }
             }
         } else {
             // Try to find the tag with various group prefixes
-            let date = find_and_parse_date(metadata, tag_name, timezone_offset)?;
-            if let Some(d) = date {
-                if is_valid_date(d) {
-                    return Ok(Some(d));
+            let date = find_and_parse_date(metadata, tag_name, timezone_offset, options.array_date, groups)?;
+            if let Some((key, d)) = date {
+                let d = add_subsec_time(metadata, &key, d);
+                if options.date_policy.is_valid(&d) {
+                    return Ok(Some((key, d)));
                 }
             }
         }
@@ -323,28 +937,38 @@ fn find_and_parse_date(
     metadata: &HashMap<String, Value>,
     tag_name: &str,
     timezone_offset: Option<i32>,
-) -> Result<Option<DateTime<Utc>>> {
+    array_date: ArrayDate,
+    groups: Option<&[&str]>,
+) -> Result<Option<(String, DateTime<Utc>)>> {
     // Try different tag name formats
     let possible_keys = vec![
         tag_name.to_string(),
         format!("EXIF:{}", tag_name),
         format!("QuickTime:{}", tag_name),
         format!("XMP:{}", tag_name),
+        // The document info dictionary of a PDF (scans, exported documents)
+        format!("PDF:{}", tag_name),
         format!("Composite:{}", tag_name),
         format!("File:{}", tag_name),
     ];
 
     for key in possible_keys {
+        if let Some(groups) = groups {
+            let in_group = key.split_once(':').is_some_and(|(group, _)| groups.contains(&group));
+            if !in_group {
+                continue;
+            }
+        }
         if let Some(value) = metadata.get(&key) {
             // Handle arrays (for Track/Media dates)
             if let Some(arr) = value.as_array() {
                 let dates = parse_date_array(arr, timezone_offset)?;
                 if !dates.is_empty() {
-                    return Ok(Some(find_mode_or_earliest(dates)));
+                    return Ok(Some((key, select_array_date(dates, array_date))));
                 }
             } else if let Some(s) = value.as_str() {
                 if let Some(date) = parse_date_string(s, timezone_offset)? {
-                    return Ok(Some(date));
+                    return Ok(Some((key, date)));
                 }
             }
         }
@@ -353,6 +977,40 @@ fn find_and_parse_date(
     Ok(None)
 }
 
+/// Add the fractional seconds from the EXIF tag that goes with `key` (such
+/// as SubSecTimeOriginal for DateTimeOriginal) to a date parsed without any
+fn add_subsec_time(metadata: &HashMap<String, Value>, key: &str, date: DateTime<Utc>) -> DateTime<Utc> {
+    let (group, tag) = key.rsplit_once(':').unwrap_or(("EXIF", key));
+    if group != "EXIF" || date.nanosecond() != 0 {
+        return date;
+    }
+    let Some(&(_, subsec_tag)) = SUBSEC_TAGS.iter().find(|(date_tag, _)| *date_tag == tag) else {
+        return date;
+    };
+
+    let value = [subsec_tag.to_string(), format!("EXIF:{}", subsec_tag)]
+        .iter()
+        .find_map(|key| match metadata.get(key) {
+            Some(Value::String(s)) => Some(s.clone()),
+            Some(Value::Number(n)) => Some(n.to_string()),
+            _ => None,
+        });
+    value
+        .and_then(|value| parse_subsec(&value))
+        .and_then(|nanos| date.with_nanosecond(nanos))
+        .unwrap_or(date)
+}
+
+/// Parse a SubSecTime value: the digits after the decimal point, so "5" is
+/// half a second and "042" 42 milliseconds. Digits past nanoseconds are cut
+fn parse_subsec(value: &str) -> Option<u32> {
+    let digits: String = value.trim().chars().take_while(char::is_ascii_digit).take(9).collect();
+    if digits.is_empty() {
+        return None;
+    }
+    format!("{:0<9}", digits).parse().ok()
+}
+
 fn parse_date_array(
     arr: &[Value],
     timezone_offset: Option<i32>,
@@ -370,22 +1028,31 @@ fn parse_date_array(
     Ok(dates)
 }
 
-fn find_mode_or_earliest(dates: Vec<DateTime<Utc>>) -> DateTime<Utc> {
+/// Pick one date from a non-empty array according to the --array-date policy
+fn select_array_date(dates: Vec<DateTime<Utc>>, policy: ArrayDate) -> DateTime<Utc> {
+    match policy {
+        ArrayDate::ModeEarliest => *find_modes(&dates).iter().min().unwrap(),
+        ArrayDate::ModeLatest => *find_modes(&dates).iter().max().unwrap(),
+        ArrayDate::Earliest => dates.into_iter().min().unwrap(),
+        ArrayDate::Latest => dates.into_iter().max().unwrap(),
+    }
+}
+
+/// All dates sharing the highest occurrence count (the tied modes)
+fn find_modes(dates: &[DateTime<Utc>]) -> Vec<DateTime<Utc>> {
     let mut counts: HashMap<i64, usize> = HashMap::new();
 
-    for date in &dates {
+    for date in dates {
         *counts.entry(date.timestamp()).or_insert(0) += 1;
     }
 
     // Find the mode (most common)
     let max_count = counts.values().max().copied().unwrap_or(0);
-    let modes: Vec<_> = dates
+    dates
         .iter()
         .filter(|d| counts.get(&d.timestamp()) == Some(&max_count))
-        .collect();
-
-    // If there's a tie, return the earliest
-    **modes.iter().min().unwrap()
+        .copied()
+        .collect()
 }
 
 fn parse_date_string(s: &str, timezone_offset: Option<i32>) -> Result<Option<DateTime<Utc>>> {
@@ -478,34 +1145,126 @@ fn extract_date_from_user_comment(metadata: &HashMap<String, Value>) -> Result<O
     Ok(None)
 }
 
-fn is_valid_date(date: DateTime<Utc>) -> bool {
-    let now = Utc::now();
-    let timestamp = date.timestamp();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    fn media_info(file_type: &str, mime_type: &str) -> MediaInfo {
+        let date = Utc.with_ymd_and_hms(2025, 8, 10, 3, 43, 16).unwrap();
+        MediaInfo {
+            dates: MediaDates {
+                creation_date: date,
+                modify_date: date,
+            },
+            file_type: Some(file_type.to_string()),
+            mime_type: Some(mime_type.to_string()),
+            gps: None,
+            orientation: None,
+            file_type_extension: None,
+            identity: None,
+            creation_tag: None,
+            modify_tag: None,
+        }
+    }
 
-    // Check if date is in the future
-    if date > now {
-        return false;
+    #[test]
+    fn test_media_folder() {
+        let mut overrides = HashMap::new();
+        assert_eq!(media_info("JPEG", "image/jpeg").media_folder(&overrides), "Photos");
+        assert_eq!(media_info("MOV", "video/quicktime").media_folder(&overrides), "Videos");
+        assert_eq!(media_info("PDF", "application/pdf").media_folder(&overrides), "Other");
+
+        overrides.insert("DNG".to_string(), "Raw".to_string());
+        overrides.insert("video".to_string(), "Movies".to_string());
+        assert_eq!(media_info("DNG", "image/x-adobe-dng").media_folder(&overrides), "Raw");
+        assert_eq!(media_info("MP4", "video/mp4").media_folder(&overrides), "Movies");
     }
 
-    // Check if date is a rejected epoch
-    for &epoch in REJECTED_EPOCHS {
-        if (timestamp - epoch).abs() < 86400 {
-            // Within a day of a rejected epoch
-            return false;
-        }
+    #[test]
+    fn test_check_date_order() {
+        let earlier = Utc.with_ymd_and_hms(2025, 8, 10, 3, 43, 16).unwrap();
+        let later = Utc.with_ymd_and_hms(2025, 8, 11, 3, 43, 16).unwrap();
+        let path = Path::new("IMG_0001.JPG");
+
+        let dates = check_date_order(path, later, earlier, DateOrder::Warn).unwrap();
+        assert_eq!(dates.creation_date, later);
+
+        let dates = check_date_order(path, later, earlier, DateOrder::Swap).unwrap();
+        assert_eq!(dates.creation_date, earlier);
+        assert_eq!(dates.modify_date, later);
+
+        assert!(check_date_order(path, later, earlier, DateOrder::Fail).is_err());
+        assert!(check_date_order(path, earlier, later, DateOrder::Fail).is_ok());
     }
 
-    // Check if timestamp is zero
-    if timestamp == 0 {
-        return false;
+    #[test]
+    fn test_resolve_date_pair() {
+        let date = Utc.with_ymd_and_hms(2025, 8, 10, 3, 43, 16).unwrap();
+        let path = Path::new("scan.jpg");
+        let mut options = ExtractOptions::default();
+
+        assert!(resolve_date_pair(path, Some(date), None, &options).is_err());
+        assert!(resolve_date_pair(path, None, Some(date), &options).is_err());
+
+        options.single_date = true;
+        assert_eq!(resolve_date_pair(path, Some(date), None, &options).unwrap(), (date, date));
+        assert_eq!(resolve_date_pair(path, None, Some(date), &options).unwrap(), (date, date));
+        assert!(resolve_date_pair(path, None, None, &options).is_err());
     }
 
-    true
-}
+    #[test]
+    fn test_no_date_tags() {
+        let path = Path::new("scan.xyz");
+        let options = ExtractOptions::default();
+        let mut metadata = HashMap::new();
+        metadata.insert("SourceFile".to_string(), Value::from("scan.xyz"));
+        let err = extract_dates_from_metadata(path, &metadata, &options).unwrap_err();
+        assert_eq!(err.downcast_ref::<NoDateTags>().unwrap().tag_count, 0);
+
+        // A date tag that is present but rejected is an ordinary failure
+        metadata.insert("File:FileModifyDate".to_string(), Value::from("0000:00:00 00:00:00"));
+        let err = extract_dates_from_metadata(path, &metadata, &options).unwrap_err();
+        assert!(err.downcast_ref::<NoDateTags>().is_none());
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_gps_parsing() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-4;
+        assert!(close(parse_gps_coordinate("37 deg 46' 29.64\" N").unwrap(), 37.7749));
+        assert!(close(parse_gps_coordinate("122 deg 25' 9.84\" W").unwrap(), -122.4194));
+        assert!(close(parse_gps_coordinate("-33.8688").unwrap(), -33.8688));
+        assert_eq!(parse_gps_coordinate(""), None);
+
+        let mut metadata = HashMap::new();
+        metadata.insert("EXIF:GPSLatitude".to_string(), Value::from("33 deg 52' 7.68\""));
+        metadata.insert("EXIF:GPSLatitudeRef".to_string(), Value::from("South"));
+        metadata.insert("EXIF:GPSLongitude".to_string(), Value::from("151 deg 12' 33.48\""));
+        metadata.insert("EXIF:GPSLongitudeRef".to_string(), Value::from("East"));
+        let (lat, lon) = extract_gps(&metadata).unwrap();
+        assert!(close(lat, -33.8688) && close(lon, 151.2093));
+    }
+
+    #[test]
+    fn test_extract_identity() {
+        let mut metadata = HashMap::new();
+        metadata.insert("EXIF:DateTimeOriginal".to_string(), Value::from("2024:05:06 07:08:09"));
+        metadata.insert("XMP:DateTimeOriginal".to_string(), Value::from("2024:05:06 07:08:10"));
+        metadata.insert("MakerNotes:SerialNumber".to_string(), Value::from("012345678"));
+        metadata.insert("File:ImageWidth".to_string(), Value::from(6000));
+        assert_eq!(extract_identity(&metadata), None);
+
+        metadata.insert("EXIF:ImageHeight".to_string(), Value::from(4000));
+        assert_eq!(
+            extract_identity(&metadata),
+            Some(ShotIdentity {
+                date_time_original: "2024:05:06 07:08:09".to_string(),
+                serial_number: "012345678".to_string(),
+                width: "6000".to_string(),
+                height: "4000".to_string(),
+            })
+        );
+    }
 
     #[test]
     fn test_timezone_offset_parsing() {
@@ -513,4 +1272,180 @@ mod tests {
         assert_eq!(parse_timezone_offset("-05:00"), Some(-5 * 3600));
         assert_eq!(parse_timezone_offset("+00:00"), Some(0));
     }
+
+    #[test]
+    fn test_select_array_date() {
+        let date = |day| Utc.with_ymd_and_hms(2025, 8, day, 0, 0, 0).unwrap();
+        let dates = vec![date(3), date(1), date(3), date(1), date(5), date(2)];
+
+        assert_eq!(select_array_date(dates.clone(), ArrayDate::ModeEarliest), date(1));
+        assert_eq!(select_array_date(dates.clone(), ArrayDate::ModeLatest), date(3));
+        assert_eq!(select_array_date(dates.clone(), ArrayDate::Earliest), date(1));
+        assert_eq!(select_array_date(dates, ArrayDate::Latest), date(5));
+    }
+
+    #[test]
+    fn test_validate_exiftool_args() {
+        let args = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert!(validate_exiftool_args(&args(&["-api", "LargeFileSupport=1", "-n", "-x", "ThumbnailImage"])).is_ok());
+        assert!(validate_exiftool_args(&args(&["-j"])).is_err());
+        assert!(validate_exiftool_args(&args(&["-G1"])).is_err());
+        assert!(validate_exiftool_args(&args(&["-X"])).is_err());
+        assert!(validate_exiftool_args(&args(&["-config", "mytags.cfg"])).is_err());
+        assert!(validate_exiftool_args(&args(&["/etc/passwd"])).is_err());
+        assert!(validate_exiftool_args(&args(&["-DateTimeOriginal=2020:01:01"])).is_err());
+        assert!(validate_exiftool_args(&args(&["-api"])).is_err());
+    }
+
+    #[test]
+    fn test_reader_timeout_scales_with_batch() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("exif_timeout");
+        // A stand-in exiftool taking a second over every command
+        let executable = dir.join("exiftool");
+        std::fs::write(
+            &executable,
+            r#"#!/bin/sh
+while IFS= read -r line; do
+    case "$line" in
+        -echo4) read -r marker ;;
+        -execute) sleep 1; printf '[]\n{ready}\n'; printf '%s\n' "$marker" >&2 ;;
+    esac
+done
+"#,
+        )
+        .unwrap();
+        std::fs::set_permissions(&executable, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let options = ExtractOptions {
+            timeout: Some(std::time::Duration::from_millis(500)),
+            exiftool_path: Some(executable),
+            ..ExtractOptions::default()
+        };
+        let mut reader = MetadataReader::new(&options).unwrap();
+        let paths: Vec<PathBuf> = ["a.jpg", "b.jpg", "c.jpg"].iter().map(|name| dir.join(name)).collect();
+
+        // Three files get a second and a half between them
+        assert!(reader.read_batch(&paths, &options).is_ok());
+        let err = reader.read_batch(&paths[..1], &options).unwrap_err();
+        assert!(err.to_string().contains("restarted"), "{}", err);
+        // The replacement process takes the next batch
+        assert!(reader.read_batch(&paths, &options).is_ok());
+    }
+
+    #[test]
+    fn test_future_tolerance() {
+        let soon = Utc::now() + Duration::hours(3);
+        let mut policy = DatePolicy::default();
+        assert!(!policy.is_valid(&soon));
+        policy.future_tolerance = Duration::hours(48);
+        assert!(policy.is_valid(&soon));
+        assert!(!policy.is_valid(&(Utc::now() + Duration::days(30))));
+
+        // A tolerance too large to add to now accepts any future date
+        policy.future_tolerance = Duration::MAX;
+        assert!(policy.is_valid(&(Utc::now() + Duration::days(30))));
+    }
+
+    #[test]
+    fn test_custom_date_policy() {
+        let dos_epoch = Utc.with_ymd_and_hms(1980, 1, 1, 12, 0, 0).unwrap();
+        let policy = DatePolicy::default();
+        assert!(!policy.is_valid(&dos_epoch));
+        assert!(policy.is_suspicious(&Utc.with_ymd_and_hms(2009, 12, 31, 0, 0, 0).unwrap()));
+        assert!(!policy.is_suspicious(&Utc.with_ymd_and_hms(2010, 1, 1, 0, 0, 0).unwrap()));
+
+        let policy = DatePolicy {
+            rejected_epochs: vec![0],
+            suspicious_before: Utc.with_ymd_and_hms(1995, 1, 1, 0, 0, 0).unwrap(),
+            ..DatePolicy::default()
+        };
+        assert!(policy.is_valid(&dos_epoch));
+        assert!(!policy.is_valid(&Utc.with_ymd_and_hms(1970, 1, 1, 0, 0, 0).unwrap()));
+        assert!(!policy.is_suspicious(&Utc.with_ymd_and_hms(2009, 12, 31, 0, 0, 0).unwrap()));
+
+        let metadata: HashMap<String, Value> = serde_json::from_str(
+            r#"{"SourceFile": "a.jpg", "EXIF:DateTimeOriginal": "1980:01:01 12:00:00",
+                "EXIF:ModifyDate": "1980:01:01 12:00:00"}"#,
+        )
+        .unwrap();
+        let options = ExtractOptions { date_policy: policy, ..ExtractOptions::default() };
+        let dates = extract_dates_from_metadata(Path::new("a.jpg"), &metadata, &options).unwrap();
+        assert_eq!(dates.creation_date, dos_epoch);
+    }
+
+    #[test]
+    fn test_date_source_tag() {
+        let mut metadata = HashMap::new();
+        metadata.insert("EXIF:DateTimeOriginal".to_string(), json!("0000:00:00 00:00:00"));
+        metadata.insert("QuickTime:CreateDate".to_string(), json!("2025:08:10 03:43:16"));
+
+        let (tag, date) = extract_date_by_priority(&metadata, CREATION_DATE_TAGS, &ExtractOptions::default()).unwrap().unwrap();
+        assert_eq!(tag, "QuickTime:CreateDate");
+        assert_eq!(date, Utc.with_ymd_and_hms(2025, 8, 10, 3, 43, 16).unwrap());
+    }
+
+    #[test]
+    fn test_subsec_time() {
+        let mut metadata = HashMap::new();
+        metadata.insert("EXIF:DateTimeOriginal".to_string(), json!("2024:03:01 10:20:30"));
+        metadata.insert("EXIF:SubSecTimeOriginal".to_string(), json!("042"));
+        metadata.insert("EXIF:ModifyDate".to_string(), json!("2024:03:01 10:20:30"));
+        metadata.insert("EXIF:SubSecTime".to_string(), json!(5));
+
+        let options = ExtractOptions::default();
+        let (_, date) = extract_date_by_priority(&metadata, CREATION_DATE_TAGS, &options).unwrap().unwrap();
+        assert_eq!(date.timestamp_subsec_millis(), 42);
+        let (_, date) = extract_date_by_priority(&metadata, MODIFY_DATE_TAGS, &options).unwrap().unwrap();
+        assert_eq!(date.timestamp_subsec_millis(), 500);
+
+        // Fractional seconds already in the date string win
+        metadata.insert("EXIF:DateTimeOriginal".to_string(), json!("2024:03:01 10:20:30.250"));
+        let (_, date) = extract_date_by_priority(&metadata, CREATION_DATE_TAGS, &options).unwrap().unwrap();
+        assert_eq!(date.timestamp_subsec_millis(), 250);
+
+        assert_eq!(parse_subsec("1234567891234"), Some(123_456_789));
+        assert_eq!(parse_subsec(" "), None);
+    }
+
+    #[test]
+    fn test_pdf_dates() {
+        let mut metadata = HashMap::new();
+        metadata.insert("PDF:CreateDate".to_string(), json!("2023:05:01 10:00:00+02:00"));
+        metadata.insert("PDF:ModifyDate".to_string(), json!("2023:05:02 09:30:00+02:00"));
+        metadata.insert("File:FileModifyDate".to_string(), json!("2024:01:01 00:00:00+00:00"));
+
+        let options = ExtractOptions::default();
+        let (tag, date) = extract_date_by_priority(&metadata, CREATION_DATE_TAGS, &options).unwrap().unwrap();
+        assert_eq!(tag, "PDF:CreateDate");
+        assert_eq!(date, Utc.with_ymd_and_hms(2023, 5, 1, 8, 0, 0).unwrap());
+        let (tag, _) = extract_date_by_priority(&metadata, MODIFY_DATE_TAGS, &options).unwrap().unwrap();
+        assert_eq!(tag, "PDF:ModifyDate");
+    }
+
+    #[test]
+    fn test_motion_photo_prefers_still_image_dates() {
+        let mut metadata = HashMap::new();
+        metadata.insert("EXIF:CreateDate".to_string(), json!("2024:06:01 18:00:00"));
+        metadata.insert("QuickTime:MediaCreateDate".to_string(), json!("2024:06:01 17:59:57"));
+
+        // Without a marker the embedded video's MediaCreateDate outranks CreateDate
+        let options = ExtractOptions::default();
+        let (tag, _) = extract_date_by_priority(&metadata, CREATION_DATE_TAGS, &options).unwrap().unwrap();
+        assert_eq!(tag, "QuickTime:MediaCreateDate");
+
+        metadata.insert("XMP:MotionPhoto".to_string(), json!(1));
+        let (tag, date) = extract_date_by_priority(&metadata, CREATION_DATE_TAGS, &options).unwrap().unwrap();
+        assert_eq!(tag, "EXIF:CreateDate");
+        assert_eq!(date, Utc.with_ymd_and_hms(2024, 6, 1, 18, 0, 0).unwrap());
+
+        // Samsung's marker, and the video's dates when the image has none
+        metadata.remove("XMP:MotionPhoto");
+        metadata.insert("Samsung:EmbeddedVideoType".to_string(), json!("MotionPhoto_Data"));
+        assert!(is_motion_photo(&metadata));
+        metadata.remove("EXIF:CreateDate");
+        let (tag, _) = extract_date_by_priority(&metadata, CREATION_DATE_TAGS, &options).unwrap().unwrap();
+        assert_eq!(tag, "QuickTime:MediaCreateDate");
+    }
 }
@@ -0,0 +1,159 @@
+use anyhow::Result;
+use std::path::Path;
+
+use crate::archive::ExtractedArchive;
+
+/// Input argument naming the first connected MTP/PTP device
+pub const MTP_INPUT: &str = "mtp:";
+
+/// Check if an input is the MTP device placeholder rather than a path
+pub fn is_mtp_input(path: &Path) -> bool {
+    path.as_os_str() == MTP_INPUT
+}
+
+/// Copy every file on the first connected MTP/PTP device into a new
+/// temporary folder, leaving out files for which `skip` returns true.
+/// Files are laid out by their parent folder's object id, and keep the
+/// device's modification time. Nothing on the device is changed
+#[cfg(feature = "mtp")]
+pub fn copy_from_device(skip: impl Fn(&Path) -> bool) -> Result<ExtractedArchive> {
+    libmtp::copy_first_device(&skip)
+}
+
+#[cfg(not(feature = "mtp"))]
+pub fn copy_from_device(_skip: impl Fn(&Path) -> bool) -> Result<ExtractedArchive> {
+    anyhow::bail!("Importing from '{}' needs collect_media built with the mtp feature", MTP_INPUT)
+}
+
+#[cfg(feature = "mtp")]
+mod libmtp {
+    use crate::archive::ExtractedArchive;
+    use anyhow::{anyhow, bail, Context, Result};
+    use std::ffi::{c_char, c_int, c_void, CStr, CString};
+    use std::fs::{self, File};
+    use std::path::Path;
+    use std::ptr;
+    use std::time::{Duration, SystemTime};
+
+    /// `LIBMTP_FILETYPE_FOLDER`, the first entry of `LIBMTP_filetype_t`
+    const FILETYPE_FOLDER: c_int = 0;
+
+    #[repr(C)]
+    struct LibmtpFile {
+        item_id: u32,
+        parent_id: u32,
+        storage_id: u32,
+        filename: *mut c_char,
+        filesize: u64,
+        modificationdate: libc::time_t,
+        filetype: c_int,
+        next: *mut LibmtpFile,
+    }
+
+    #[link(name = "mtp")]
+    extern "C" {
+        fn LIBMTP_Init();
+        fn LIBMTP_Get_First_Device() -> *mut c_void;
+        fn LIBMTP_Release_Device(device: *mut c_void);
+        fn LIBMTP_Get_Filelisting_With_Callback(
+            device: *mut c_void,
+            callback: *const c_void,
+            data: *const c_void,
+        ) -> *mut LibmtpFile;
+        fn LIBMTP_Get_File_To_File(
+            device: *mut c_void,
+            id: u32,
+            path: *const c_char,
+            callback: *const c_void,
+            data: *const c_void,
+        ) -> c_int;
+        fn LIBMTP_destroy_file_t(file: *mut LibmtpFile);
+    }
+
+    /// An open device, released on drop
+    struct Device(*mut c_void);
+
+    impl Drop for Device {
+        fn drop(&mut self) {
+            // SAFETY: the pointer came from LIBMTP_Get_First_Device and is released once
+            unsafe { LIBMTP_Release_Device(self.0) }
+        }
+    }
+
+    pub(super) fn copy_first_device(skip: &dyn Fn(&Path) -> bool) -> Result<ExtractedArchive> {
+        // SAFETY: LIBMTP_Init has no preconditions and may be called more than once
+        let device = unsafe {
+            LIBMTP_Init();
+            LIBMTP_Get_First_Device()
+        };
+        if device.is_null() {
+            bail!("No MTP/PTP device found");
+        }
+        let device = Device(device);
+
+        let staged = ExtractedArchive::create()?;
+        let mut result = Ok(());
+        // SAFETY: the device is open; the list is freed entry by entry below
+        let mut file = unsafe { LIBMTP_Get_Filelisting_With_Callback(device.0, ptr::null(), ptr::null()) };
+        while !file.is_null() {
+            // SAFETY: `file` is a non-null entry of the list, not yet destroyed
+            let entry = unsafe { &*file };
+            if result.is_ok() && entry.filetype != FILETYPE_FOLDER && !entry.filename.is_null() {
+                result = copy_file(&device, entry, staged.dir(), skip);
+            }
+            let next = entry.next;
+            // SAFETY: each entry is destroyed once, after its last use
+            unsafe { LIBMTP_destroy_file_t(file) };
+            file = next;
+        }
+        result.context("Failed to copy from MTP device")?;
+
+        Ok(staged)
+    }
+
+    fn copy_file(device: &Device, entry: &LibmtpFile, dir: &Path, skip: &dyn Fn(&Path) -> bool) -> Result<()> {
+        // SAFETY: libmtp file names are NUL-terminated, and checked non-null by the caller
+        let name = unsafe { CStr::from_ptr(entry.filename) }.to_string_lossy();
+        // Device file names are not trusted to stay inside the folder
+        if name.is_empty() || name.contains('/') || name == "." || name == ".." {
+            eprintln!("Warning: Skipping unsafe MTP file name: {}", name);
+            return Ok(());
+        }
+        let relative = Path::new(&entry.parent_id.to_string()).join(name.as_ref());
+        if skip(&relative) {
+            return Ok(());
+        }
+
+        let target = dir.join(&relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let target_c = CString::new(target.as_os_str().as_encoded_bytes())
+            .map_err(|_| anyhow!("File path contains a NUL byte: {}", target.display()))?;
+        // SAFETY: the device is open and target_c outlives the call
+        if unsafe { LIBMTP_Get_File_To_File(device.0, entry.item_id, target_c.as_ptr(), ptr::null(), ptr::null()) }
+            != 0
+        {
+            bail!("Failed to copy {} from the device", relative.display());
+        }
+
+        // Keep the camera's time, which the date fallback relies on
+        if entry.modificationdate > 0 {
+            let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(entry.modificationdate as u64);
+            File::options().write(true).open(&target)?.set_modified(modified)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_mtp_input() {
+        assert!(is_mtp_input(Path::new("mtp:")));
+        assert!(!is_mtp_input(Path::new("mtp:/DCIM")));
+        assert!(!is_mtp_input(Path::new("/Volumes/mtp:")));
+    }
+}
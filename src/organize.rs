@@ -0,0 +1,91 @@
+use anyhow::{bail, Result};
+use chrono::Datelike;
+use std::path::PathBuf;
+
+/// Default size of a --organize-by-location grid cell, in degrees
+pub const DEFAULT_LOCATION_GRID: f64 = 1.0;
+
+/// Date-based folder layout under the output directory
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DateLayout {
+    /// Everything directly in the output directory
+    #[default]
+    Flat,
+    /// `YYYY/`
+    Year,
+    /// `YYYY/MM/`
+    Month,
+    /// `YYYY/MM/DD/`
+    Day,
+}
+
+impl DateLayout {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "flat" => Ok(DateLayout::Flat),
+            "year" => Ok(DateLayout::Year),
+            "month" => Ok(DateLayout::Month),
+            "day" => Ok(DateLayout::Day),
+            other => bail!("--organize-by expects flat, year, month, or day, got '{}'", other),
+        }
+    }
+}
+
+/// Subfolder for a date under the given layout
+pub fn date_folder(date: &impl Datelike, layout: DateLayout) -> PathBuf {
+    let mut folder = PathBuf::new();
+    if layout == DateLayout::Flat {
+        return folder;
+    }
+
+    folder.push(format!("{:04}", date.year()));
+    if layout == DateLayout::Month || layout == DateLayout::Day {
+        folder.push(format!("{:02}", date.month()));
+    }
+    if layout == DateLayout::Day {
+        folder.push(format!("{:02}", date.day()));
+    }
+    folder
+}
+
+/// Offline coarse location: the name of the lat/long grid cell containing a
+/// coordinate, e.g. `N37_W122` for San Francisco with 1-degree cells
+pub fn location_folder(latitude: f64, longitude: f64, grid: f64) -> String {
+    format!(
+        "{}_{}",
+        cell_name(latitude, grid, 'N', 'S'),
+        cell_name(longitude, grid, 'E', 'W')
+    )
+}
+
+/// Name the cell edge nearest the equator/meridian, so cells never straddle zero
+fn cell_name(value: f64, grid: f64, positive: char, negative: char) -> String {
+    let edge = (value.abs() / grid).floor() * grid;
+    let hemisphere = if value < 0.0 { negative } else { positive };
+    // Trim trailing zeros so whole-degree cells read as N37 rather than N37.000
+    let number = format!("{:.3}", edge);
+    let number = number.trim_end_matches('0').trim_end_matches('.');
+    format!("{}{}", hemisphere, number)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_date_folder() {
+        let date = Utc.with_ymd_and_hms(2025, 8, 10, 3, 43, 16).unwrap();
+        assert_eq!(date_folder(&date, DateLayout::Flat), PathBuf::new());
+        assert_eq!(date_folder(&date, DateLayout::Year), PathBuf::from("2025"));
+        assert_eq!(date_folder(&date, DateLayout::Month), PathBuf::from("2025/08"));
+        assert_eq!(date_folder(&date, DateLayout::Day), PathBuf::from("2025/08/10"));
+    }
+
+    #[test]
+    fn test_location_folder() {
+        assert_eq!(location_folder(37.7749, -122.4194, 1.0), "N37_W122");
+        assert_eq!(location_folder(-33.8688, 151.2093, 0.5), "S33.5_E151");
+        assert_eq!(location_folder(0.2, 0.2, 1.0), "N0_E0");
+    }
+}
@@ -0,0 +1,117 @@
+use anyhow::{bail, Context, Result};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// exiftool's printed value for an image that needs no rotation or flip
+const NORMAL_ORIENTATION: &str = "Horizontal (normal)";
+
+/// Check if an EXIF Orientation value asks viewers to rotate or flip the image
+pub fn needs_orienting(orientation: Option<&str>) -> bool {
+    matches!(orientation, Some(value) if value != NORMAL_ORIENTATION)
+}
+
+/// The jpegtran transform that undoes an exiftool Orientation value
+fn jpegtran_transform(orientation: &str) -> Option<&'static [&'static str]> {
+    match orientation {
+        "Mirror horizontal" => Some(&["-flip", "horizontal"]),
+        "Rotate 180" => Some(&["-rotate", "180"]),
+        "Mirror vertical" => Some(&["-flip", "vertical"]),
+        "Mirror horizontal and rotate 270 CW" => Some(&["-transpose"]),
+        "Rotate 90 CW" => Some(&["-rotate", "90"]),
+        "Mirror horizontal and rotate 90 CW" => Some(&["-transverse"]),
+        "Rotate 270 CW" => Some(&["-rotate", "270"]),
+        _ => None,
+    }
+}
+
+/// Rotate an image's pixels upright and reset its Orientation tag. JPEGs are
+/// transformed losslessly with jpegtran; other formats are re-encoded by
+/// ImageMagick's `-auto-orient`. The result is written beside the file and
+/// renamed over it, so the file is left untouched if anything fails
+pub fn auto_orient(path: &Path, orientation: &str, mime_type: Option<&str>) -> Result<()> {
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .context("Cannot auto-orient a path without a file name")?;
+    // Keep the extension last so ImageMagick writes the same format
+    let temp_path = path.with_file_name(format!(".orienting.{}", file_name));
+
+    let written = if mime_type == Some("image/jpeg") {
+        orient_jpeg(path, &temp_path, orientation)
+    } else {
+        run(Command::new("magick").arg(path).arg("-auto-orient").arg(&temp_path), "ImageMagick (magick)", path)
+    };
+    if let Err(e) = written {
+        let _ = fs::remove_file(&temp_path);
+        return Err(e);
+    }
+
+    if let Err(e) = fs::rename(&temp_path, path) {
+        let _ = fs::remove_file(&temp_path);
+        bail!("Failed to replace {} with its oriented version: {}", path.display(), e);
+    }
+    Ok(())
+}
+
+/// Write `path` upright to `temp_path` without re-encoding it. `-perfect`
+/// makes jpegtran fail rather than drop the edge blocks of an image whose
+/// size isn't a whole number of them
+fn orient_jpeg(path: &Path, temp_path: &Path, orientation: &str) -> Result<()> {
+    let transform = jpegtran_transform(orientation)
+        .with_context(|| format!("Unknown orientation '{}'", orientation))?;
+    run(
+        Command::new("jpegtran")
+            .args(["-copy", "all", "-perfect"])
+            .args(transform)
+            .arg("-outfile")
+            .arg(temp_path)
+            .arg(path),
+        "jpegtran",
+        path,
+    )?;
+    // jpegtran copies the EXIF as it was, Orientation included
+    run(
+        Command::new("exiftool")
+            .args(["-q", "-n", "-Orientation=1", "-overwrite_original"])
+            .arg(temp_path),
+        "exiftool",
+        path,
+    )
+}
+
+/// Run one step of orienting `path`, failing with its stderr if it does
+fn run(command: &mut Command, tool: &str, path: &Path) -> Result<()> {
+    let output = command
+        .output()
+        .with_context(|| format!("Failed to run {} for --auto-orient", tool))?;
+    if !output.status.success() {
+        bail!(
+            "{} failed to orient {}: {}",
+            tool,
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_needs_orienting() {
+        assert!(!needs_orienting(None));
+        assert!(!needs_orienting(Some("Horizontal (normal)")));
+        assert!(needs_orienting(Some("Rotate 90 CW")));
+        assert!(needs_orienting(Some("Mirror horizontal")));
+    }
+
+    #[test]
+    fn test_jpegtran_transform() {
+        assert_eq!(jpegtran_transform("Rotate 90 CW"), Some(&["-rotate", "90"][..]));
+        assert_eq!(jpegtran_transform("Mirror horizontal and rotate 270 CW"), Some(&["-transpose"][..]));
+        assert_eq!(jpegtran_transform("Horizontal (normal)"), None);
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::filename::{get_extension, normalize_extension};
+
+/// File types that win a same-stem group, highest priority first
+pub const DEFAULT_PAIR_PRIORITY: &[&str] = &["DNG", "HEIC", "JPG"];
+
+/// What happens to the lower-priority files of a same-stem group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreviewPolicy {
+    /// Leave previews in place and don't archive them
+    Skip,
+    /// Archive previews next to their primary, under the primary's name
+    Sidecar,
+}
+
+impl PreviewPolicy {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "skip" => Ok(PreviewPolicy::Skip),
+            "sidecar" => Ok(PreviewPolicy::Sidecar),
+            other => bail!("--pair-previews expects skip or sidecar, got '{}'", other),
+        }
+    }
+}
+
+/// A primary file and the previews shot alongside it, as indices into the
+/// list given to `find_pairs`
+#[derive(Debug, PartialEq, Eq)]
+pub struct PairGroup {
+    pub primary: usize,
+    pub previews: Vec<usize>,
+}
+
+/// Rank of a file's type in the priority list (lower wins), if it has one
+fn rank(path: &Path, priority: &[String]) -> Option<usize> {
+    let extension = normalize_extension(&get_extension(path)?);
+    priority.iter().position(|p| *p == extension)
+}
+
+/// Group files sharing a directory and (case-insensitive) stem whose types
+/// are all in `priority`, e.g. `IMG_0001.DNG` with `IMG_0001.JPG`. Each group
+/// of two or more names its highest-priority file as the primary; on a tie
+/// the earlier file in `paths` wins
+pub fn find_pairs(paths: &[PathBuf], priority: &[String]) -> Vec<PairGroup> {
+    let mut groups: HashMap<(PathBuf, String), Vec<(usize, usize)>> = HashMap::new();
+    for (index, path) in paths.iter().enumerate() {
+        let Some(rank) = rank(path, priority) else {
+            continue;
+        };
+        let (Some(parent), Some(stem)) = (path.parent(), path.file_stem().and_then(|s| s.to_str())) else {
+            continue;
+        };
+        groups
+            .entry((parent.to_path_buf(), stem.to_lowercase()))
+            .or_default()
+            .push((rank, index));
+    }
+
+    let mut pairs: Vec<PairGroup> = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|mut members| {
+            members.sort();
+            let primary = members[0].1;
+            let mut previews: Vec<usize> = members[1..].iter().map(|&(_, index)| index).collect();
+            previews.sort();
+            PairGroup { primary, previews }
+        })
+        .collect();
+    pairs.sort_by_key(|group| group.primary);
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_pairs() {
+        let priority: Vec<String> = DEFAULT_PAIR_PRIORITY.iter().map(|s| s.to_string()).collect();
+        let paths: Vec<PathBuf> = [
+            "/card/IMG_0001.DNG",
+            "/card/IMG_0001.JPG",
+            "/card/IMG_0002.jpeg",
+            "/card/img_0002.HEIC",
+            "/card/IMG_0003.JPG",
+            "/card/IMG_0004.MOV",
+            "/card/IMG_0004.JPG",
+            "/other/IMG_0003.DNG",
+        ]
+        .iter()
+        .map(PathBuf::from)
+        .collect();
+
+        assert_eq!(
+            find_pairs(&paths, &priority),
+            vec![
+                PairGroup { primary: 0, previews: vec![1] },
+                PairGroup { primary: 3, previews: vec![2] },
+            ]
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use anyhow::{bail, Context, Result};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::filename::{get_extension, normalize_extension};
+
+/// Width and height of the grayscale thumbnail a difference hash is taken
+/// from; each row gives 8 bits from its 9 pixels
+const HASH_WIDTH: usize = 9;
+const HASH_HEIGHT: usize = 8;
+
+/// Library file types hashed when a destination folder is first checked
+const IMAGE_EXTENSIONS: &[&str] = &["JPG", "HEIC", "HEIF", "PNG", "TIF", "TIFF", "DNG", "GIF", "WEBP"];
+
+/// Largest possible distance between two 64-bit hashes
+pub const MAX_DISTANCE: u32 = 64;
+
+/// Compute a 64-bit difference hash (dHash) of an image: shrink it to 9x8
+/// grayscale with ImageMagick and record whether each pixel is brighter than
+/// its right-hand neighbour. Resized or re-compressed copies of a shot hash
+/// within a few bits of each other
+pub fn dhash(path: &Path) -> Result<u64> {
+    // Only the first frame of multi-page or animated files
+    let mut input = OsString::from(path);
+    input.push("[0]");
+
+    let output = Command::new("magick")
+        .arg(&input)
+        .args(["-colorspace", "Gray", "-resize", "9x8!", "-depth", "8", "gray:-"])
+        .output()
+        .context("Failed to run ImageMagick (magick) for --phash-dedupe")?;
+
+    if !output.status.success() {
+        bail!(
+            "ImageMagick failed to read {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    if output.stdout.len() != HASH_WIDTH * HASH_HEIGHT {
+        bail!("ImageMagick returned {} bytes of pixels for {}", output.stdout.len(), path.display());
+    }
+    Ok(dhash_pixels(&output.stdout))
+}
+
+/// dHash of 9x8 8-bit grayscale pixels, row by row
+fn dhash_pixels(pixels: &[u8]) -> u64 {
+    let mut hash = 0u64;
+    for row in pixels.chunks(HASH_WIDTH) {
+        for pair in row.windows(2) {
+            hash = (hash << 1) | u64::from(pair[0] > pair[1]);
+        }
+    }
+    hash
+}
+
+/// Number of bits two hashes differ in
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
+
+/// Hash the images directly in a library folder, skipping any that can't be read
+pub fn hash_folder(dir: &Path) -> Vec<(PathBuf, u64)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            get_extension(path).is_some_and(|ext| IMAGE_EXTENSIONS.contains(&normalize_extension(&ext).as_str()))
+        })
+        .filter_map(|path| dhash(&path).ok().map(|hash| (path, hash)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dhash_pixels() {
+        // A left-to-right gradient never gets darker, so no bits are set
+        let gradient: Vec<u8> = (0..HASH_HEIGHT).flat_map(|_| (0..HASH_WIDTH as u8).map(|x| x * 10)).collect();
+        assert_eq!(dhash_pixels(&gradient), 0);
+
+        // Slightly brightened, as a re-compressed copy might be
+        let brighter: Vec<u8> = gradient.iter().map(|p| p + 3).collect();
+        assert_eq!(distance(dhash_pixels(&gradient), dhash_pixels(&brighter)), 0);
+
+        let reversed: Vec<u8> = gradient.iter().map(|p| 255 - p).collect();
+        assert_eq!(dhash_pixels(&reversed), u64::MAX);
+        assert_eq!(distance(0, u64::MAX), MAX_DISTANCE);
+    }
+}
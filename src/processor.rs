@@ -1,38 +1,245 @@
 use anyhow::{Context, Result};
-use crossbeam_channel::{bounded, Sender, Receiver};
-use exiftool::ExifTool;
-use std::collections::HashMap;
+use crossbeam_channel::{bounded, Receiver, RecvTimeoutError, Sender};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::io::{self, Write};
-use std::os::unix::fs::MetadataExt;
+use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{Instant, SystemTime};
 use walkdir::WalkDir;
 
-use crate::failed::handle_failed_file;
-use crate::filename::{generate_filename, generate_filename_without_counter, get_extension};
-use crate::metadata::{extract_dates_batch, MediaDates};
+use crate::archive::{extract_archive, is_archive};
+use crate::copy::partial_path;
+use crate::decode::decode_error;
+use crate::events::ProcessEvent;
+use crate::failed::{available_name, handle_failed_file};
+use crate::fileops::{FileOps, RealFs};
+use crate::filename::{
+    check_path_length, dates_precede_name, generate_filename, get_extension, normalize_extension, output_extension, same_file_type,
+    too_many_collisions, NameFormat, DEFAULT_MAX_COLLISIONS,
+};
+use crate::hash::readers_equal;
+use crate::hook::ExecHook;
+use crate::ignore::IgnoreList;
+use crate::library::{find_duplicate_groups, LibraryIndex};
+use crate::metadata::{
+    extract_dates_batch, ExtractOptions, MediaDates, MediaInfo, MediaKind, MetadataReader, ShotIdentity,
+};
+use crate::mtp::{copy_from_device, is_mtp_input};
+use crate::organize::{date_folder, location_folder, DEFAULT_LOCATION_GRID};
+use crate::orient::{auto_orient, needs_orienting};
+use crate::pairs::{find_pairs, PreviewPolicy, DEFAULT_PAIR_PRIORITY};
+use crate::phash::{dhash, distance, hash_folder};
+use crate::provenance::ProvenanceWriter;
+use crate::resume::{read_transfer_log, remove_partial_copies, TransferLog};
+use crate::settings::{ConflictPolicy, DedupeBy, DedupeScope, FailurePolicy, LinkMode, Settings, SummaryFormat};
+use crate::sidecar::{sidecar_json, sidecar_path};
+use crate::style::{self, Style};
 
 const INITIAL_BATCH_SIZE: usize = 50;
 const BATCH_SIZE_INCREMENT: usize = 10;
 const MAX_BATCH_SIZE: usize = 1000;
+/// Files between progress lines, unless --stats-interval says otherwise
+const DEFAULT_STATS_INTERVAL: usize = 100;
+/// Largest source file read when --max-read-size isn't given
+const DEFAULT_MAX_READ_SIZE: u64 = 64 * 1024 * 1024 * 1024;
 
-/// Check if two paths are on the same filesystem volume
-fn is_same_volume(path1: &Path, path2: &Path) -> Result<bool> {
-    let meta1 = fs::metadata(path1)
-        .with_context(|| format!("Failed to get metadata for {}", path1.display()))?;
-    let meta2 = fs::metadata(path2)
-        .with_context(|| format!("Failed to get metadata for {}", path2.display()))?;
+/// Check if a file or directory name is hidden (starts with `.`)
+fn is_hidden(name: &std::ffi::OsStr) -> bool {
+    name.as_encoded_bytes().starts_with(b".")
+}
+
+/// Check if a file is Finder clutter that shouldn't keep a source folder
+/// alive (`.DS_Store` and AppleDouble `._*` files)
+fn is_folder_clutter(name: &std::ffi::OsStr) -> bool {
+    name.to_str().is_some_and(|name| name == ".DS_Store" || name.starts_with("._"))
+}
+
+/// Remove the folders under `root` (not `root` itself) that are empty or hold
+/// only Finder clutter, deepest first, so a folder emptied by removing its
+/// subfolders goes too. Returns how many folders were removed
+fn prune_empty_dirs(root: &Path) -> usize {
+    let mut removed = 0;
+    for entry in WalkDir::new(root).min_depth(1).contents_first(true).into_iter().flatten() {
+        if !entry.file_type().is_dir() {
+            continue;
+        }
+        let dir = entry.path();
+        let Ok(children) = fs::read_dir(dir) else {
+            continue;
+        };
+        let children: Vec<fs::DirEntry> = children.flatten().collect();
+        let only_clutter = children
+            .iter()
+            .all(|child| child.file_type().is_ok_and(|t| t.is_file()) && is_folder_clutter(&child.file_name()));
+        if !only_clutter {
+            continue;
+        }
+
+        for child in &children {
+            let _ = fs::remove_file(child.path());
+        }
+        match fs::remove_dir(dir) {
+            Ok(()) => removed += 1,
+            Err(e) => eprintln!("Warning: Failed to remove emptied folder {}: {}", dir.display(), e),
+        }
+    }
+    removed
+}
+
+/// Count, for each pair of inputs, the files of the first (by content hash)
+/// whose content is also in the second, with each input's file count
+/// (`totals`) on the diagonal. Also returns, per input, how many of its files
+/// are in any other input
+fn overlap_matrix(totals: &[usize], hashes: &[Vec<String>]) -> (Vec<Vec<usize>>, Vec<usize>) {
+    let sets: Vec<HashSet<&String>> = hashes.iter().map(|input| input.iter().collect()).collect();
+
+    let mut matrix = vec![vec![0; totals.len()]; totals.len()];
+    let mut in_others = vec![0; totals.len()];
+    for (row, input) in hashes.iter().enumerate() {
+        matrix[row][row] = totals[row];
+        for hash in input {
+            let mut shared = false;
+            for (column, set) in sets.iter().enumerate() {
+                if column != row && set.contains(hash) {
+                    matrix[row][column] += 1;
+                    shared = true;
+                }
+            }
+            in_others[row] += usize::from(shared);
+        }
+    }
+    (matrix, in_others)
+}
+
+/// Check if an error was caused by an IO permission denied error
+fn is_permission_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .map(|e| e.kind() == io::ErrorKind::PermissionDenied)
+            .unwrap_or(false)
+    })
+}
+
+/// Check if an error was caused by the destination running out of space (ENOSPC)
+fn is_disk_full_error(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<io::Error>()
+            .map(|e| e.kind() == io::ErrorKind::StorageFull)
+            .unwrap_or(false)
+    })
+}
+
+/// Give the owner read/write on a file and full access to its directory, so a
+/// move or copy-and-delete can succeed (--fix-perms)
+fn add_owner_permissions(file_path: &Path) -> Result<()> {
+    let mut targets = vec![(file_path, 0o600)];
+    if let Some(parent) = file_path.parent() {
+        targets.push((parent, 0o700));
+    }
+
+    for (path, bits) in targets {
+        let mut permissions = fs::metadata(path)
+            .with_context(|| format!("Failed to get metadata for {}", path.display()))?
+            .permissions();
+        permissions.set_mode(permissions.mode() | bits);
+        fs::set_permissions(path, permissions)
+            .with_context(|| format!("Failed to change permissions of {}", path.display()))?;
+    }
 
-    // Compare device IDs (st_dev on Unix)
-    Ok(meta1.dev() == meta2.dev())
+    Ok(())
 }
 
 pub struct Processor {
     output_dir: PathBuf,
     failed_cases_dir: PathBuf,
+    settings: Settings,
     stats: Arc<Mutex<ProcessingStats>>,
+    /// Destination paths claimed by transfers that are still in flight
+    reserved: Mutex<HashSet<PathBuf>>,
+    /// Filesystem used for duplicate checks and transfers
+    file_ops: Arc<dyn FileOps>,
+    /// Command run on each archived file (--exec)
+    exec_hook: Option<ExecHook>,
+    /// Records each archived file's source in its XMP (--tag-provenance)
+    provenance: Option<ProvenanceWriter>,
+    /// Perceptual hashes of the images in each destination folder, including
+    /// those archived this run (--phash-dedupe)
+    phash_index: Mutex<HashMap<PathBuf, Vec<(PathBuf, u64)>>>,
+    /// Content index of the whole library, built on first use
+    /// (--dedupe-scope global)
+    library_index: Mutex<Option<LibraryIndex>>,
+    /// File names skipped as system metadata (--ignore-name)
+    ignored: IgnoreList,
+    /// Log of each transfer as it happens, for --resume (not kept in
+    /// report-only runs, or on a filesystem other than the real one)
+    transfer_log: Option<TransferLog>,
+    /// Destination names --dry-run has given out, and the source each would
+    /// hold
+    planned_names: Mutex<HashMap<PathBuf, PathBuf>>,
+    /// Each source archived this run by its size and hash, so a later
+    /// identical source is recognized at once
+    run_sources: Mutex<HashMap<(u64, String), RunSource>>,
+    /// The library file of each shot archived this run (--dedupe-by identity)
+    run_shots: Mutex<HashMap<ShotIdentity, PathBuf>>,
+    /// Source folders on read-only volumes, whose files are copied and left
+    /// in place
+    read_only_dirs: HashSet<PathBuf>,
+    /// Where per-file events go, for embedders (None: not reported)
+    events: Option<Sender<ProcessEvent>>,
+    /// Stream of JSON progress lines (--progress-fd)
+    progress_out: Mutex<Option<fs::File>>,
+    /// Where the JSON summary goes instead of stdout (None: stdout)
+    summary_out: Option<fs::File>,
+}
+
+/// Why a file found in the inputs was left out of the run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SkipReason {
+    /// A dotfile, or inside a dot-directory (which counts once, as it isn't
+    /// descended into)
+    Hidden,
+    /// Matched a system metadata name (--ignore-name)
+    Ignored,
+    /// A symlink, with --skip-symlinks
+    Symlink,
+    /// Archived by an earlier run (--resume)
+    Resumed,
+    /// A same-shot preview, with --pair-previews skip
+    Preview,
+    /// Left out by --only-with-gps or --only-without-gps
+    Gps,
+    /// Larger than --max-read-size
+    TooLarge,
+}
+
+impl SkipReason {
+    const ALL: [SkipReason; 7] = [
+        SkipReason::Ignored,
+        SkipReason::Hidden,
+        SkipReason::Symlink,
+        SkipReason::Resumed,
+        SkipReason::Preview,
+        SkipReason::Gps,
+        SkipReason::TooLarge,
+    ];
+
+    /// How the summary describes `count` files skipped for this reason
+    fn describe(self, count: usize) -> String {
+        match self {
+            SkipReason::Hidden => format!("{} hidden", count),
+            SkipReason::Ignored => format!("{} system files", count),
+            SkipReason::Symlink => format!("{} symlinks", count),
+            SkipReason::Resumed => format!("{} archived by an earlier run", count),
+            SkipReason::Preview => format!("{} same-shot previews", count),
+            SkipReason::Gps => format!("{} not matching the GPS filter", count),
+            SkipReason::TooLarge => format!("{} larger than --max-read-size", count),
+        }
+    }
 }
 
 #[derive(Debug, Default)]
@@ -42,44 +249,284 @@ pub struct ProcessingStats {
     pub copied: usize,
     pub skipped: usize,
     pub failed: usize,
+    /// Failed files by upper-cased extension ("" for none), for --list-unsupported
+    pub failed_by_extension: BTreeMap<String, usize>,
     pub duplicates: Vec<(PathBuf, PathBuf)>, // (source_path, destination_path)
+    /// Sources identical to one archived earlier in the run (included in `skipped`)
+    pub duplicates_in_run: usize,
+    /// Library files renamed to a duplicate source's earlier date (--keep-earliest-date)
+    pub redated: usize,
+    /// (library file, earlier library file) pairs archived this run that are
+    /// the same shot but not the same bytes (--dedupe-by identity)
+    pub same_shots: Vec<(PathBuf, PathBuf)>,
+    /// Files archived into the Review folder (included in `moved`/`copied`)
+    pub review: usize,
+    /// Images that failed --validate-decode, archived into the Corrupt folder
+    /// (included in `moved`/`copied`)
+    pub corrupt: usize,
+    /// Failed transfers caused by permission denied errors (included in `failed`)
+    pub permission_errors: usize,
+    /// Files handed to the workers that never came back, as when none of them
+    /// could start exiftool (included in `failed`)
+    pub unread: usize,
+    /// (content hash, destination) of every file written this run, for --manifest
+    pub manifest: Vec<(String, PathBuf)>,
+    /// Number of files found before --limit truncated the run
+    pub limited_from: Option<usize>,
+    /// Files left out of the run, by why they were filtered
+    pub filtered: HashMap<SkipReason, usize>,
+    /// Zero-byte source files, which are never archived
+    pub empty: Vec<PathBuf>,
+    /// Source files grouped by content hash (--dedupe-report-only)
+    pub source_hashes: HashMap<String, Vec<PathBuf>>,
+    /// Files moved into the Replaced folder by --on-conflict
+    pub replaced: usize,
+    /// (source, destination) of every file archived this run, in order
+    pub transfers: Vec<(PathBuf, PathBuf)>,
+    /// Why the run was stopped early, if it was
+    pub aborted: Option<String>,
+    /// Same-shot previews archived beside their primary (--pair-previews sidecar)
+    pub previews_archived: usize,
+    /// Copied sources whose deletion waits for the end-of-run prompt
+    /// (--confirm-deletes)
+    pub deferred_deletes: Vec<PathBuf>,
+    /// Files copied from a read-only volume and left there (included in
+    /// `copied`)
+    pub kept_read_only: usize,
+    /// Files archived as a hard link to their source (--link hardlink;
+    /// included in `moved`)
+    pub hardlinked: usize,
+    /// Images rotated upright by --auto-orient (included in `moved`/`copied`)
+    pub oriented: usize,
+    /// Files whose source was written into their XMP (--tag-provenance)
+    pub provenance_tagged: usize,
+    /// Files given the extension of their detected type (--fix-extension)
+    pub extensions_fixed: usize,
+    /// --exec commands that failed to start or exited unsuccessfully
+    pub exec_failures: usize,
+    /// Outcomes broken down by the input each file came from
+    pub by_source: BTreeMap<PathBuf, SourceStats>,
+    /// Paths that couldn't be read while scanning, so were never considered
+    pub scan_errors: usize,
+    /// (image, similar library image, hash distance) found by --phash-dedupe
+    pub near_duplicates: Vec<(PathBuf, PathBuf, u32)>,
+    /// (file, earliest of its dates before the date policy's
+    /// `suspicious_before`) for the files warned about while processing
+    pub suspicious_dates: Vec<(PathBuf, chrono::DateTime<chrono::Utc>)>,
+    /// What --dry-run found would become of each source file
+    pub planned: Vec<(PathBuf, Plan)>,
+}
+
+/// What --dry-run found would become of a source file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Plan {
+    /// Archived under its generated name
+    New(PathBuf),
+    /// Skipped as identical to this library file (or a source planned earlier)
+    DuplicateOf(PathBuf),
+    /// Archived under this higher-numbered name, as a different file has the
+    /// first one
+    WouldCollide(PathBuf),
+}
+
+/// (files, bytes) of the --dry-run sources that would be skipped as duplicates
+#[derive(Debug, Default, PartialEq, Eq)]
+struct DuplicateSavings {
+    in_library: (usize, u64),
+    in_sources: (usize, u64),
+}
+
+/// How a completed run went, for the process exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// Every file found was archived or skipped as a duplicate
+    Success,
+    /// Some files failed (more than --tolerate-failures)
+    Failures,
+    /// Some paths couldn't be scanned, so files may have been missed
+    ScanErrors,
 }
 
+impl RunOutcome {
+    /// 0 for success, 2 for failed files, 3 for scan errors (1 is reserved
+    /// for fatal errors)
+    pub fn exit_code(self) -> i32 {
+        match self {
+            RunOutcome::Success => 0,
+            RunOutcome::Failures => 2,
+            RunOutcome::ScanErrors => 3,
+        }
+    }
+}
+
+/// Name of the folder under the output that failed files are linked into,
+/// unless --failed-name gives another
+pub const DEFAULT_FAILED_NAME: &str = "Failed Cases";
+
+/// Folder under the output that images failing --validate-decode go into
+const CORRUPT_FOLDER: &str = "Corrupt";
+
 /// Work item sent to worker threads
-type WorkItem = (PathBuf, bool); // (file_path, should_move)
+#[derive(Debug, Clone)]
+struct WorkItem {
+    path: PathBuf,
+    /// Directory of the file relative to its input root (--preserve-structure)
+    relative_dir: PathBuf,
+    /// The input directory, archive, or file list the file came from
+    source: PathBuf,
+    /// Lower-priority files of the same shot to archive alongside this one
+    /// (--pair-previews sidecar)
+    previews: Vec<PathBuf>,
+}
+
+impl WorkItem {
+    /// A file with no input-root subdirectory
+    fn new(path: PathBuf, source: &Path) -> Self {
+        WorkItem {
+            path,
+            relative_dir: PathBuf::new(),
+            source: source.to_path_buf(),
+            previews: Vec::new(),
+        }
+    }
+}
+
+/// What happened to a file handed back by the workers, with where it now
+/// lives in the library (or the library file it duplicates)
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Outcome {
+    Moved(PathBuf),
+    Copied(PathBuf),
+    Skipped(PathBuf),
+    Failed,
+}
+
+/// Per-input counts shown in the summary
+#[derive(Debug, Default)]
+pub struct SourceStats {
+    pub moved: usize,
+    pub copied: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
 
 /// Result sent back from worker threads
 #[derive(Debug)]
 struct WorkerResult {
-    original_path: PathBuf,
+    item: WorkItem,
     result: Result<ProcessedFile>,
 }
 
 #[derive(Debug)]
 struct ProcessedFile {
-    dates: MediaDates,
+    info: MediaInfo,
     extension: String,
-    should_move: bool,
+    /// Why the image failed to decode (--validate-decode)
+    decode_error: Option<String>,
 }
 
 impl Processor {
-    pub fn new(output_dir: PathBuf) -> Result<Self> {
-        // Create output directory if it doesn't exist
-        fs::create_dir_all(&output_dir)
-            .with_context(|| format!("Failed to create output directory: {}", output_dir.display()))?;
+    pub fn new(output_dir: PathBuf, settings: Settings) -> Result<Self> {
+        let file_ops = RealFs::new(settings.copy_buffer_size, settings.max_rate);
+        let mut processor = Self::with_file_ops(output_dir, settings, Arc::new(file_ops))?;
+        if !processor.settings.read_only() {
+            processor.transfer_log = Some(TransferLog::open(&processor.output_dir)?);
+        }
+        if let Some(fd) = processor.settings.progress_fd {
+            processor.progress_out = Mutex::new(Some(open_progress_fd(fd)?));
+        }
+        Ok(processor)
+    }
 
-        // Create "Failed Cases" directory
-        let failed_cases_dir = output_dir.join("Failed Cases");
-        fs::create_dir_all(&failed_cases_dir)
-            .with_context(|| format!("Failed to create failed cases directory: {}", failed_cases_dir.display()))?;
+    /// Create a processor that checks for duplicates and transfers files
+    /// through `file_ops` instead of the real filesystem
+    pub fn with_file_ops(output_dir: PathBuf, settings: Settings, file_ops: Arc<dyn FileOps>) -> Result<Self> {
+        let failed_cases_dir = output_dir.join(settings.failed_name.as_deref().unwrap_or(DEFAULT_FAILED_NAME));
+
+        // Report-only runs must not write to the output
+        if !settings.read_only() {
+            // Create output directory if it doesn't exist
+            file_ops
+                .create_dir_all(&output_dir)
+                .with_context(|| format!("Failed to create output directory: {}", output_dir.display()))?;
+
+            // Create the Failed Cases directory
+            file_ops
+                .create_dir_all(&failed_cases_dir)
+                .with_context(|| format!("Failed to create failed cases directory: {}", failed_cases_dir.display()))?;
+
+            // Both may already have existed on a read-only share, so find out
+            // now rather than from every file failing after the scan
+            for dir in [&output_dir, &failed_cases_dir] {
+                file_ops
+                    .probe_writable(dir)
+                    .with_context(|| format!("Output directory is not writable: {}", dir.display()))?;
+            }
+        }
+
+        let exec_hook = match &settings.exec {
+            Some(command) => Some(ExecHook::new(command, (num_cpus::get() / 2).max(1))?),
+            None => None,
+        };
+        let provenance = match settings.tag_provenance && !settings.read_only() {
+            true => Some(ProvenanceWriter::new()?),
+            false => None,
+        };
 
         Ok(Processor {
             output_dir,
             failed_cases_dir,
             stats: Arc::new(Mutex::new(ProcessingStats::default())),
+            reserved: Mutex::new(HashSet::new()),
+            file_ops,
+            exec_hook,
+            provenance,
+            phash_index: Mutex::new(HashMap::new()),
+            library_index: Mutex::new(None),
+            ignored: IgnoreList::new(&settings.ignore_names)?,
+            transfer_log: None,
+            planned_names: Mutex::new(HashMap::new()),
+            run_sources: Mutex::new(HashMap::new()),
+            run_shots: Mutex::new(HashMap::new()),
+            read_only_dirs: HashSet::new(),
+            events: None,
+            progress_out: Mutex::new(None),
+            summary_out: None,
+            settings,
         })
     }
 
+    /// Report each file's progress through the run on `sender`, as it
+    /// happens. Events are dropped once the receiver is gone
+    pub fn set_event_sender(&mut self, sender: Sender<ProcessEvent>) {
+        self.events = Some(sender);
+    }
+
+    /// Write the JSON summary to `out` instead of stdout, e.g. the real
+    /// stdout once everything else printed there has been diverted
+    pub fn set_summary_output(&mut self, out: fs::File) {
+        self.summary_out = Some(out);
+    }
+
+    /// Write one --progress-fd line; the stream is dropped with a warning
+    /// if it can't be written, e.g. once its reader has gone
+    fn write_progress_line(&self, done: usize, total: usize, path: &Path) {
+        let mut out = self.progress_out.lock().unwrap();
+        let Some(file) = out.as_mut() else {
+            return;
+        };
+        if let Err(e) = writeln!(file, "{}", progress_line(done, total, path)) {
+            eprintln!("Warning: Failed to write to --progress-fd, no longer writing to it: {}", e);
+            *out = None;
+        }
+    }
+
+    fn emit(&self, event: ProcessEvent) {
+        if let Some(events) = &self.events {
+            let _ = events.send(event);
+        }
+    }
+
     pub fn process_directories(&mut self, input_dirs: &[PathBuf]) -> Result<()> {
         println!("Starting media collection...");
         println!("Output directory: {}", self.output_dir.display());
@@ -87,492 +534,3906 @@ impl Processor {
 
         // Collect all files from all directories upfront
         let mut all_files = Vec::new();
+        let mut archives = Vec::new();
+        // Plain input folders, scanned together once archives are extracted
+        let mut directories = Vec::new();
         for input_dir in input_dirs {
-            println!("Scanning directory: {}", input_dir.display());
-            let files = self.collect_files(input_dir)?;
-            all_files.extend(files);
-        }
+            if is_archive(input_dir) {
+                println!("Extracting archive: {}", input_dir.display());
+                let archive = extract_archive(input_dir, |path| self.ignored.matches(path))?;
+                // Archives are scanned in full, whatever --max-depth says
+                all_files.extend(self.collect_files(archive.dir(), input_dir, usize::MAX)?);
+                archives.push(archive);
+                continue;
+            }
+            if is_mtp_input(input_dir) {
+                println!("Copying from MTP device");
+                let staged = copy_from_device(|path| self.ignored.matches(path))?;
+                all_files.extend(self.collect_files(staged.dir(), input_dir, usize::MAX)?);
+                archives.push(staged);
+                continue;
+            }
 
-        let total_files = all_files.len();
-        {
-            let mut stats = self.stats.lock().unwrap();
-            stats.total_files = total_files;
+            println!("Scanning directory: {}", input_dir.display());
+            directories.push((input_dir.as_path(), input_dir.as_path()));
         }
-        println!("Found {} files to process", total_files);
-        println!();
+        all_files.extend(self.scan_trees(&directories, self.scan_depth()));
 
-        if total_files == 0 {
-            self.print_summary();
+        if self.settings.analyze_overlap {
+            self.print_overlap(input_dirs, &all_files);
             return Ok(());
         }
 
-        // Process files in parallel
-        self.process_files_parallel(all_files)?;
-
-        self.print_summary();
-        Ok(())
-    }
+        let result = self.process_work_items(all_files);
 
-    fn collect_files(&self, dir: &Path) -> Result<Vec<WorkItem>> {
-        // Check if this directory is on the same volume as the output
-        let same_volume = is_same_volume(dir, &self.output_dir).unwrap_or(false);
+        if result.is_ok() && self.settings.prune_empty_sources && !self.settings.read_only() {
+            let removed: usize = input_dirs
+                .iter()
+                .filter(|dir| !is_archive(dir) && !is_mtp_input(dir))
+                .map(|dir| prune_empty_dirs(dir))
+                .sum();
+            println!("Removed {} emptied source folder(s)", removed);
+        }
 
-        if same_volume {
-            println!("  → Same volume detected, files will be moved (not copied)");
+        // Failed Cases links point into the extracted files, so keep them if anything failed
+        if self.stats.lock().unwrap().failed > 0 {
+            for archive in &mut archives {
+                archive.keep();
+                println!("Kept extracted files in {}", archive.dir().display());
+            }
         }
 
-        let mut files = Vec::new();
+        result
+    }
 
-        for entry_result in WalkDir::new(dir)
-            .max_depth(1)
-            .min_depth(1)
-            .into_iter()
-        {
-            let entry = match entry_result {
-                Ok(e) => e,
-                Err(err) => {
-                    // Handle WalkDir errors
-                    if let Some(path) = err.path() {
-                        eprintln!("Warning: Failed to access {}: {}", path.display(), err);
-                    } else {
-                        eprintln!("Warning: WalkDir error: {}", err);
-                    }
-                    continue;
-                }
-            };
+    /// Process an explicit list of files (one path per line) read from
+    /// `list_path`, or from stdin when it is `-`, instead of scanning directories
+    pub fn process_file_list(&mut self, list_path: &Path) -> Result<()> {
+        println!("Starting media collection...");
+        println!("Output directory: {}", self.output_dir.display());
+        println!();
 
-            let path = entry.path();
+        let contents = if list_path == Path::new("-") {
+            println!("Reading file list from stdin");
+            io::read_to_string(io::stdin()).context("Failed to read file list from stdin")?
+        } else {
+            println!("Reading file list: {}", list_path.display());
+            fs::read_to_string(list_path)
+                .with_context(|| format!("Failed to read file list: {}", list_path.display()))?
+        };
 
-            // Skip if not a file
-            if !path.is_file() {
+        let mut all_files = Vec::new();
+        for line in contents.lines() {
+            let line = line.trim_end_matches('\r');
+            if line.is_empty() {
                 continue;
             }
 
-            // Get filename for filtering
-            let filename = path.file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or("");
-
-            // Skip AppleDouble files (._*)
-            if filename.starts_with("._") {
+            let path = PathBuf::from(line);
+            if !path.is_file() {
+                self.stats.lock().unwrap().scan_errors += 1;
+                eprintln!("Warning: Listed file does not exist or is not a file: {}", path.display());
                 continue;
             }
-
-            // Skip .DS_Store files (macOS metadata)
-            if filename == ".DS_Store" {
+            if self.ignored.matches(&path) {
+                self.count_skipped(SkipReason::Ignored, 1);
                 continue;
             }
+            let Some(path) = self.resolve_symlink(&path) else {
+                continue;
+            };
 
-            // Skip AAE files (Apple's sidecar files for edits)
-            if let Some(ext) = path.extension() {
-                if ext.eq_ignore_ascii_case("aae") {
-                    continue;
-                }
-            }
-
-            files.push((path.to_path_buf(), same_volume));
+            all_files.push(WorkItem::new(path, list_path));
         }
 
-        Ok(files)
+        self.process_work_items(all_files)
     }
 
-    fn process_files_parallel(&self, files: Vec<WorkItem>) -> Result<()> {
-        // Determine number of worker threads (CPU cores / 2)
-        let num_workers = (num_cpus::get() / 2).max(1);
-        println!("Starting {} worker threads", num_workers);
+    fn process_work_items(&mut self, mut all_files: Vec<WorkItem>) -> Result<()> {
+        println!("Found {} files to process", all_files.len());
 
-        // Create channels
-        let (work_sender, work_receiver) = bounded::<WorkItem>(num_workers * 2);
-        let (result_sender, result_receiver) = bounded::<WorkerResult>(num_workers * 2);
+        // Dispatch in a stable order so re-runs see the same input sequence.
+        // Counters for same-date files are assigned as results come back from
+        // the parallel workers, so their numbering can still vary between runs
+        all_files.sort_by(|a, b| a.path.cmp(&b.path));
 
-        // Spawn worker threads
-        let mut worker_handles = Vec::new();
-        for worker_id in 0..num_workers {
-            let work_rx = work_receiver.clone();
-            let result_tx = result_sender.clone();
+        if !self.settings.read_only() {
+            self.find_read_only_dirs(&all_files);
+        }
 
-            let handle = thread::spawn(move || {
-                worker_thread(worker_id, work_rx, result_tx);
-            });
+        if self.settings.resume {
+            match remove_partial_copies(&self.output_dir, &self.failed_cases_dir) {
+                Ok(0) => {}
+                Ok(removed) => println!("Removed {} partial copies left by an interrupted run", removed),
+                Err(e) => eprintln!("Warning: Failed to clean up partial copies: {:#}", e),
+            }
+            all_files = self.skip_already_archived(all_files)?;
+        }
 
-            worker_handles.push(handle);
+        if let Some(policy) = self.settings.pair_previews {
+            all_files = self.group_pairs(all_files, policy);
         }
 
-        // Drop our copies of the channels
-        drop(work_receiver);
-        drop(result_sender);
+        all_files = self.skip_oversized(all_files);
 
-        // Send all work items to workers
-        let total_files = files.len();
-        thread::spawn(move || {
-            for work_item in files {
-                if work_sender.send(work_item).is_err() {
-                    break; // Workers have shut down
-                }
+        // Zero-byte files are usually broken downloads; keep them away from
+        // exiftool and from matching each other as "duplicates"
+        let (empty_files, mut all_files): (Vec<WorkItem>, Vec<WorkItem>) = all_files
+            .into_iter()
+            .partition(|item| self.file_ops.metadata(&item.path).is_ok_and(|meta| meta.len == 0));
+
+        // The limit counts only files that will actually be read, so it
+        // applies after the oversized and empty ones are set aside
+        if let Some(limit) = self.settings.limit {
+            if all_files.len() > limit {
+                let mut stats = self.stats.lock().unwrap();
+                stats.limited_from = Some(all_files.len());
+                all_files.truncate(limit);
+                println!("Limiting this run to the first {} files", limit);
             }
-            // Channel closes when work_sender is dropped
-        });
+        }
 
-        // Process results from workers
-        let mut processed = 0;
+        let total_files = empty_files.len() + all_files.len();
+        {
+            let mut stats = self.stats.lock().unwrap();
+            stats.total_files = total_files;
+        }
+        println!();
 
-        for worker_result in result_receiver {
-            processed += 1;
-            if processed % 100 == 0 {
-                println!("Progress: {}/{} files processed", processed, total_files);
+        self.handle_empty_files(empty_files);
+
+        if all_files.is_empty() {
+            self.print_summary();
+            self.confirm_deferred_deletes();
+            self.verify_output();
+            return Ok(());
+        }
+
+        // Process files in parallel
+        self.process_files_parallel(all_files)?;
+
+        let aborted = self.stats.lock().unwrap().aborted.clone();
+        if let Some(reason) = aborted {
+            if let Some(hook) = &self.exec_hook {
+                hook.wait_all();
+            }
+            eprintln!();
+            eprintln!("Stopping the run: {}", reason);
+            match self.write_partial_report(total_files, &reason) {
+                Ok(path) => eprintln!("Partial report written to {}", path.display()),
+                Err(e) => eprintln!("Error: Failed to write the partial report: {:#}", e),
             }
+            self.print_summary();
+            let kept = self.stats.lock().unwrap().deferred_deletes.len();
+            if kept > 0 {
+                eprintln!("Left the {} source files copied so far in place (--confirm-deletes)", kept);
+            }
+            anyhow::bail!("Run aborted: {}", reason);
+        }
 
-            self.handle_worker_result(worker_result);
+        if let Some(hook) = &self.exec_hook {
+            self.stats.lock().unwrap().exec_failures = hook.wait_all();
         }
 
-        // Wait for all workers to finish
-        for handle in worker_handles {
-            let _ = handle.join();
+        if let Some(manifest_path) = &self.settings.manifest {
+            if !self.settings.read_only() {
+                self.write_manifest(manifest_path)?;
+            }
         }
 
+        self.print_summary();
+        self.confirm_deferred_deletes();
+        self.verify_output();
         Ok(())
     }
 
-    fn handle_worker_result(
-        &self,
-        worker_result: WorkerResult,
-    ) {
-        let WorkerResult { original_path, result } = worker_result;
-
-        match result {
-            Ok(processed) => {
-                // Worker successfully extracted metadata
-                let ProcessedFile { dates, extension, should_move } = processed;
-
-                // Read source file content
-                let content = match fs::read(&original_path) {
-                    Ok(c) => c,
-                    Err(e) => {
-                        let mut stats = self.stats.lock().unwrap();
-                        stats.failed += 1;
-                        let err = anyhow::anyhow!("Failed to read file: {}", e);
-                        if let Err(handle_err) = handle_failed_file(&original_path, &self.failed_cases_dir, &err) {
-                            eprintln!("Error handling failed file: {}", handle_err);
-                        }
-                        return;
-                    }
-                };
-
-                // Check existing files on disk starting from counter 1
-                let mut check_counter = 1;
-                let mut found_duplicate = false;
+    /// With --confirm-deletes, ask once whether to delete the sources that
+    /// were copied into the library, now that the whole run has finished
+    fn confirm_deferred_deletes(&self) {
+        let sources = std::mem::take(&mut self.stats.lock().unwrap().deferred_deletes);
+        if sources.is_empty() {
+            return;
+        }
 
-                loop {
-                    let check_filename = generate_filename(&dates, &extension, check_counter);
-                    let check_path = self.output_dir.join(&check_filename);
+        println!();
+        println!("=== COPIED SOURCES (--confirm-deletes) ===");
+        let total_size: u64 = sources
+            .iter()
+            .filter_map(|source| self.file_ops.metadata(source).ok())
+            .map(|metadata| metadata.len)
+            .sum();
+        println!(
+            "{} source files were copied into the library ({:.2} MB)",
+            sources.len(),
+            total_size as f64 / 1_048_576.0
+        );
+        if self.settings.no_prompt {
+            println!("Left the {} copied source files in place (--no-prompt)", sources.len());
+            return;
+        }
+        print!("Delete these {} copied source files? (y/n): ", sources.len());
+        io::stdout().flush().unwrap();
 
-                    if !check_path.exists() {
-                        // File doesn't exist - this is the counter to use
-                        // No need to check higher counters (they won't exist either)
-                        break;
-                    }
+        let mut input = String::new();
+        let confirmed = io::stdin().read_line(&mut input).is_ok()
+            && matches!(input.trim().to_lowercase().as_str(), "y" | "yes");
+        if !confirmed {
+            println!();
+            println!("Copied source files were not deleted.");
+            return;
+        }
+        let mut deleted = 0;
+        let mut failed = 0;
+        for source in &sources {
+            match self.delete_source(source) {
+                Ok(()) => deleted += 1,
+                Err(e) => {
+                    failed += 1;
+                    style::eprint(Style::Failure, format_args!("✗ Failed to delete {}: {}", source.display(), e));
+                }
+            }
+        }
+        println!("Source cleanup complete: {} deleted, {} failed", deleted, failed);
+    }
 
-                    // File exists, check if it's a duplicate
-                    match fs::read(&check_path) {
-                        Ok(existing_content) => {
-                            if existing_content == content {
-                                // Duplicate found! Skip this file
-                                found_duplicate = true;
-                                let mut stats = self.stats.lock().unwrap();
-                                stats.skipped += 1;
-                                stats.duplicates.push((original_path.clone(), check_path.clone()));
-                                println!("- Skipped (already exists): {}", original_path.display());
-                                break;
-                            }
-                        }
-                        Err(e) => {
-                            eprintln!("Warning: failed to read {}: {}", check_path.display(), e);
-                        }
-                    }
+    /// With --verify-output, hash the whole library once the import is done
+    /// and list any byte-identical files, offering to delete all but the
+    /// first (by path) of each group
+    fn verify_output(&self) {
+        if !self.settings.verify_output || self.settings.read_only() {
+            return;
+        }
 
-                    // Not a duplicate, increment and check next counter
-                    check_counter += 1;
+        println!();
+        println!("=== VERIFYING OUTPUT (--verify-output) ===");
+        let skip = std::slice::from_ref(&self.failed_cases_dir);
+        let groups = find_duplicate_groups(&self.output_dir, skip, self.settings.checksum_algo);
+        if groups.is_empty() {
+            println!("No byte-identical files in {}", self.output_dir.display());
+            return;
+        }
 
-                    if check_counter > 10000 {
-                        // Safety limit
-                        let mut stats = self.stats.lock().unwrap();
-                        stats.failed += 1;
-                        let err = anyhow::anyhow!("Too many filename collisions for the same date pair");
-                        if let Err(handle_err) = handle_failed_file(&original_path, &self.failed_cases_dir, &err) {
-                            eprintln!("Error handling failed file: {}", handle_err);
-                        }
-                        return;
-                    }
-                }
+        for group in &groups {
+            println!();
+            println!("Identical ({} files):", group.len());
+            for path in group {
+                println!("  {}", path.display());
+            }
+        }
+        let extra: usize = groups.iter().map(|group| group.len() - 1).sum();
+        println!();
+        if self.settings.no_prompt {
+            println!("Kept all {} extra copies (--no-prompt)", extra);
+            return;
+        }
+        print!("Delete the {} extra copies, keeping the first file of each group? (y/n): ", extra);
+        io::stdout().flush().unwrap();
 
-                // If not a duplicate, transfer the file
-                if !found_duplicate {
-                    match self.transfer_file(&original_path, &dates, &extension, check_counter, should_move, &content) {
-                        Ok(ProcessResult::Moved) => {
-                            let mut stats = self.stats.lock().unwrap();
-                            stats.moved += 1;
-                            println!("✓ Moved: {}", original_path.display());
-                        }
-                        Ok(ProcessResult::Copied) => {
-                            let mut stats = self.stats.lock().unwrap();
-                            stats.copied += 1;
-                            println!("✓ Copied: {}", original_path.display());
-                        }
-                        Ok(ProcessResult::Skipped(dest_path)) => {
-                            let mut stats = self.stats.lock().unwrap();
-                            stats.skipped += 1;
-                            stats.duplicates.push((original_path.clone(), dest_path));
-                            println!("- Skipped (already exists): {}", original_path.display());
-                        }
-                        Err(e) => {
-                            let mut stats = self.stats.lock().unwrap();
-                            stats.failed += 1;
-                            if let Err(handle_err) = handle_failed_file(&original_path, &self.failed_cases_dir, &e) {
-                                eprintln!("Error handling failed file: {}", handle_err);
-                            }
-                        }
-                    }
+        let mut input = String::new();
+        let confirmed = io::stdin().read_line(&mut input).is_ok()
+            && matches!(input.trim().to_lowercase().as_str(), "y" | "yes");
+        if !confirmed {
+            println!("Library duplicates were not deleted.");
+            return;
+        }
+        let mut deleted = 0;
+        let mut failed = 0;
+        for path in groups.iter().flat_map(|group| &group[1..]) {
+            match self.delete_source(path) {
+                Ok(()) => {
+                    deleted += 1;
+                    style::print(Style::Success, format_args!("✓ Deleted: {}", path.display()));
                 }
-            }
-            Err(e) => {
-                // Worker failed to extract metadata
-                let mut stats = self.stats.lock().unwrap();
-                stats.failed += 1;
-                if let Err(handle_err) = handle_failed_file(&original_path, &self.failed_cases_dir, &e) {
-                    eprintln!("Error handling failed file: {}", handle_err);
+                Err(e) => {
+                    failed += 1;
+                    style::eprint(Style::Failure, format_args!("✗ Failed to delete {}: {}", path.display(), e));
                 }
             }
         }
+        println!("Library cleanup complete: {} deleted, {} failed", deleted, failed);
     }
 
-    fn transfer_file(
-        &self,
-        file_path: &Path,
-        dates: &MediaDates,
-        extension: &str,
-        counter: u32,
-        should_move: bool,
-        content: &[u8],
-    ) -> Result<ProcessResult> {
-        // Generate target filename with counter
-        let filename = generate_filename(dates, extension, counter);
-        let target_path = self.output_dir.join(&filename);
+    /// Print how many files each input shares with each other input
+    /// (--analyze-overlap). Only files whose size turns up in more than one
+    /// input can be shared, so only those are hashed
+    fn print_overlap(&self, input_dirs: &[PathBuf], files: &[WorkItem]) {
+        let sized: Vec<(usize, &Path, u64)> = files
+            .iter()
+            .filter_map(|item| {
+                let input = input_dirs.iter().position(|dir| *dir == item.source)?;
+                let len = self.file_ops.metadata(&item.path).ok()?.len;
+                // Zero-byte files would all match each other
+                (len > 0).then_some((input, item.path.as_path(), len))
+            })
+            .collect();
 
-        // File shouldn't exist at this point since we already checked
-        // But double-check just in case
-        if target_path.exists() {
-            let existing_content = fs::read(&target_path)
-                .with_context(|| format!("Failed to read existing file: {}", target_path.display()))?;
+        let mut inputs_by_len: HashMap<u64, HashSet<usize>> = HashMap::new();
+        for &(input, _, len) in &sized {
+            inputs_by_len.entry(len).or_default().insert(input);
+        }
 
-            if existing_content == content {
-                return Ok(ProcessResult::Skipped(target_path));
+        let mut totals = vec![0; input_dirs.len()];
+        let mut hashes: Vec<Vec<String>> = vec![Vec::new(); input_dirs.len()];
+        for &(input, path, len) in &sized {
+            totals[input] += 1;
+            if inputs_by_len[&len].len() < 2 {
+                continue;
+            }
+            match self.digest(path) {
+                Ok(digest) => hashes[input].push(digest.hash),
+                Err(e) => eprintln!("Warning: failed to read {}: {}", path.display(), e),
             }
         }
 
-        // Transfer file to destination (move or copy depending on volume)
-        if should_move {
-            // Use rename for same-volume transfers (fast, atomic)
-            fs::rename(file_path, &target_path)
-                .with_context(|| format!("Failed to move file to {}", target_path.display()))?;
-            Ok(ProcessResult::Moved)
-        } else {
-            // Use copy for cross-volume transfers
-            fs::copy(file_path, &target_path)
-                .with_context(|| format!("Failed to copy file to {}", target_path.display()))?;
-
-            // Delete source file after successful copy
-            fs::remove_file(file_path)
-                .with_context(|| format!("Failed to delete source file after copy: {}", file_path.display()))?;
+        let (matrix, in_others) = overlap_matrix(&totals, &hashes);
+        let width = totals.iter().max().map_or(0, |n| n.to_string().len()).max(4);
 
-            Ok(ProcessResult::Copied)
+        println!();
+        println!("Files in each input (row) whose content is also in another input (column):");
+        print!("{:>6}", "");
+        for column in 1..=input_dirs.len() {
+            print!(" {:>width$}", format!("[{}]", column));
         }
-    }
-
-    fn print_summary(&self) {
-        let stats = self.stats.lock().unwrap();
-
         println!();
-        println!("=== PROCESSING COMPLETE ===");
-        println!("Total files scanned: {}", stats.total_files);
-
-        let total_processed = stats.moved + stats.copied;
-        println!("Successfully processed: {}", total_processed);
-
-        if stats.moved > 0 {
-            println!("  - Moved (same volume): {}", stats.moved);
+        for (row, counts) in matrix.iter().enumerate() {
+            print!("{:>6}", format!("[{}]", row + 1));
+            for count in counts {
+                print!(" {:>width$}", count);
+            }
+            println!();
         }
-        if stats.copied > 0 {
-            println!("  - Copied (cross volume): {}", stats.copied);
+        println!();
+        for (input, dir) in input_dirs.iter().enumerate() {
+            println!(
+                "[{}] {}: {} files, {} also in another input",
+                input + 1,
+                dir.display(),
+                totals[input],
+                in_others[input]
+            );
+        }
+    }
+
+    /// Drop the files the transfer log records as archived by an earlier run,
+    /// as long as they are unchanged and their library copy is still there,
+    /// so they are neither read by exiftool nor compared with the library
+    fn skip_already_archived(&self, files: Vec<WorkItem>) -> Result<Vec<WorkItem>> {
+        let logged = read_transfer_log(&self.output_dir)?;
+        if logged.is_empty() {
+            println!("No transfer log from an earlier run, archiving everything");
+            return Ok(files);
         }
 
-        println!("Skipped (already exist): {}", stats.skipped);
-        println!("Failed: {}", stats.failed);
+        let (done, remaining): (Vec<WorkItem>, Vec<WorkItem>) = files.into_iter().partition(|item| {
+            let Some(transfer) = logged.get(&item.path) else {
+                return false;
+            };
+            let unchanged = self
+                .file_ops
+                .metadata(&item.path)
+                .is_ok_and(|meta| transfer.matches(meta.len, meta.modified));
+            unchanged && self.file_ops.exists(&transfer.destination)
+        });
 
-        if stats.failed > 0 {
-            println!();
-            println!(
-                "Failed cases have been logged in: {}",
-                self.failed_cases_dir.display()
-            );
+        println!("Resuming: {} files were archived by an earlier run", done.len());
+        self.count_skipped(SkipReason::Resumed, done.len());
+        Ok(remaining)
+    }
+
+    /// Find the source folders on read-only volumes before anything is
+    /// transferred, warning once per input, so their files are copied and
+    /// kept instead of each failing at the final delete
+    fn find_read_only_dirs(&mut self, files: &[WorkItem]) {
+        let mut checked = HashSet::new();
+        let mut warned = HashSet::new();
+        for item in files {
+            let Some(dir) = item.path.parent() else {
+                continue;
+            };
+            if !checked.insert(dir) || !self.file_ops.is_read_only(dir).unwrap_or(false) {
+                continue;
+            }
+            if warned.insert(&item.source) {
+                eprintln!(
+                    "Warning: {} is on a read-only volume; its files will be copied and left in place",
+                    item.source.display()
+                );
+            }
+            self.read_only_dirs.insert(dir.to_path_buf());
         }
+    }
 
-        // Handle duplicates cleanup
-        if !stats.duplicates.is_empty() {
-            println!();
-            println!("=== DUPLICATE FILES ===");
-            println!();
+    /// How deep to scan input directories (--max-depth, or --recursive)
+    fn scan_depth(&self) -> usize {
+        if self.settings.recursive {
+            usize::MAX
+        } else {
+            self.settings.max_depth.unwrap_or(1)
+        }
+    }
+
+    /// Fold same-shot previews (e.g. the JPG beside a ProRAW DNG) into their
+    /// primary's work item, or drop them with --pair-previews skip
+    fn group_pairs(&self, files: Vec<WorkItem>, policy: PreviewPolicy) -> Vec<WorkItem> {
+        let priority: Vec<String> = if self.settings.pair_priority.is_empty() {
+            DEFAULT_PAIR_PRIORITY.iter().map(|s| s.to_string()).collect()
+        } else {
+            self.settings.pair_priority.clone()
+        };
+        let paths: Vec<PathBuf> = files.iter().map(|item| item.path.clone()).collect();
+        let groups = find_pairs(&paths, &priority);
 
-            // Calculate total size
-            let mut total_size: u64 = 0;
-            for (source, _) in &stats.duplicates {
-                if let Ok(metadata) = fs::metadata(source) {
-                    total_size += metadata.len();
+        let mut files: Vec<Option<WorkItem>> = files.into_iter().map(Some).collect();
+        let mut skipped = 0;
+        for group in groups {
+            let previews: Vec<PathBuf> = group
+                .previews
+                .iter()
+                .filter_map(|&index| files[index].take())
+                .map(|item| item.path)
+                .collect();
+            match policy {
+                PreviewPolicy::Skip => skipped += previews.len(),
+                PreviewPolicy::Sidecar => {
+                    if let Some(primary) = files[group.primary].as_mut() {
+                        primary.previews = previews;
+                    }
                 }
             }
+        }
 
-            // Display each duplicate with its match
-            for (source, dest) in &stats.duplicates {
-                println!("Source: {}", source.display());
-                println!("   → Duplicate of: {}", dest.display());
-                println!();
-            }
+        if skipped > 0 {
+            println!("Leaving {} same-shot preview(s) in place", skipped);
+            self.count_skipped(SkipReason::Preview, skipped);
+        }
+        files.into_iter().flatten().collect()
+    }
 
-            // Show summary
-            let size_mb = total_size as f64 / 1_048_576.0;
-            println!("Total: {} duplicates ({:.2} MB)", stats.duplicates.len(), size_mb);
-            println!();
+    /// Find the files under `dir`, recording `source` as the input they came
+    /// from (the archive itself, for extracted archives)
+    fn collect_files(&self, dir: &Path, source: &Path, max_depth: usize) -> Result<Vec<WorkItem>> {
+        Ok(self.scan_trees(&[(dir, source)], max_depth))
+    }
 
-            // We need to drop the lock before prompting for input
-            // Clone the duplicates list so we can use it after dropping the lock
-            let duplicates = stats.duplicates.clone();
-            drop(stats);
+    /// Find the files under each `(dir, source)` root, on a pool of threads so
+    /// that slow (network) mounts are read concurrently: first each root's top
+    /// level, then, when scanning deeper, each of its subfolders as a separate
+    /// tree. The results are in no particular order
+    fn scan_trees(&self, roots: &[(&Path, &Path)], max_depth: usize) -> Vec<WorkItem> {
+        let split = max_depth > 1;
+        let top_levels = scan_in_parallel(roots.to_vec(), |(dir, source)| {
+            let mut subdirs = Vec::new();
+            let files = self.walk_files(dir, dir, source, 1, split.then_some(&mut subdirs));
+            let subtrees: Vec<(PathBuf, &Path, &Path)> = subdirs.into_iter().map(|sub| (sub, dir, source)).collect();
+            (files, subtrees)
+        });
 
-            // Prompt for confirmation
-            print!("Delete these {} duplicate source files? (y/n): ", duplicates.len());
-            io::stdout().flush().unwrap();
+        let mut files = Vec::new();
+        let mut subtrees = Vec::new();
+        for (top_files, top_subtrees) in top_levels {
+            files.extend(top_files);
+            subtrees.extend(top_subtrees);
+        }
+        for subtree_files in scan_in_parallel(subtrees, |(sub, dir, source)| {
+            self.walk_files(&sub, dir, source, max_depth - 1, None)
+        }) {
+            files.extend(subtree_files);
+        }
+        files
+    }
 
-            let mut input = String::new();
-            if io::stdin().read_line(&mut input).is_ok() {
-                let input = input.trim().to_lowercase();
-                if input == "y" || input == "yes" {
-                    println!();
-                    println!("Deleting duplicate source files...");
-                    let mut deleted = 0;
-                    let mut failed = 0;
+    /// Walk `start` (a `root` input or a folder inside it) down to `max_depth`
+    /// levels. With `subdirs`, the folders found are added to it instead of
+    /// being descended into; hidden ones are left out either way
+    fn walk_files(
+        &self,
+        start: &Path,
+        root: &Path,
+        source: &Path,
+        max_depth: usize,
+        mut subdirs: Option<&mut Vec<PathBuf>>,
+    ) -> Vec<WorkItem> {
+        let mut files = Vec::new();
 
-                    for (source, _) in &duplicates {
-                        match fs::remove_file(source) {
-                            Ok(_) => {
-                                deleted += 1;
-                                println!("✓ Deleted: {}", source.display());
-                            }
-                            Err(e) => {
-                                failed += 1;
-                                eprintln!("✗ Failed to delete {}: {}", source.display(), e);
-                            }
-                        }
+        // With the output inside an input, the library and its failed cases
+        // would otherwise be picked up again as new sources
+        let library_dirs: Vec<PathBuf> = [&self.output_dir, &self.failed_cases_dir]
+            .into_iter()
+            .filter_map(|dir| dir.canonicalize().ok())
+            .collect();
+        let canonical_start = start.canonicalize().unwrap_or_else(|_| start.to_path_buf());
+        let is_library_dir = |entry: &walkdir::DirEntry| {
+            entry.file_type().is_dir()
+                && entry
+                    .path()
+                    .strip_prefix(start)
+                    .is_ok_and(|relative| library_dirs.contains(&canonical_start.join(relative)))
+        };
+
+        let include_hidden = self.settings.include_hidden;
+        let mut hidden = 0;
+        for entry_result in WalkDir::new(start)
+            .max_depth(max_depth)
+            .min_depth(1)
+            .into_iter()
+            // Prune dotfiles and dot-directories (.Trashes, .Spotlight-V100, ...)
+            // without descending into them; the starting folder itself is always kept
+            .filter_entry(|entry| {
+                if entry.depth() > 0 && is_library_dir(entry) {
+                    return false;
+                }
+                let keep = include_hidden || entry.depth() == 0 || !is_hidden(entry.file_name());
+                if !keep {
+                    hidden += 1;
+                }
+                keep
+            })
+        {
+            let entry = match entry_result {
+                Ok(e) => e,
+                Err(err) => {
+                    // Handle WalkDir errors
+                    self.stats.lock().unwrap().scan_errors += 1;
+                    if let Some(path) = err.path() {
+                        eprintln!("Warning: Failed to access {}: {}", path.display(), err);
+                    } else {
+                        eprintln!("Warning: WalkDir error: {}", err);
                     }
+                    continue;
+                }
+            };
 
-                    println!();
-                    println!("Cleanup complete: {} deleted, {} failed", deleted, failed);
-                } else {
-                    println!();
-                    println!("Duplicate source files were not deleted.");
+            let path = entry.path();
+
+            if let Some(subdirs) = subdirs.as_deref_mut() {
+                if entry.file_type().is_dir() {
+                    subdirs.push(path.to_path_buf());
+                    continue;
                 }
             }
+
+            // Skip if not a file
+            if !path.is_file() {
+                continue;
+            }
+
+            if self.ignored.matches(path) {
+                self.count_skipped(SkipReason::Ignored, 1);
+                continue;
+            }
+
+            // Where the file sits relative to the input root, for --preserve-structure
+            let relative_dir = path
+                .parent()
+                .and_then(|parent| parent.strip_prefix(root).ok())
+                .map(Path::to_path_buf)
+                .unwrap_or_default();
+
+            if let Some(path) = self.resolve_symlink(path) {
+                files.push(WorkItem {
+                    path,
+                    relative_dir,
+                    source: source.to_path_buf(),
+                    previews: Vec::new(),
+                });
+            }
         }
+
+        self.count_skipped(SkipReason::Hidden, hidden);
+        files
     }
-}
 
-enum ProcessResult {
-    Moved,
-    Copied,
-    Skipped(PathBuf), // Contains the destination path it's a duplicate of
-}
+    /// Record `count` files left out of the run for `reason`
+    fn count_skipped(&self, reason: SkipReason, count: usize) {
+        if count > 0 {
+            *self.stats.lock().unwrap().filtered.entry(reason).or_default() += count;
+        }
+    }
 
-/// Worker thread function
-fn worker_thread(
-    worker_id: usize,
-    work_receiver: Receiver<WorkItem>,
-    result_sender: Sender<WorkerResult>,
-) {
-    // Create ExifTool instance for this worker
-    let mut exiftool = match ExifTool::new() {
-        Ok(tool) => tool,
-        Err(e) => {
-            eprintln!("Worker {}: Failed to initialize ExifTool: {}", worker_id, e);
+    /// Leave out files larger than --max-read-size, with a warning for each.
+    /// Hashing and comparing read a file in full, which for a corrupt source
+    /// showing as a huge sparse file would take practically forever
+    fn skip_oversized(&self, files: Vec<WorkItem>) -> Vec<WorkItem> {
+        let limit = self.settings.max_read_size.unwrap_or(DEFAULT_MAX_READ_SIZE);
+        let (oversized, files): (Vec<WorkItem>, Vec<WorkItem>) = files
+            .into_iter()
+            .partition(|item| self.file_ops.metadata(&item.path).is_ok_and(|meta| meta.len > limit));
+        for item in &oversized {
+            style::eprint(
+                Style::Failure,
+                format_args!("⚠ Larger than --max-read-size ({} bytes), left in place: {}", limit, item.path.display()),
+            );
+            self.count_skipped(SkipReason::TooLarge, 1 + item.previews.len());
+        }
+        files
+    }
+
+    /// Count zero-byte files and, with --quarantine-empty, move them into the
+    /// "Empty Files" folder instead of leaving them in the sources
+    fn handle_empty_files(&self, empty_files: Vec<WorkItem>) {
+        if empty_files.is_empty() {
             return;
         }
-    };
 
-    // Process work items in batches with progressive sizing
-    let mut batch = Vec::new();
-    let mut batch_info = Vec::new(); // Store (path, should_move) tuples
-    let mut current_batch_size = INITIAL_BATCH_SIZE; // Start at 50
+        let quarantine = self.settings.quarantine_empty && !self.settings.read_only();
+        let empty_dir = self.output_dir.join("Empty Files");
+        if quarantine {
+            if let Err(e) = self.file_ops.create_dir_all(&empty_dir) {
+                eprintln!("Warning: failed to create {}: {}", empty_dir.display(), e);
+                return;
+            }
+        }
 
-    for (file_path, should_move) in work_receiver {
-        batch.push(file_path.clone());
-        batch_info.push((file_path, should_move));
+        for item in empty_files {
+            self.stats.lock().unwrap().empty.push(item.path.clone());
 
-        if batch.len() >= current_batch_size {
-            process_batch(&mut exiftool, &batch, &batch_info, &result_sender);
-            batch.clear();
-            batch_info.clear();
+            if !quarantine {
+                println!("- Empty (zero bytes): {}", item.path.display());
+                continue;
+            }
 
-            // Grow batch size: 50 → 60 → 70 → ... → MAX_BATCH_SIZE
-            current_batch_size = (current_batch_size + BATCH_SIZE_INCREMENT).min(MAX_BATCH_SIZE);
+            let name = item.path.file_name().and_then(|n| n.to_str()).unwrap_or("unknown");
+            let result = available_name(&empty_dir, name, |path| self.file_ops.exists(path))
+                .and_then(|target| self.move_path(&item.path, &target).map(|_| target));
+            match result {
+                Ok(target) => println!("- Empty (zero bytes), moved to {}", target.display()),
+                Err(e) => eprintln!("Warning: failed to quarantine empty file {}: {:#}", item.path.display(), e),
+            }
         }
     }
 
-    // Process remaining files in the last batch
-    if !batch.is_empty() {
-        process_batch(&mut exiftool, &batch, &batch_info, &result_sender);
+    /// Resolve a symlinked input to the file it points at, so that moves and
+    /// copies act on the real data rather than relocating the link itself.
+    /// Returns None if the file should be skipped (`--skip-symlinks`)
+    fn resolve_symlink(&self, path: &Path) -> Option<PathBuf> {
+        let is_symlink = fs::symlink_metadata(path)
+            .map(|m| m.file_type().is_symlink())
+            .unwrap_or(false);
+        if !is_symlink {
+            return Some(path.to_path_buf());
+        }
+
+        if self.settings.skip_symlinks {
+            self.count_skipped(SkipReason::Symlink, 1);
+            eprintln!("Warning: Skipping symlink {}", path.display());
+            return None;
+        }
+
+        match fs::canonicalize(path) {
+            Ok(target) => Some(target),
+            Err(e) => {
+                self.stats.lock().unwrap().scan_errors += 1;
+                eprintln!("Warning: Failed to resolve symlink {}: {}", path.display(), e);
+                None
+            }
+        }
     }
-}
 
-fn process_batch(
-    exiftool: &mut ExifTool,
-    batch: &[PathBuf],
-    batch_info: &[(PathBuf, bool)],
-    result_sender: &Sender<WorkerResult>,
-) {
-    // Extract metadata for all files in batch
-    let metadata_results = extract_dates_batch(exiftool, batch);
+    fn process_files_parallel(&self, files: Vec<WorkItem>) -> Result<()> {
+        let total_files = files.len();
 
-    // Process each file with its metadata
-    for (file_path, should_move) in batch_info {
-        let dates_result = metadata_results.get(file_path);
+        // One pool of CPU cores / 2 workers for all files, or with
+        // --workers-per-volume a pool of that many for each source volume
+        let pools = match self.settings.workers_per_volume {
+            Some(per_volume) => {
+                let queues = partition_by_volume(files);
+                if queues.len() > 1 {
+                    println!("Reading {} source volumes with {} worker thread(s) each", queues.len(), per_volume);
+                }
+                queues.into_iter().map(|queue| (queue, per_volume)).collect()
+            }
+            None => vec![(files, (num_cpus::get() / 2).max(1))],
+        };
+        let num_workers: usize = pools.iter().map(|(_, workers)| workers).sum();
+        println!("Starting {} worker threads", num_workers);
 
-        let result = match dates_result {
-            Some(Ok(dates)) => {
-                // We have metadata, extract extension
-                match get_extension(file_path) {
-                    Some(extension) => Ok(ProcessedFile {
-                        dates: dates.clone(),
-                        extension,
-                        should_move: *should_move,
-                    }),
-                    None => Err(anyhow::anyhow!("File has no extension")),
+        let (result_sender, result_receiver) = bounded::<WorkerResult>(num_workers * 2);
+
+        // Spawn each pool's worker threads and the thread feeding them
+        let mut worker_handles = Vec::new();
+        for (queue, pool_workers) in pools {
+            let (work_sender, work_receiver) = bounded::<WorkItem>(pool_workers * 2);
+            for _ in 0..pool_workers {
+                let worker_id = worker_handles.len();
+                let work_rx = work_receiver.clone();
+                let result_tx = result_sender.clone();
+                let options = self.settings.extract.clone();
+                let validate_decode = self.settings.validate_decode;
+
+                let handle = thread::spawn(move || {
+                    worker_thread(worker_id, work_rx, result_tx, options, validate_decode);
+                });
+
+                worker_handles.push(handle);
+            }
+
+            thread::spawn(move || {
+                for work_item in queue {
+                    if work_sender.send(work_item).is_err() {
+                        break; // Workers have shut down
+                    }
+                }
+                // Channel closes when work_sender is dropped
+            });
+        }
+
+        // Drop our copy of the result channel so it closes with the last worker
+        drop(result_sender);
+
+        // Process results from workers
+        let mut processed = 0;
+        let interval = self.settings.stats_interval.unwrap_or(DEFAULT_STATS_INTERVAL);
+        let mut last_progress = Instant::now();
+
+        loop {
+            // With --stats-every, wake up when a progress line is due even if
+            // no result has arrived
+            let received = match self.settings.stats_every {
+                Some(every) => result_receiver.recv_timeout(every.saturating_sub(last_progress.elapsed())),
+                None => result_receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let worker_result = match received {
+                Ok(worker_result) => Some(worker_result),
+                Err(RecvTimeoutError::Timeout) => None,
+                Err(RecvTimeoutError::Disconnected) => break,
+            };
+
+            if worker_result.is_some() {
+                processed += 1;
+            }
+            let count_due = worker_result.is_some() && interval > 0 && processed % interval == 0;
+            let time_due = self.settings.stats_every.is_some_and(|every| last_progress.elapsed() >= every);
+            if count_due || time_due {
+                println!("Progress: {}/{} files processed", processed, total_files);
+                last_progress = Instant::now();
+            }
+
+            let Some(worker_result) = worker_result else {
+                continue;
+            };
+            let path = worker_result.item.path.clone();
+            self.handle_worker_result(worker_result);
+            self.write_progress_line(processed, total_files, &path);
+            self.emit(ProcessEvent::Progress {
+                done: processed,
+                total: total_files,
+            });
+
+            // Stop taking results; dropping the receiver below fails the
+            // workers' pending sends so they stop
+            if self.stats.lock().unwrap().aborted.is_some() {
+                break;
+            }
+        }
+
+        // Workers blocked on a full result channel would never finish
+        // while it's still open
+        drop(result_receiver);
+
+        // Wait for all workers to finish
+        for handle in worker_handles {
+            let _ = handle.join();
+        }
+
+        // Workers that couldn't start exiftool return without taking any
+        // files, so with none running the rest of the queue is never read
+        let mut stats = self.stats.lock().unwrap();
+        if stats.aborted.is_none() && processed < total_files {
+            let unread = total_files - processed;
+            eprintln!("Error: {} files were never read; the workers stopped before reaching them", unread);
+            stats.unread += unread;
+            stats.failed += unread;
+        }
+
+        Ok(())
+    }
+
+    fn handle_worker_result(&self, worker_result: WorkerResult) {
+        let path = worker_result.item.path.clone();
+        let source = worker_result.item.source.clone();
+        let previews = worker_result.item.previews.clone();
+
+        // GPS presence is only known once the metadata has been read
+        if let (Some(filter), Ok(processed)) = (self.settings.gps_filter, &worker_result.result) {
+            if !filter.allows(processed.info.gps) {
+                self.count_skipped(SkipReason::Gps, 1 + previews.len());
+                return;
+            }
+        }
+
+        self.emit(ProcessEvent::Started { path: path.clone() });
+        let Some(outcome) = self.archive_worker_result(worker_result) else {
+            return;
+        };
+
+        {
+            let mut stats = self.stats.lock().unwrap();
+            let counts = stats.by_source.entry(source).or_default();
+            match outcome {
+                Outcome::Moved(_) => counts.moved += 1,
+                Outcome::Copied(_) => counts.copied += 1,
+                Outcome::Skipped(_) => counts.skipped += 1,
+                Outcome::Failed => counts.failed += 1,
+            }
+        }
+
+        // Failures are reported by record_failure, with their reason
+        match &outcome {
+            Outcome::Moved(dest) => self.emit(ProcessEvent::Moved { src: path, dst: dest.clone() }),
+            Outcome::Copied(dest) => self.emit(ProcessEvent::Copied { src: path, dst: dest.clone() }),
+            Outcome::Skipped(existing) => self.emit(ProcessEvent::Skipped {
+                path,
+                duplicate_of: existing.clone(),
+            }),
+            Outcome::Failed => {}
+        }
+
+        match outcome {
+            Outcome::Moved(dest) | Outcome::Copied(dest) | Outcome::Skipped(dest) => {
+                for preview in &previews {
+                    self.archive_preview(preview, &dest);
                 }
             }
-            Some(Err(e)) => {
-                // Metadata extraction failed
-                Err(anyhow::anyhow!("{}", e))
+            Outcome::Failed if !previews.is_empty() => {
+                eprintln!("Warning: Left {} preview(s) in place since their primary failed", previews.len());
             }
-            None => {
-                // Shouldn't happen, but handle gracefully
-                Err(anyhow::anyhow!("No metadata result for file"))
+            Outcome::Failed => {}
+        }
+    }
+
+    /// Archive a --pair-previews sidecar next to its primary's library file,
+    /// under the same name with the preview's own extension
+    fn archive_preview(&self, preview: &Path, primary_dest: &Path) {
+        let Some(extension) = get_extension(preview) else {
+            return;
+        };
+        let target = primary_dest.with_extension(output_extension(&extension, self.settings.name_format.ext_case));
+
+        let reservation = {
+            let mut reserved = self.reserved.lock().unwrap();
+            if reserved.contains(&target) || self.file_ops.exists(&target) {
+                None
+            } else {
+                reserved.insert(target.clone());
+                Some(Reservation {
+                    reserved: &self.reserved,
+                    path: target.clone(),
+                })
             }
         };
 
-        let worker_result = WorkerResult {
-            original_path: file_path.clone(),
-            result,
+        let Some(reservation) = reservation else {
+            // Already archived with an earlier copy of the primary, or taken
+            let identical = self
+                .digest(preview)
+                .and_then(|digest| self.is_identical(preview, &digest, &target));
+            match identical {
+                Ok(true) => {
+                    let mut stats = self.stats.lock().unwrap();
+                    stats.skipped += 1;
+                    stats.duplicates.push((preview.to_path_buf(), target));
+                    style::print(Style::Skipped, format_args!("- Skipped preview (already exists): {}", preview.display()));
+                }
+                _ => eprintln!(
+                    "Warning: Left preview {} in place, {} is already taken",
+                    preview.display(),
+                    target.display()
+                ),
+            }
+            return;
         };
 
-        // Send result back to main thread
-        if result_sender.send(worker_result).is_err() {
-            break; // Main thread has shut down
+        let dest_dir = primary_dest.parent().unwrap_or(&self.output_dir);
+        let should_move = self.file_ops.same_volume(preview, dest_dir).unwrap_or(false);
+        match self.transfer_file(preview, &reservation.path, should_move) {
+            Ok(_) => {
+                self.stats.lock().unwrap().previews_archived += 1;
+                style::print(Style::Success, format_args!("✓ Archived preview: {}", preview.display()));
+            }
+            Err(e) => self.record_failure(preview, &e),
+        }
+    }
+
+    /// Archive one file the workers extracted metadata for, returning what
+    /// happened to it (nothing, in --dedupe-report-only mode)
+    fn archive_worker_result(&self, worker_result: WorkerResult) -> Option<Outcome> {
+        let WorkerResult { item, result } = worker_result;
+        let WorkItem { path: original_path, relative_dir, .. } = item;
+
+        match result {
+            Ok(processed) => {
+                // Worker successfully extracted metadata
+                let ProcessedFile {
+                    info,
+                    extension,
+                    decode_error,
+                } = processed;
+                let dates = &info.dates;
+                let extension = self.output_extension(&original_path, extension, &info);
+                self.note_suspicious_date(&original_path, dates);
+
+                if let Some(error) = &decode_error {
+                    style::eprint(
+                        Style::Failure,
+                        format_args!("⚠ Corrupt image, archiving into Corrupt: {}: {}", original_path.display(), error),
+                    );
+                }
+
+                // Duplicate checks and the transfer are scoped to the file's destination folder
+                let dest_dir = self.destination_dir(&info, decode_error.is_some(), &relative_dir);
+
+                if self.settings.dedupe_report.is_some() {
+                    self.report_duplicates(&original_path, &dest_dir, dates, &extension);
+                    if info.kind() == MediaKind::Image {
+                        self.check_near_duplicates(&original_path, &dest_dir);
+                    }
+                    return None;
+                }
+
+                if self.settings.dry_run {
+                    self.plan_transfer(&original_path, &dest_dir, dates, &extension);
+                    return None;
+                }
+
+                if let Err(e) = self.file_ops.create_dir_all(&dest_dir) {
+                    let err = anyhow::anyhow!("Failed to create destination directory {}: {}", dest_dir.display(), e);
+                    self.record_failure(&original_path, &err);
+                    return Some(Outcome::Failed);
+                }
+
+                // Decide move vs copy against the real destination folder, which
+                // may be on a different mount than the output root
+                let should_move = self.file_ops.same_volume(&original_path, &dest_dir).unwrap_or(false);
+
+                // Hash the source in chunks; its bytes are never held in memory
+                let digest = match self.digest(&original_path) {
+                    Ok(digest) => digest,
+                    Err(e) => {
+                        let err = anyhow::anyhow!("Failed to read file: {}", e);
+                        self.record_failure(&original_path, &err);
+                        return Some(Outcome::Failed);
+                    }
+                };
+
+                // A copy of a source archived earlier in the run (e.g. the same
+                // photo in two overlapping backups) is a duplicate of its file
+                if let Some((first, existing)) = self.find_run_duplicate(&original_path, &digest, &dest_dir, dates) {
+                    let existing = self.keep_earliest_date(&original_path, existing, &dest_dir, dates, &digest);
+                    return Some(self.skip_run_duplicate(&original_path, &first, existing));
+                }
+
+                // With --dedupe-scope global, an identical file anywhere in the
+                // library counts, whatever its name
+                if let Some(existing) = self.find_global_duplicate(&original_path, &digest) {
+                    let existing = self.keep_earliest_date(&original_path, existing, &dest_dir, dates, &digest);
+                    return Some(self.skip_duplicate(&original_path, existing));
+                }
+
+                // Find the first free counter, or an identical file already archived
+                let target_path = match self.claim_destination(&original_path, &digest, &dest_dir, dates, &extension) {
+                    Ok(Claim::Duplicate(existing)) => return Some(self.skip_duplicate(&original_path, existing)),
+                    Ok(Claim::Free(reservation)) => reservation,
+                    Err(e) => {
+                        self.record_failure(&original_path, &e);
+                        return Some(Outcome::Failed);
+                    }
+                };
+
+                // With --on-conflict, a different file at the first counter is
+                // settled by replacing one of the two instead of incrementing.
+                // No counter holds a copy of the file, or it was skipped above
+                if self.settings.on_conflict != ConflictPolicy::Increment {
+                    if let Some(outcome) =
+                        self.resolve_conflict(&original_path, &dest_dir, &info, &extension, &digest, should_move)
+                    {
+                        return Some(outcome);
+                    }
+                }
+
+                // The reservation is held until the transfer finishes
+                let result = self.transfer_file(&original_path, &target_path.path, should_move);
+                Some(self.record_transfer(&original_path, &info, &digest, result))
+            }
+            Err(e) => {
+                // Worker failed to extract metadata
+                self.record_failure(&original_path, &e);
+                Some(Outcome::Failed)
+            }
+        }
+    }
+
+    /// With --fix-extension, the extension for the file type exiftool
+    /// detected when it disagrees with the source's (a HEIC named .jpg)
+    fn output_extension(&self, path: &Path, extension: String, info: &MediaInfo) -> String {
+        if !self.settings.fix_extension {
+            return extension;
+        }
+        match &info.file_type_extension {
+            Some(detected) if !detected.is_empty() && !same_file_type(&extension, detected) => {
+                println!(
+                    "↺ Extension corrected from {} to {}: {}",
+                    normalize_extension(&extension),
+                    normalize_extension(detected),
+                    path.display()
+                );
+                self.stats.lock().unwrap().extensions_fixed += 1;
+                detected.clone()
+            }
+            _ => extension,
+        }
+    }
+
+    /// Count a source left in place because `existing` has its content
+    fn skip_duplicate(&self, original_path: &Path, existing: PathBuf) -> Outcome {
+        let mut stats = self.stats.lock().unwrap();
+        stats.skipped += 1;
+        stats.duplicates.push((original_path.to_path_buf(), existing.clone()));
+        style::print(Style::Skipped, format_args!("- Skipped (already exists): {}", original_path.display()));
+        Outcome::Skipped(existing)
+    }
+
+    /// The source archived earlier in the run with the same content as
+    /// `source`, and its library file, confirmed byte for byte (a file
+    /// changed since, e.g. by --auto-orient, no longer matches). With
+    /// --dedupe-scope date-bucket it must also share the folder and dates
+    fn find_run_duplicate(
+        &self,
+        source: &Path,
+        digest: &ContentDigest,
+        dest_dir: &Path,
+        dates: &MediaDates,
+    ) -> Option<(PathBuf, PathBuf)> {
+        let first = self.run_sources.lock().unwrap().get(&(digest.len, digest.hash.clone())).cloned()?;
+        let same_bucket = first.dest.parent() == Some(dest_dir) && first.dates == *dates;
+        if self.settings.dedupe_scope == DedupeScope::DateBucket && !same_bucket {
+            return None;
+        }
+        let existing = first.dest;
+        match self.is_identical(source, digest, &existing) {
+            Ok(true) => Some((first.source, existing)),
+            Ok(false) => None,
+            Err(e) => {
+                eprintln!("Warning: failed to compare {} with {}: {}", source.display(), existing.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Count a source left in place because `first`, archived earlier in the
+    /// run as `existing`, has its content
+    fn skip_run_duplicate(&self, original_path: &Path, first: &Path, existing: PathBuf) -> Outcome {
+        let mut stats = self.stats.lock().unwrap();
+        stats.skipped += 1;
+        stats.duplicates_in_run += 1;
+        stats.duplicates.push((original_path.to_path_buf(), existing.clone()));
+        style::print(
+            Style::Skipped,
+            format_args!("- Skipped (duplicate within this run of {}): {}", first.display(), original_path.display()),
+        );
+        Outcome::Skipped(existing)
+    }
+
+    /// With --keep-earliest-date, rename `existing`, a library file identical
+    /// to `source`, to the name `source` would get in `dest_dir` when that
+    /// leads with an earlier date. Returns where the library file now is
+    fn keep_earliest_date(
+        &self,
+        source: &Path,
+        existing: PathBuf,
+        dest_dir: &Path,
+        dates: &MediaDates,
+        digest: &ContentDigest,
+    ) -> PathBuf {
+        if !self.settings.keep_earliest_date || self.settings.read_only() {
+            return existing;
+        }
+        let name = existing.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+        if !dates_precede_name(dates, name, &self.settings.name_format) {
+            return existing;
+        }
+
+        // The library file keeps its own extension
+        let extension = get_extension(&existing).unwrap_or_default();
+        let claim = self
+            .file_ops
+            .create_dir_all(dest_dir)
+            .map_err(anyhow::Error::from)
+            .and_then(|()| self.claim_destination(source, digest, dest_dir, dates, &extension));
+        let target = match claim {
+            Ok(Claim::Free(reservation)) => reservation,
+            // Another copy already has the earlier name
+            Ok(Claim::Duplicate(other)) => return other,
+            Err(e) => {
+                eprintln!("Warning: Failed to rename {} to an earlier date: {:#}", existing.display(), e);
+                return existing;
+            }
+        };
+        if let Err(e) = self.move_path(&existing, &target.path) {
+            eprintln!("Warning: Failed to rename {} to an earlier date: {:#}", existing.display(), e);
+            return existing;
+        }
+        // Its --emit-sidecar JSON goes with it
+        let sidecar = sidecar_path(&existing);
+        if self.settings.emit_sidecar && self.file_ops.exists(&sidecar) {
+            if let Err(e) = self.move_path(&sidecar, &sidecar_path(&target.path)) {
+                eprintln!("Warning: Failed to rename {}: {:#}", sidecar.display(), e);
+            }
+        }
+
+        println!(
+            "↺ Renamed to the earlier date of {}: {} → {}",
+            source.display(),
+            existing.display(),
+            target.path.display()
+        );
+        self.note_renamed(&existing, &target.path, dates);
+        target.path.clone()
+    }
+
+    /// Point this run's records of the library file `from` at `to`, which it
+    /// was renamed to for having the earlier `dates`
+    fn note_renamed(&self, from: &Path, to: &Path, dates: &MediaDates) {
+        if let Some(index) = self.library_index.lock().unwrap().as_mut() {
+            index.rename(from, to);
+        }
+        for run_source in self.run_sources.lock().unwrap().values_mut().filter(|run_source| run_source.dest == from) {
+            run_source.dest = to.to_path_buf();
+            run_source.dates = dates.clone();
+        }
+        for shot in self.run_shots.lock().unwrap().values_mut().filter(|shot| *shot == from) {
+            *shot = to.to_path_buf();
+        }
+
+        let mut stats = self.stats.lock().unwrap();
+        let stats = &mut *stats;
+        stats.redated += 1;
+        let transfers = stats.transfers.iter_mut().map(|(_, dest)| dest);
+        let duplicates = stats.duplicates.iter_mut().map(|(_, dest)| dest);
+        let manifest = stats.manifest.iter_mut().map(|(_, dest)| dest);
+        let same_shots = stats.same_shots.iter_mut().flat_map(|(later, earlier)| [later, earlier]);
+        for dest in transfers.chain(duplicates).chain(manifest).chain(same_shots).filter(|dest| *dest == from) {
+            *dest = to.to_path_buf();
+        }
+    }
+
+    /// With --dedupe-scope global, find a library file with the same content
+    /// as `source`, indexing the library the first time one is looked for
+    fn find_global_duplicate(&self, source: &Path, digest: &ContentDigest) -> Option<PathBuf> {
+        if self.settings.dedupe_scope != DedupeScope::Global {
+            return None;
+        }
+
+        let mut index = self.library_index.lock().unwrap();
+        let index = index.get_or_insert_with(|| {
+            println!("Indexing the library for --dedupe-scope global...");
+            LibraryIndex::scan(
+                &self.output_dir,
+                std::slice::from_ref(&self.failed_cases_dir),
+                self.settings.checksum_algo,
+            )
+        });
+        match index.find(self.file_ops.as_ref(), source, digest.len, &digest.hash) {
+            Ok(existing) => existing,
+            Err(e) => {
+                eprintln!("Warning: failed to read {}: {}", source.display(), e);
+                None
+            }
+        }
+    }
+
+    fn remember_run_source(&self, original_path: &Path, dest_path: &Path, info: &MediaInfo, digest: &ContentDigest) {
+        self.run_sources
+            .lock()
+            .unwrap()
+            .entry((digest.len, digest.hash.clone()))
+            .or_insert_with(|| RunSource {
+                source: original_path.to_path_buf(),
+                dest: dest_path.to_path_buf(),
+                dates: info.dates.clone(),
+            });
+    }
+
+    /// With --dedupe-by identity, report a file archived this run as the
+    /// same shot as an earlier one with different bytes. Both are kept
+    fn note_same_shot(&self, dest_path: &Path, info: &MediaInfo) {
+        if self.settings.dedupe_by != DedupeBy::Identity {
+            return;
+        }
+        let Some(identity) = &info.identity else {
+            return;
+        };
+        let earlier = {
+            let mut run_shots = self.run_shots.lock().unwrap();
+            match run_shots.get(identity) {
+                Some(earlier) => earlier.clone(),
+                None => {
+                    run_shots.insert(identity.clone(), dest_path.to_path_buf());
+                    return;
+                }
+            }
+        };
+        println!("≈ Same shot as {}: {}", earlier.display(), dest_path.display());
+        self.stats.lock().unwrap().same_shots.push((dest_path.to_path_buf(), earlier));
+    }
+
+    /// Count a finished transfer and report it
+    fn record_transfer(
+        &self,
+        original_path: &Path,
+        info: &MediaInfo,
+        digest: &ContentDigest,
+        result: Result<ProcessResult>,
+    ) -> Outcome {
+        match result {
+            Ok(ProcessResult::Moved(dest_path)) => {
+                self.remember_run_source(original_path, &dest_path, info, digest);
+                self.note_same_shot(&dest_path, info);
+                self.finish_archived_file(original_path, dest_path.clone(), info, digest);
+                let mut stats = self.stats.lock().unwrap();
+                stats.moved += 1;
+                self.count_set_aside(&mut stats, info, &dest_path);
+                style::print(Style::Success, format_args!("✓ Moved: {}", original_path.display()));
+                Outcome::Moved(dest_path)
+            }
+            Ok(ProcessResult::Copied(dest_path)) => {
+                self.remember_run_source(original_path, &dest_path, info, digest);
+                self.note_same_shot(&dest_path, info);
+                self.finish_archived_file(original_path, dest_path.clone(), info, digest);
+                let mut stats = self.stats.lock().unwrap();
+                stats.copied += 1;
+                self.count_set_aside(&mut stats, info, &dest_path);
+                style::print(Style::Success, format_args!("✓ Copied: {}", original_path.display()));
+                Outcome::Copied(dest_path)
+            }
+            Err(e) => {
+                if is_permission_error(&e) {
+                    self.stats.lock().unwrap().permission_errors += 1;
+                }
+                if is_disk_full_error(&e) {
+                    // Every remaining file would fail the same way
+                    self.stats.lock().unwrap().aborted = Some(format!(
+                        "The destination disk is full (while archiving {})",
+                        original_path.display()
+                    ));
+                }
+                self.record_failure(original_path, &e);
+                Outcome::Failed
+            }
+        }
+    }
+
+    /// Count an archived file that went to the Corrupt or Review folder
+    fn count_set_aside(&self, stats: &mut ProcessingStats, info: &MediaInfo, dest_path: &Path) {
+        if dest_path.starts_with(self.output_dir.join(CORRUPT_FOLDER)) {
+            stats.corrupt += 1;
+        } else if self.needs_review(info) {
+            stats.review += 1;
+        }
+    }
+
+    /// Apply --on-conflict when the first counter's file exists with different
+    /// content: whichever of the two the policy prefers ends up at that name,
+    /// and the other is moved into the Replaced folder. A source set aside there
+    /// counts as skipped, not archived. Returns None when there is no conflict
+    /// to settle, so the counter claimed by `claim_destination` is used instead
+    fn resolve_conflict(
+        &self,
+        original_path: &Path,
+        dest_dir: &Path,
+        info: &MediaInfo,
+        extension: &str,
+        digest: &ContentDigest,
+        should_move: bool,
+    ) -> Option<Outcome> {
+        let filename = generate_filename(&info.dates, extension, 1, &self.settings.name_format);
+        let first = {
+            let mut reserved = self.reserved.lock().unwrap();
+            let path = dest_dir.join(&filename);
+            if reserved.contains(&path) || !self.file_ops.exists(&path) {
+                return None;
+            }
+            reserved.insert(path.clone());
+            Reservation {
+                reserved: &self.reserved,
+                path,
+            }
+        };
+
+        match self.is_identical(original_path, digest, &first.path) {
+            // A duplicate, not a conflict
+            Ok(true) => return None,
+            Ok(false) => {}
+            Err(e) => {
+                let err = anyhow::anyhow!("Failed to read {}: {}", first.path.display(), e);
+                return Some(self.record_transfer(original_path, info, digest, Err(err)));
+            }
+        }
+
+        let modified = |path: &Path| self.file_ops.metadata(path).ok().and_then(|m| m.modified);
+        let len = |path: &Path| self.file_ops.metadata(path).map(|m| m.len).unwrap_or(0);
+        let candidate_wins = match self.settings.on_conflict {
+            ConflictPolicy::Increment => return None,
+            ConflictPolicy::KeepLarger => digest.len > len(&first.path),
+            ConflictPolicy::KeepNewer => modified(original_path) > modified(&first.path),
+        };
+
+        let replaced_dir = self.output_dir.join("Replaced");
+        let aside = self
+            .file_ops
+            .create_dir_all(&replaced_dir)
+            .with_context(|| format!("Failed to create {}", replaced_dir.display()))
+            .and_then(|_| available_name(&replaced_dir, &filename, |path| self.file_ops.exists(path)));
+
+        if candidate_wins {
+            let result = aside.and_then(|aside| {
+                self.move_path(&first.path, &aside)?;
+                println!("↻ Replaced {} (moved to {})", first.path.display(), aside.display());
+                self.transfer_file(original_path, &first.path, should_move)
+            });
+            if result.is_ok() {
+                self.stats.lock().unwrap().replaced += 1;
+            }
+            return Some(self.record_transfer(original_path, info, digest, result));
+        }
+
+        let result = aside.and_then(|aside| {
+            let should_move = self.file_ops.same_volume(original_path, &replaced_dir).unwrap_or(false);
+            self.transfer_file(original_path, &aside, should_move)
+        });
+        match result {
+            Ok(ProcessResult::Moved(aside) | ProcessResult::Copied(aside)) => {
+                let mut stats = self.stats.lock().unwrap();
+                stats.replaced += 1;
+                stats.skipped += 1;
+                println!("↻ Kept {}, setting {} aside", first.path.display(), original_path.display());
+                Some(Outcome::Skipped(aside))
+            }
+            Err(e) => Some(self.record_transfer(original_path, info, digest, Err(e))),
+        }
+    }
+
+    /// Post-transfer steps for a newly archived file: --auto-orient,
+    /// --tag-provenance, the --manifest entry, and the --exec command
+    fn finish_archived_file(&self, original_path: &Path, dest_path: PathBuf, info: &MediaInfo, digest: &ContentDigest) {
+        self.stats
+            .lock()
+            .unwrap()
+            .transfers
+            .push((original_path.to_path_buf(), dest_path.clone()));
+
+        // Set once a step rewrites the archived file, so its hash is taken again
+        let mut rewritten = false;
+        if self.settings.auto_orient
+            && info.kind() == MediaKind::Image
+            && needs_orienting(info.orientation.as_deref())
+        {
+            // On failure the archived file is left exactly as it was copied
+            let orientation = info.orientation.as_deref().unwrap_or_default();
+            match auto_orient(&dest_path, orientation, info.mime_type.as_deref()) {
+                Ok(()) => {
+                    self.stats.lock().unwrap().oriented += 1;
+                    rewritten = true;
+                }
+                Err(e) => eprintln!("Warning: --auto-orient failed, keeping {} as is: {:#}", dest_path.display(), e),
+            }
+        }
+
+        if let Some(provenance) = &self.provenance {
+            if matches!(info.kind(), MediaKind::Image | MediaKind::Video) {
+                match provenance.tag(&dest_path, original_path, chrono::Utc::now()) {
+                    Ok(()) => {
+                        self.stats.lock().unwrap().provenance_tagged += 1;
+                        rewritten = true;
+                    }
+                    Err(e) => eprintln!("Warning: --tag-provenance failed, keeping {} as is: {:#}", dest_path.display(), e),
+                }
+            }
+        }
+
+        let rehashed = match rewritten {
+            true => match self.settings.checksum_algo.hash_file(&dest_path) {
+                Ok(hash) => Some(hash),
+                Err(e) => {
+                    eprintln!("Warning: {:#}", e);
+                    None
+                }
+            },
+            false => None,
+        };
+
+        if self.settings.emit_sidecar {
+            self.write_sidecar(original_path, &dest_path, info);
+        }
+
+        if info.kind() == MediaKind::Image {
+            if let Some(dest_dir) = dest_path.parent() {
+                self.check_near_duplicates(&dest_path, dest_dir);
+            }
+        }
+
+        if let Some(hook) = &self.exec_hook {
+            hook.run(&dest_path);
+        }
+
+        if let Some(log) = &self.transfer_log {
+            if let Err(e) = log.record(original_path, &dest_path, digest.len, digest.modified) {
+                eprintln!("Warning: Failed to add {} to the transfer log: {}", dest_path.display(), e);
+            }
+        }
+
+        if self.settings.dedupe_scope == DedupeScope::Global {
+            // A rewritten file's size is looked up again, and if it couldn't
+            // be rehashed, hashed when the index next needs it
+            let (len, hash) = match (rewritten, &rehashed) {
+                (true, hash) => (self.file_ops.metadata(&dest_path).map_or(0, |m| m.len), hash.clone()),
+                (false, _) => (digest.len, Some(digest.hash.clone())),
+            };
+            if let Some(index) = self.library_index.lock().unwrap().as_mut() {
+                index.insert(dest_path.clone(), len, hash);
+            }
+        }
+        self.record_manifest_entry(dest_path, rehashed.unwrap_or_else(|| digest.hash.clone()));
+    }
+
+    /// Write the --emit-sidecar JSON of a file archived to `dest_path`
+    fn write_sidecar(&self, original_path: &Path, dest_path: &Path, info: &MediaInfo) {
+        let path = sidecar_path(dest_path);
+        let mut content = serde_json::to_string_pretty(&sidecar_json(original_path, info)).unwrap_or_default();
+        content.push('\n');
+        if let Err(e) = self.file_ops.write_file(&path, content.as_bytes()) {
+            eprintln!("Warning: Failed to write {}: {}", path.display(), e);
+        }
+    }
+
+    /// With --phash-dedupe, compare an image's perceptual hash against the
+    /// images in its destination folder and those archived (or, in report
+    /// mode, checked) earlier this run, recording close ones for review
+    fn check_near_duplicates(&self, path: &Path, dest_dir: &Path) {
+        let Some(max_distance) = self.settings.phash_dedupe else {
+            return;
+        };
+        let hash = match dhash(path) {
+            Ok(hash) => hash,
+            Err(e) => {
+                eprintln!("Warning: --phash-dedupe skipped {}: {:#}", path.display(), e);
+                return;
+            }
+        };
+
+        let mut index = self.phash_index.lock().unwrap();
+        let known = index
+            .entry(dest_dir.to_path_buf())
+            .or_insert_with(|| hash_folder(dest_dir));
+        let matches: Vec<(PathBuf, u32)> = known
+            .iter()
+            .filter(|(known_path, _)| known_path != path)
+            .map(|(known_path, known_hash)| (known_path.clone(), distance(hash, *known_hash)))
+            .filter(|&(_, d)| d <= max_distance)
+            .collect();
+        if !known.iter().any(|(known_path, _)| known_path == path) {
+            known.push((path.to_path_buf(), hash));
+        }
+        drop(index);
+
+        for (similar, d) in matches {
+            println!("≈ Probable near-duplicate of {} (distance {}): {}", similar.display(), d, path.display());
+            self.stats.lock().unwrap().near_duplicates.push((path.to_path_buf(), similar, d));
+        }
+    }
+
+    /// After an aborted run, record what was done as JSON so the run can be
+    /// picked up again. The output disk may be full, so fall back to the
+    /// temporary directory if the report can't be written there
+    fn write_partial_report(&self, total_files: usize, reason: &str) -> Result<PathBuf> {
+        let stats = self.stats.lock().unwrap();
+        let handled = stats.moved + stats.copied + stats.skipped + stats.failed;
+        let report = serde_json::json!({
+            "aborted": reason,
+            "total_files": total_files,
+            "unprocessed": total_files.saturating_sub(handled + stats.empty.len()),
+            "moved": stats.moved,
+            "copied": stats.copied,
+            "skipped": stats.skipped,
+            "failed": stats.failed,
+            "transfers": stats
+                .transfers
+                .iter()
+                .map(|(source, dest)| serde_json::json!({
+                    "source": source.display().to_string(),
+                    "destination": dest.display().to_string(),
+                }))
+                .collect::<Vec<_>>(),
+        });
+        let contents = serde_json::to_string_pretty(&report)?;
+
+        let file_name = "collect_media partial report.json";
+        let mut last_error = None;
+        for dir in [self.output_dir.clone(), std::env::temp_dir()] {
+            let path = dir.join(file_name);
+            match fs::write(&path, &contents) {
+                Ok(()) => return Ok(path),
+                Err(e) => last_error = Some(anyhow::Error::from(e).context(format!("Failed to write {}", path.display()))),
+            }
+        }
+        Err(last_error.unwrap())
+    }
+
+    /// Remember the hash of a file written to the library, for --manifest
+    fn record_manifest_entry(&self, dest_path: PathBuf, hash: String) {
+        if self.settings.manifest.is_some() {
+            self.stats.lock().unwrap().manifest.push((hash, dest_path));
+        }
+    }
+
+    /// Write the --manifest file in `shasum` format (`<hash>  <path>`), with
+    /// paths relative to the output directory so it can be checked from there
+    fn write_manifest(&self, manifest_path: &Path) -> Result<()> {
+        let mut entries = self.stats.lock().unwrap().manifest.clone();
+        entries.sort_by(|a, b| a.1.cmp(&b.1));
+
+        let mut manifest = String::new();
+        for (hash, path) in &entries {
+            let relative = path.strip_prefix(&self.output_dir).unwrap_or(path);
+            manifest.push_str(&format!("{}  {}\n", hash, relative.display()));
+        }
+
+        fs::write(manifest_path, manifest)
+            .with_context(|| format!("Failed to write manifest to {}", manifest_path.display()))?;
+        println!("Manifest of {} files written to: {}", entries.len(), manifest_path.display());
+        Ok(())
+    }
+
+    /// Report-only counterpart of the duplicate check: hash the source, look
+    /// for a byte-identical file already in the library, and group it with
+    /// other sources sharing its content, without writing anything
+    fn report_duplicates(&self, original_path: &Path, dest_dir: &Path, dates: &MediaDates, extension: &str) {
+        let digest = match self.digest(original_path) {
+            Ok(digest) => digest,
+            Err(e) => {
+                let err = anyhow::anyhow!("Failed to read file: {}", e);
+                self.record_failure(original_path, &err);
+                return;
+            }
+        };
+        let existing = match self.settings.dedupe_scope {
+            DedupeScope::Global => self.find_global_duplicate(original_path, &digest),
+            DedupeScope::DateBucket => (1..=self.max_collisions())
+                .map(|counter| dest_dir.join(generate_filename(dates, extension, counter, &self.settings.name_format)))
+                .take_while(|check_path| self.file_ops.exists(check_path))
+                .find(|check_path| {
+                    let hash = self.file_ops.open(check_path).and_then(|file| self.settings.checksum_algo.hash_reader(file));
+                    match hash {
+                        Ok(existing_hash) => existing_hash == digest.hash,
+                        Err(e) => {
+                            eprintln!("Warning: failed to read {} for hashing: {}", check_path.display(), e);
+                            false
+                        }
+                    }
+                }),
+        };
+
+        let mut stats = self.stats.lock().unwrap();
+        if let Some(existing) = existing {
+            stats.skipped += 1;
+            println!("- Duplicate of {}: {}", existing.display(), original_path.display());
+            stats.duplicates.push((original_path.to_path_buf(), existing));
+        }
+        stats.source_hashes.entry(digest.hash).or_default().push(original_path.to_path_buf());
+    }
+
+    /// Dry-run counterpart of the transfer: work out what would become of a
+    /// source, printing and recording it without writing anything
+    fn plan_transfer(&self, original_path: &Path, dest_dir: &Path, dates: &MediaDates, extension: &str) {
+        let plan = self
+            .digest(original_path)
+            .map_err(|e| anyhow::anyhow!("Failed to read file: {}", e))
+            .and_then(|digest| self.find_planned_destination(original_path, &digest, dest_dir, dates, extension));
+        let plan = match plan {
+            Ok(plan) => plan,
+            Err(e) => {
+                self.record_failure(original_path, &e);
+                return;
+            }
+        };
+
+        match &plan {
+            Plan::New(dest) => println!("+ NEW: {} → {}", original_path.display(), dest.display()),
+            Plan::DuplicateOf(existing) => {
+                println!("= DUPLICATE-OF {}: {}", existing.display(), original_path.display())
+            }
+            Plan::WouldCollide(dest) => println!(
+                "≠ WOULD-COLLIDE: {} → {} (a different file has its name)",
+                original_path.display(),
+                dest.display()
+            ),
+        }
+        self.stats.lock().unwrap().planned.push((original_path.to_path_buf(), plan));
+    }
+
+    /// Find the name a source would be archived under, or the file it
+    /// duplicates, as `claim_destination` would. Sources planned earlier in
+    /// the run stand in for the library files they would become
+    fn find_planned_destination(
+        &self,
+        source: &Path,
+        digest: &ContentDigest,
+        dest_dir: &Path,
+        dates: &MediaDates,
+        extension: &str,
+    ) -> Result<Plan> {
+        if let Some(existing) = self.find_global_duplicate(source, digest) {
+            return Ok(Plan::DuplicateOf(existing));
+        }
+
+        let mut planned = self.planned_names.lock().unwrap();
+        for counter in 1..=self.max_collisions() {
+            let path = dest_dir.join(generate_filename(dates, extension, counter, &self.settings.name_format));
+            let existing = match planned.get(&path) {
+                Some(planned_source) => planned_source.clone(),
+                None if self.file_ops.exists(&path) => path.clone(),
+                None => {
+                    planned.insert(path.clone(), source.to_path_buf());
+                    if let Some(index) = self.library_index.lock().unwrap().as_mut() {
+                        index.insert(source.to_path_buf(), digest.len, Some(digest.hash.clone()));
+                    }
+                    return Ok(if counter == 1 { Plan::New(path) } else { Plan::WouldCollide(path) });
+                }
+            };
+
+            match self.is_identical(source, digest, &existing) {
+                Ok(true) => return Ok(Plan::DuplicateOf(existing)),
+                Ok(false) => {}
+                Err(e) => eprintln!("Warning: failed to read {}: {}", existing.display(), e),
+            }
+        }
+
+        Err(too_many_collisions(dest_dir, dates, extension, self.max_collisions(), &self.settings.name_format))
+    }
+
+    /// Write the --dedupe-report-only report listing library duplicates and
+    /// groups of identical source files
+    fn write_dedupe_report(&self, stats: &ProcessingStats, report_path: &Path) -> Result<()> {
+        let mut report = String::new();
+
+        report.push_str("=== DUPLICATES OF FILES ALREADY IN THE LIBRARY ===\n");
+        for (source, dest) in &stats.duplicates {
+            report.push_str(&format!("{}\n   → Duplicate of: {}\n", source.display(), dest.display()));
+        }
+        report.push('\n');
+
+        report.push_str("=== IDENTICAL FILES AMONG THE SOURCES ===\n");
+        let mut groups: Vec<(&String, &Vec<PathBuf>)> = stats
+            .source_hashes
+            .iter()
+            .filter(|(_, paths)| paths.len() > 1)
+            .collect();
+        groups.sort_by(|a, b| a.1[0].cmp(&b.1[0]));
+        for (hash, paths) in &groups {
+            report.push_str(&format!(
+                "{} {} ({} files)\n",
+                self.settings.checksum_algo.name(),
+                hash,
+                paths.len()
+            ));
+            for path in paths.iter() {
+                report.push_str(&format!("  {}\n", path.display()));
+            }
+        }
+
+        if !stats.suspicious_dates.is_empty() {
+            report.push('\n');
+            report.push_str(&format!("=== SUSPICIOUS DATES (before {}) ===\n", self.suspicious_threshold()));
+            for line in suspicious_date_lines(&stats.suspicious_dates) {
+                report.push_str(&line);
+                report.push('\n');
+            }
+        }
+
+        if self.settings.phash_dedupe.is_some() {
+            report.push('\n');
+            report.push_str("=== PROBABLE NEAR-DUPLICATES (--phash-dedupe) ===\n");
+            for (image, similar, d) in &stats.near_duplicates {
+                report.push_str(&format!("{}\n   ≈ Similar to: {} (distance {})\n", image.display(), similar.display(), d));
+            }
+        }
+
+        fs::write(report_path, report)
+            .with_context(|| format!("Failed to write duplicate report to {}", report_path.display()))?;
+
+        println!();
+        println!(
+            "Duplicate report: {} library duplicates, {} groups of identical sources",
+            stats.duplicates.len(),
+            groups.len()
+        );
+        println!("Report written to: {}", report_path.display());
+        Ok(())
+    }
+
+    /// Remember a file dated before the date policy's plausible threshold,
+    /// for the summary's list of them
+    fn note_suspicious_date(&self, path: &Path, dates: &MediaDates) {
+        let policy = &self.settings.extract.date_policy;
+        let suspect = [dates.creation_date, dates.modify_date]
+            .into_iter()
+            .filter(|date| policy.is_suspicious(date))
+            .min();
+        if let Some(date) = suspect {
+            self.stats.lock().unwrap().suspicious_dates.push((path.to_path_buf(), date));
+        }
+    }
+
+    /// Count a failed file and record it in Failed Cases
+    fn record_failure(&self, file_path: &Path, error: &anyhow::Error) {
+        let mut stats = self.stats.lock().unwrap();
+        stats.failed += 1;
+        let extension = get_extension(file_path).unwrap_or_default().to_uppercase();
+        *stats.failed_by_extension.entry(extension).or_default() += 1;
+        drop(stats);
+        self.emit(ProcessEvent::Failed {
+            path: file_path.to_path_buf(),
+            reason: format!("{:#}", error),
+        });
+
+        if self.settings.read_only() {
+            // Report-only runs leave the output untouched
+            style::eprint(Style::Failure, format_args!("✗ Failed: {}: {:#}", file_path.display(), error));
+        } else {
+            // Files on a read-only volume can only be linked to
+            let read_only_source = file_path.parent().is_some_and(|dir| self.read_only_dirs.contains(dir));
+            let move_original = self.settings.move_failed && !read_only_source;
+            if let Err(handle_err) = handle_failed_file(file_path, &self.failed_cases_dir, error, move_original) {
+                eprintln!("Error handling failed file: {}", handle_err);
+            }
+        }
+
+        if self.settings.on_failure == FailurePolicy::Stop {
+            self.stats.lock().unwrap().aborted.get_or_insert_with(|| {
+                format!("--on-failure stop: {} failed: {:#}", file_path.display(), error)
+            });
+        }
+    }
+
+    /// The date policy's plausible-date threshold, for summary headings
+    fn suspicious_threshold(&self) -> String {
+        use chrono::{Datelike, Timelike};
+        let before = self.settings.extract.date_policy.suspicious_before;
+        if before.ordinal0() == 0 && before.num_seconds_from_midnight() == 0 {
+            before.format("%Y").to_string()
+        } else {
+            before.format("%Y-%m-%d").to_string()
+        }
+    }
+
+    /// Check if a file's dates are suspicious enough to set it aside for
+    /// manual review (--review-suspicious)
+    fn needs_review(&self, info: &MediaInfo) -> bool {
+        self.settings.review_suspicious && self.settings.extract.date_policy.is_suspicious(&info.dates.creation_date)
+    }
+
+    /// Directory a file will be archived into
+    fn destination_dir(&self, info: &MediaInfo, corrupt: bool, relative_dir: &Path) -> PathBuf {
+        let mut dest_dir = self.output_dir.clone();
+        if corrupt {
+            dest_dir.push(CORRUPT_FOLDER);
+        } else if self.needs_review(info) {
+            dest_dir.push("Review");
+        }
+        if self.settings.split_media {
+            dest_dir.push(info.media_folder(&self.settings.media_folders));
+        }
+        match info.gps {
+            Some((latitude, longitude)) if self.settings.organize_by_location => {
+                dest_dir.push(location_folder(latitude, longitude, self.settings.location_grid.unwrap_or(DEFAULT_LOCATION_GRID)));
+            }
+            _ => {
+                let (primary, _) = info.dates.ordered(self.settings.name_format.primary_date);
+                let local = self.settings.name_format.timezone.convert(primary);
+                dest_dir.push(date_folder(&local, self.settings.organize_by));
+            }
+        }
+        if self.settings.preserve_structure {
+            dest_dir.push(relative_dir);
+        }
+        dest_dir
+    }
+
+    /// Size, content hash (--checksum-algo) and modification time of a file,
+    /// read in chunks through `file_ops`
+    fn digest(&self, path: &Path) -> io::Result<ContentDigest> {
+        let metadata = self.file_ops.metadata(path)?;
+        Ok(ContentDigest {
+            len: metadata.len,
+            hash: self.settings.checksum_algo.hash_reader(self.file_ops.open(path)?)?,
+            modified: metadata.modified,
+        })
+    }
+
+    /// Check whether `existing` has the content `source` was digested to:
+    /// sizes first, then a chunked byte comparison. The existing file isn't
+    /// hashed first, since that reads all of it even when (as for most
+    /// same-size videos) the two differ within the first chunk
+    fn is_identical(&self, source: &Path, digest: &ContentDigest, existing: &Path) -> io::Result<bool> {
+        if self.file_ops.metadata(existing)?.len != digest.len {
+            return Ok(false);
+        }
+        readers_equal(self.file_ops.open(source)?, self.file_ops.open(existing)?)
+    }
+
+    /// Highest filename counter tried for one date pair (--max-collisions)
+    fn max_collisions(&self) -> u32 {
+        self.settings.max_collisions.unwrap_or(DEFAULT_MAX_COLLISIONS)
+    }
+
+    /// Find the first counter whose filename is free in `dest_dir`, or an
+    /// existing file with identical content. A free path is reserved until the
+    /// returned guard is dropped, so concurrent transfers never claim the same
+    /// name; reserved names are skipped like taken ones
+    fn claim_destination(
+        &self,
+        source: &Path,
+        digest: &ContentDigest,
+        dest_dir: &Path,
+        dates: &MediaDates,
+        extension: &str,
+    ) -> Result<Claim<'_>> {
+        for counter in 1..=self.max_collisions() {
+            let path = dest_dir.join(generate_filename(dates, extension, counter, &self.settings.name_format));
+
+            {
+                // Checking existence under the lock means a path can't be
+                // written between the check and the reservation
+                let mut reserved = self.reserved.lock().unwrap();
+                if reserved.contains(&path) {
+                    continue;
+                }
+                if !self.file_ops.exists(&path) {
+                    reserved.insert(path.clone());
+                    return Ok(Claim::Free(Reservation {
+                        reserved: &self.reserved,
+                        path,
+                    }));
+                }
+            }
+
+            // Taken: it's either this file already archived, or a different file
+            match self.is_identical(source, digest, &path) {
+                Ok(true) => return Ok(Claim::Duplicate(path)),
+                Ok(false) => {}
+                Err(e) => eprintln!("Warning: failed to read {}: {}", path.display(), e),
+            }
+        }
+
+        Err(too_many_collisions(dest_dir, dates, extension, self.max_collisions(), &self.settings.name_format))
+    }
+
+    /// Move or copy a file to a destination reserved by `claim_destination`
+    fn transfer_file(&self, file_path: &Path, target_path: &Path, should_move: bool) -> Result<ProcessResult> {
+        check_path_length(target_path)?;
+
+        match self.move_or_copy(file_path, target_path, should_move) {
+            Err(e) if self.settings.fix_perms && is_permission_error(&e) => {
+                eprintln!(
+                    "Permission denied for {}, adding owner read/write and retrying",
+                    file_path.display()
+                );
+                add_owner_permissions(file_path)?;
+                self.move_or_copy(file_path, target_path, should_move)
+            }
+            result => result,
+        }
+    }
+
+    fn move_or_copy(&self, file_path: &Path, target_path: &Path, should_move: bool) -> Result<ProcessResult> {
+        let read_only_source = file_path.parent().is_some_and(|dir| self.read_only_dirs.contains(dir));
+        let keep_source = self.settings.keep_linked_sources || read_only_source;
+        if self.settings.link == LinkMode::Hardlink && !read_only_source {
+            match self.file_ops.hard_link(file_path, target_path) {
+                Ok(()) => {
+                    if !keep_source {
+                        self.file_ops.remove_file(file_path).with_context(|| {
+                            format!("Failed to delete source file after linking: {}", file_path.display())
+                        })?;
+                    }
+                    self.stats.lock().unwrap().hardlinked += 1;
+                    return Ok(ProcessResult::Moved(target_path.to_path_buf()));
+                }
+                // Different devices after all: copy as usual
+                Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {}
+                Err(e) => {
+                    return Err(anyhow::Error::from(e).context(format!("Failed to link file to {}", target_path.display())))
+                }
+            }
+        }
+
+        // Transfer file to destination (move or copy depending on volume)
+        if should_move && !keep_source && self.settings.link != LinkMode::Copy {
+            // Use rename for same-volume transfers (fast, atomic)
+            self.file_ops
+                .rename(file_path, target_path)
+                .with_context(|| format!("Failed to move file to {}", target_path.display()))?;
+            Ok(ProcessResult::Moved(target_path.to_path_buf()))
+        } else {
+            // Use copy for cross-volume transfers (buffered with --copy-buffer-size
+            // or --max-rate, otherwise the platform's fast copy). The copy is
+            // written beside the target and renamed into place once complete, so
+            // an interruption never leaves a truncated file under the real name
+            let partial = partial_path(target_path);
+            if let Some(log) = &self.transfer_log {
+                if let Err(e) = log.record_partial(&partial) {
+                    eprintln!("Warning: Failed to add {} to the transfer log: {}", partial.display(), e);
+                }
+            }
+            if let Err(e) = self.file_ops.copy(file_path, &partial) {
+                // Don't leave a truncated copy behind (e.g. when the disk fills)
+                let _ = self.file_ops.remove_file(&partial);
+                return Err(e.context(format!("Failed to copy file to {}", target_path.display())));
+            }
+            if self.settings.preserve_mtime {
+                if let Err(e) = self.file_ops.copy_times(file_path, &partial) {
+                    eprintln!("Warning: Failed to copy timestamps to {}: {}", target_path.display(), e);
+                }
+            }
+            // The target was reserved, so nothing else is there to be replaced
+            if let Err(e) = self.file_ops.rename(&partial, target_path) {
+                let _ = self.file_ops.remove_file(&partial);
+                return Err(anyhow::Error::from(e).context(format!("Failed to copy file to {}", target_path.display())));
+            }
+
+            // Delete source file after successful copy
+            if !keep_source && self.settings.confirm_deletes {
+                self.stats.lock().unwrap().deferred_deletes.push(file_path.to_path_buf());
+            } else if !keep_source {
+                self.delete_source(file_path)
+                    .with_context(|| format!("Failed to delete source file after copy: {}", file_path.display()))?;
+            }
+            if read_only_source {
+                self.stats.lock().unwrap().kept_read_only += 1;
+            }
+
+            Ok(ProcessResult::Copied(target_path.to_path_buf()))
+        }
+    }
+
+    /// Delete a file the run is done with: a source that was copied or is a
+    /// duplicate, or an extra library copy. With --trash it goes to the system
+    /// trash instead
+    fn delete_source(&self, path: &Path) -> io::Result<()> {
+        if self.settings.trash {
+            self.file_ops.trash(path)
+        } else {
+            self.file_ops.remove_file(path)
+        }
+    }
+
+    /// Move a file within the output, falling back to copy-and-delete across volumes
+    fn move_path(&self, source: &Path, target: &Path) -> Result<()> {
+        if self.file_ops.rename(source, target).is_ok() {
+            return Ok(());
+        }
+
+        self.file_ops.copy(source, target)?;
+        self.file_ops
+            .remove_file(source)
+            .with_context(|| format!("Failed to delete source file after copy: {}", source.display()))?;
+        Ok(())
+    }
+
+    /// The counts and lists gathered so far, e.g. for an embedder to read
+    /// once the run has finished
+    pub fn stats(&self) -> std::sync::MutexGuard<'_, ProcessingStats> {
+        self.stats.lock().unwrap()
+    }
+
+    /// How the run went, once it has completed: scan errors outrank failed
+    /// files, since they mean files may not have been looked at at all
+    pub fn outcome(&self) -> RunOutcome {
+        let stats = self.stats.lock().unwrap();
+        if stats.scan_errors > 0 {
+            RunOutcome::ScanErrors
+        } else if stats.failed > self.settings.tolerate_failures {
+            RunOutcome::Failures
+        } else {
+            RunOutcome::Success
+        }
+    }
+
+    /// The human-readable summary: counts, per-input breakdown, and the
+    /// lists of suspicious dates and near-duplicates (--summary-format text)
+    fn print_text_summary(&self, stats: &ProcessingStats) {
+        println!();
+        println!("=== PROCESSING COMPLETE ===");
+        println!("Total files scanned: {}", stats.total_files);
+        if let Some(found) = stats.limited_from {
+            println!("  (limit applied: {} of {} files found)", stats.total_files, found);
+        }
+
+        let total_processed = stats.moved + stats.copied;
+        style::print(Style::Success, format_args!("Successfully processed: {}", total_processed));
+
+        if stats.moved > 0 {
+            println!("  - Moved (same volume): {}", stats.moved);
+        }
+        if stats.copied > 0 {
+            println!("  - Copied (cross volume): {}", stats.copied);
+        }
+        if stats.kept_read_only > 0 {
+            println!("  - Left in place on read-only sources: {}", stats.kept_read_only);
+        }
+        if stats.hardlinked > 0 {
+            println!("  - Hard-linked (--link hardlink, included in moved): {}", stats.hardlinked);
+        }
+        if stats.corrupt > 0 {
+            style::print(
+                Style::Failure,
+                format_args!(
+                    "  - Failed to decode (--validate-decode): {} (in {})",
+                    stats.corrupt,
+                    self.output_dir.join(CORRUPT_FOLDER).display()
+                ),
+            );
+        }
+        if stats.previews_archived > 0 {
+            println!("  - Previews archived beside their primary: {}", stats.previews_archived);
+        }
+        if stats.oriented > 0 {
+            println!("  - Rotated upright (--auto-orient): {}", stats.oriented);
+        }
+        if stats.provenance_tagged > 0 {
+            println!("  - Source recorded in XMP (--tag-provenance): {}", stats.provenance_tagged);
+        }
+        if stats.extensions_fixed > 0 {
+            println!("  - Extension corrected (--fix-extension): {}", stats.extensions_fixed);
+        }
+        if stats.review > 0 {
+            println!(
+                "  - Set aside for review (dated before {}): {} (in {})",
+                self.suspicious_threshold(),
+                stats.review,
+                self.output_dir.join("Review").display()
+            );
+        }
+
+        style::print(Style::Skipped, format_args!("Skipped (already exist): {}", stats.skipped));
+        if stats.duplicates_in_run > 0 {
+            println!("  - Duplicates within this run: {}", stats.duplicates_in_run);
+        }
+        if stats.redated > 0 {
+            println!("  - Library files renamed to an earlier date (--keep-earliest-date): {}", stats.redated);
+        }
+        let filtered: usize = stats.filtered.values().sum();
+        if filtered > 0 {
+            let reasons: Vec<String> = SkipReason::ALL
+                .iter()
+                .filter_map(|&reason| stats.filtered.get(&reason).map(|&count| reason.describe(count)))
+                .collect();
+            println!("Filtered out: {} ({})", filtered, reasons.join(", "));
+        }
+        if !stats.same_shots.is_empty() {
+            println!("Same shot, kept for review (--dedupe-by identity): {}", stats.same_shots.len());
+            for (later, earlier) in &stats.same_shots {
+                println!("  {} = {}", later.display(), earlier.display());
+            }
+        }
+        if stats.replaced > 0 {
+            println!(
+                "Conflicts settled (--on-conflict): {} (losers in {})",
+                stats.replaced,
+                self.output_dir.join("Replaced").display()
+            );
+        }
+        if !stats.empty.is_empty() {
+            if self.settings.quarantine_empty && !self.settings.read_only() {
+                println!(
+                    "Empty (zero bytes): {} (moved to {})",
+                    stats.empty.len(),
+                    self.output_dir.join("Empty Files").display()
+                );
+            } else {
+                println!("Empty (zero bytes): {}", stats.empty.len());
+            }
+        }
+        if stats.failed > 0 {
+            style::print(Style::Failure, format_args!("Failed: {}", stats.failed));
+        } else {
+            println!("Failed: 0");
+        }
+        if stats.permission_errors > 0 {
+            println!("  - Permission denied: {}", stats.permission_errors);
+        }
+        if stats.unread > 0 {
+            println!("  - Never read by a worker: {}", stats.unread);
+        }
+        if stats.exec_failures > 0 {
+            println!("--exec command failures: {}", stats.exec_failures);
+        }
+        if stats.scan_errors > 0 {
+            style::print(
+                Style::Failure,
+                format_args!("Paths that couldn't be scanned: {}", stats.scan_errors),
+            );
+        }
+
+        // Per-input breakdown, when there is more than one input to tell apart
+        if stats.by_source.len() > 1 {
+            println!();
+            println!("By input:");
+            for (source, counts) in &stats.by_source {
+                println!(
+                    "  {}: {} moved, {} copied, {} skipped, {} failed",
+                    source.display(),
+                    counts.moved,
+                    counts.copied,
+                    counts.skipped,
+                    counts.failed
+                );
+            }
+        }
+
+        if self.settings.list_unsupported && !stats.failed_by_extension.is_empty() {
+            println!();
+            println!("Unsupported/failed by extension:");
+            for line in extension_failure_lines(&stats.failed_by_extension) {
+                println!("  {}", line);
+            }
+        }
+
+        if stats.failed > 0 && !self.settings.read_only() {
+            println!();
+            println!(
+                "Failed cases have been logged in: {}",
+                self.failed_cases_dir.display()
+            );
+        }
+
+        if !stats.suspicious_dates.is_empty() {
+            println!();
+            println!(
+                "=== SUSPICIOUS DATES (before {}, {} files) ===",
+                self.suspicious_threshold(),
+                stats.suspicious_dates.len()
+            );
+            println!();
+            for line in suspicious_date_lines(&stats.suspicious_dates) {
+                println!("{}", line);
+            }
+        }
+
+        if !stats.near_duplicates.is_empty() && self.settings.dedupe_report.is_none() {
+            println!();
+            println!("=== PROBABLE NEAR-DUPLICATES (for review, nothing is deleted) ===");
+            println!();
+            for (image, similar, d) in &stats.near_duplicates {
+                println!("Archived: {}", image.display());
+                println!("   ≈ Similar to: {} (distance {})", similar.display(), d);
+            }
+        }
+    }
+
+    /// How many --dry-run sources are duplicates, and their total size:
+    /// those matching a file already in the library, and those only matching
+    /// another source planned earlier in the run
+    fn duplicate_savings(&self, stats: &ProcessingStats) -> DuplicateSavings {
+        let mut savings = DuplicateSavings::default();
+        for (source, plan) in &stats.planned {
+            let Plan::DuplicateOf(existing) = plan else {
+                continue;
+            };
+            let len = self.file_ops.metadata(source).map_or(0, |metadata| metadata.len);
+            let tally = match existing.starts_with(&self.output_dir) {
+                true => &mut savings.in_library,
+                false => &mut savings.in_sources,
+            };
+            tally.0 += 1;
+            tally.1 += len;
+        }
+        savings
+    }
+
+    /// The summary as one JSON object (--summary-format json)
+    fn summary_json(&self, stats: &ProcessingStats) -> serde_json::Value {
+        let paths = |paths: &[PathBuf]| paths.iter().map(|path| path.display().to_string()).collect::<Vec<_>>();
+        let mut summary = serde_json::json!({
+            "total_files": stats.total_files,
+            "limited_from": stats.limited_from,
+            "moved": stats.moved,
+            "copied": stats.copied,
+            "hardlinked": stats.hardlinked,
+            "kept_read_only": stats.kept_read_only,
+            "corrupt": stats.corrupt,
+            "review": stats.review,
+            "previews_archived": stats.previews_archived,
+            "oriented": stats.oriented,
+            "provenance_tagged": stats.provenance_tagged,
+            "extensions_fixed": stats.extensions_fixed,
+            "skipped": stats.skipped,
+            "duplicates_in_run": stats.duplicates_in_run,
+            "redated": stats.redated,
+            "same_shots": stats
+                .same_shots
+                .iter()
+                .map(|(later, earlier)| serde_json::json!({
+                    "file": later.display().to_string(),
+                    "same_shot_as": earlier.display().to_string(),
+                }))
+                .collect::<Vec<_>>(),
+            "filtered": SkipReason::ALL
+                .iter()
+                .filter_map(|reason| Some((format!("{:?}", reason).to_lowercase(), (*stats.filtered.get(reason)?).into())))
+                .collect::<serde_json::Map<_, _>>(),
+            "replaced": stats.replaced,
+            "empty": paths(&stats.empty),
+            "failed": stats.failed,
+            "failed_by_extension": stats.failed_by_extension,
+            "permission_errors": stats.permission_errors,
+            "unread": stats.unread,
+            "exec_failures": stats.exec_failures,
+            "scan_errors": stats.scan_errors,
+            "by_input": stats
+                .by_source
+                .iter()
+                .map(|(source, counts)| serde_json::json!({
+                    "input": source.display().to_string(),
+                    "moved": counts.moved,
+                    "copied": counts.copied,
+                    "skipped": counts.skipped,
+                    "failed": counts.failed,
+                }))
+                .collect::<Vec<_>>(),
+            "duplicates": stats
+                .duplicates
+                .iter()
+                .map(|(source, dest)| serde_json::json!({
+                    "source": source.display().to_string(),
+                    "duplicate_of": dest.display().to_string(),
+                }))
+                .collect::<Vec<_>>(),
+            "suspicious_dates": stats
+                .suspicious_dates
+                .iter()
+                .map(|(path, date)| serde_json::json!({
+                    "file": path.display().to_string(),
+                    "date": date.to_rfc3339(),
+                }))
+                .collect::<Vec<_>>(),
+            "near_duplicates": stats
+                .near_duplicates
+                .iter()
+                .map(|(image, similar, d)| serde_json::json!({
+                    "file": image.display().to_string(),
+                    "similar_to": similar.display().to_string(),
+                    "distance": d,
+                }))
+                .collect::<Vec<_>>(),
+        });
+        if self.settings.dry_run {
+            let savings = self.duplicate_savings(stats);
+            summary["already_in_library"] =
+                serde_json::json!({ "files": savings.in_library.0, "bytes": savings.in_library.1 });
+            summary["duplicates_of_sources"] =
+                serde_json::json!({ "files": savings.in_sources.0, "bytes": savings.in_sources.1 });
+            summary["planned"] = stats
+                .planned
+                .iter()
+                .map(|(source, plan)| {
+                    let (plan, path) = match plan {
+                        Plan::New(path) => ("new", path),
+                        Plan::DuplicateOf(path) => ("duplicate-of", path),
+                        Plan::WouldCollide(path) => ("would-collide", path),
+                    };
+                    serde_json::json!({
+                        "source": source.display().to_string(),
+                        "plan": plan,
+                        "path": path.display().to_string(),
+                    })
+                })
+                .collect();
+        }
+        summary
+    }
+
+    fn print_summary(&self) {
+        let stats = self.stats.lock().unwrap();
+        if self.settings.summary_format != SummaryFormat::Json {
+            self.print_text_summary(&stats);
+        }
+        if self.settings.summary_format != SummaryFormat::Text {
+            let summary = serde_json::to_string_pretty(&self.summary_json(&stats)).unwrap();
+            match self.summary_out.as_ref() {
+                Some(mut out) => {
+                    if let Err(e) = writeln!(out, "{}", summary) {
+                        eprintln!("Error: Failed to write the summary: {}", e);
+                    }
+                }
+                None => println!("{}", summary),
+            }
+        }
+
+        if self.settings.dry_run {
+            if self.settings.summary_format != SummaryFormat::Json {
+                let count = |wanted: fn(&Plan) -> bool| stats.planned.iter().filter(|(_, plan)| wanted(plan)).count();
+                let savings = self.duplicate_savings(&stats);
+                let mb = |bytes| bytes as f64 / 1_048_576.0;
+                println!();
+                println!("=== DRY RUN (nothing was changed) ===");
+                println!("New: {}", count(|plan| matches!(plan, Plan::New(_))));
+                println!(
+                    "Already in the library: {} ({:.2} MB that would not be imported again)",
+                    savings.in_library.0,
+                    mb(savings.in_library.1)
+                );
+                if savings.in_sources.0 > 0 {
+                    println!(
+                        "Duplicates of other sources: {} ({:.2} MB)",
+                        savings.in_sources.0,
+                        mb(savings.in_sources.1)
+                    );
+                }
+                println!("Would collide with a different file: {}", count(|plan| matches!(plan, Plan::WouldCollide(_))));
+            }
+            return;
+        }
+
+        if let Some(report_path) = &self.settings.dedupe_report {
+            if let Err(e) = self.write_dedupe_report(&stats, report_path) {
+                eprintln!("Error: {:#}", e);
+            }
+            return;
+        }
+
+        // Handle duplicates cleanup
+        if !stats.duplicates.is_empty() {
+            // In JSON the duplicates are listed in the summary object
+            if self.settings.summary_format != SummaryFormat::Json {
+                println!();
+                println!("=== DUPLICATE FILES ===");
+                println!();
+
+                // Calculate total size
+                let mut total_size: u64 = 0;
+                for (source, _) in &stats.duplicates {
+                    if let Ok(metadata) = self.file_ops.metadata(source) {
+                        total_size += metadata.len;
+                    }
+                }
+
+                // Display each duplicate with its match
+                for (source, dest) in &stats.duplicates {
+                    println!("Source: {}", source.display());
+                    println!("   → Duplicate of: {}", dest.display());
+                    println!();
+                }
+
+                // Show summary
+                let size_mb = total_size as f64 / 1_048_576.0;
+                println!("Total: {} duplicates ({:.2} MB)", stats.duplicates.len(), size_mb);
+                println!();
+            }
+
+            // We need to drop the lock before prompting for input
+            // Clone the duplicates list so we can use it after dropping the lock
+            let duplicates = stats.duplicates.clone();
+            drop(stats);
+
+            if self.settings.no_prompt {
+                println!("Left the {} duplicate source files in place (--no-prompt)", duplicates.len());
+                return;
+            }
+
+            if self.settings.interactive_dupes {
+                resolve_duplicates_interactively(&duplicates, |source| self.delete_source(source));
+                return;
+            }
+
+            // Prompt for confirmation
+            print!("Delete these {} duplicate source files? (y/n): ", duplicates.len());
+            io::stdout().flush().unwrap();
+
+            let mut input = String::new();
+            if io::stdin().read_line(&mut input).is_ok() {
+                let input = input.trim().to_lowercase();
+                if input == "y" || input == "yes" {
+                    println!();
+                    println!("Deleting duplicate source files...");
+                    let mut deleted = 0;
+                    let mut failed = 0;
+
+                    for (source, _) in &duplicates {
+                        match self.delete_source(source) {
+                            Ok(_) => {
+                                deleted += 1;
+                                style::print(Style::Success, format_args!("✓ Deleted: {}", source.display()));
+                            }
+                            Err(e) => {
+                                failed += 1;
+                                style::eprint(Style::Failure, format_args!("✗ Failed to delete {}: {}", source.display(), e));
+                            }
+                        }
+                    }
+
+                    println!();
+                    println!("Cleanup complete: {} deleted, {} failed", deleted, failed);
+                } else {
+                    println!();
+                    println!("Duplicate source files were not deleted.");
+                }
+            }
+        }
+    }
+}
+
+/// Ask about each duplicate source file in turn (--interactive-dupes),
+/// removing those chosen with `delete`
+fn resolve_duplicates_interactively(duplicates: &[(PathBuf, PathBuf)], delete: impl Fn(&Path) -> io::Result<()>) {
+    let mut deleted = 0;
+    let mut failed = 0;
+    let mut delete_all = false;
+
+    for (index, (source, dest)) in duplicates.iter().enumerate() {
+        println!("[{}/{}] Source: {}", index + 1, duplicates.len(), describe_file(source));
+        println!("   → Duplicate of: {}", describe_file(dest));
+
+        let delete_source = if delete_all {
+            true
+        } else {
+            print!("Delete source? [k]eep / [d]elete / [s]kip all / delete [a]ll: ");
+            io::stdout().flush().unwrap();
+
+            let mut input = String::new();
+            if io::stdin().read_line(&mut input).unwrap_or(0) == 0 {
+                // End of input: keep everything that remains
+                println!();
+                break;
+            }
+
+            match input.trim().to_lowercase().as_str() {
+                "d" | "delete" => true,
+                "s" | "skip all" => break,
+                "a" | "delete all" => {
+                    delete_all = true;
+                    true
+                }
+                _ => false,
+            }
+        };
+
+        if delete_source {
+            match delete(source) {
+                Ok(_) => {
+                    deleted += 1;
+                    style::print(Style::Success, format_args!("✓ Deleted: {}", source.display()));
+                }
+                Err(e) => {
+                    failed += 1;
+                    style::eprint(Style::Failure, format_args!("✗ Failed to delete {}: {}", source.display(), e));
+                }
+            }
+        }
+        println!();
+    }
+
+    println!("Cleanup complete: {} deleted, {} failed", deleted, failed);
+}
+
+/// Path with its size and modification time, for duplicate prompts
+fn describe_file(path: &Path) -> String {
+    match fs::metadata(path) {
+        Ok(metadata) => {
+            let size_mb = metadata.len() as f64 / 1_048_576.0;
+            match metadata.modified() {
+                Ok(modified) => format!(
+                    "{} ({:.2} MB, modified {})",
+                    path.display(),
+                    size_mb,
+                    chrono::DateTime::<chrono::Utc>::from(modified).format("%Y-%m-%d %H:%M:%S UTC")
+                ),
+                Err(_) => format!("{} ({:.2} MB)", path.display(), size_mb),
+            }
+        }
+        Err(_) => path.display().to_string(),
+    }
+}
+
+enum ProcessResult {
+    Moved(PathBuf),  // Contains the destination path the file was written to
+    Copied(PathBuf), // Contains the destination path the file was written to
+}
+
+/// Take a duplicate of file descriptor `fd` for --progress-fd, so the
+/// stream can be owned (and closed) without touching the caller's descriptor
+fn open_progress_fd(fd: i32) -> Result<fs::File> {
+    use std::os::fd::FromRawFd;
+    // SAFETY: dup only reads the descriptor table; it fails for a closed fd
+    let copy = unsafe { libc::dup(fd) };
+    if copy < 0 {
+        return Err(anyhow::Error::from(io::Error::last_os_error()))
+            .with_context(|| format!("--progress-fd {} is not an open file descriptor", fd));
+    }
+    // SAFETY: `copy` is a new descriptor that nothing else owns
+    Ok(unsafe { fs::File::from_raw_fd(copy) })
+}
+
+/// One --progress-fd line
+fn progress_line(done: usize, total: usize, path: &Path) -> String {
+    serde_json::json!({
+        "done": done,
+        "total": total,
+        "path": path.to_string_lossy(),
+    })
+    .to_string()
+}
+
+/// Failure counts by extension, most failures first (--list-unsupported)
+fn extension_failure_lines(by_extension: &BTreeMap<String, usize>) -> Vec<String> {
+    let mut counts: Vec<(&String, &usize)> = by_extension.iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    counts
+        .into_iter()
+        .map(|(extension, count)| match extension.as_str() {
+            "" => format!("(no extension): {} failed", count),
+            extension => format!(".{}: {} failed", extension, count),
+        })
+        .collect()
+}
+
+/// The files with suspiciously early dates, grouped under each suspect day
+/// (often a camera's reset clock, such as 2000-01-01) in date order
+fn suspicious_date_lines(entries: &[(PathBuf, chrono::DateTime<chrono::Utc>)]) -> Vec<String> {
+    let mut by_day: BTreeMap<chrono::NaiveDate, Vec<&Path>> = BTreeMap::new();
+    for (path, date) in entries {
+        by_day.entry(date.date_naive()).or_default().push(path);
+    }
+
+    let mut lines = Vec::new();
+    for (day, mut paths) in by_day {
+        paths.sort();
+        lines.push(format!("{} ({} files)", day, paths.len()));
+        lines.extend(paths.iter().map(|path| format!("  {}", path.display())));
+    }
+    lines
+}
+
+/// Threads reading directories during the scan; mostly waiting on the disk or
+/// network, so not tied to the number of CPUs
+const SCAN_THREADS: usize = 8;
+
+/// Run `scan` on each item on up to SCAN_THREADS threads, returning the
+/// results in no particular order
+fn scan_in_parallel<T: Send, R: Send>(items: Vec<T>, scan: impl Fn(T) -> R + Sync) -> Vec<R> {
+    if items.len() <= 1 {
+        return items.into_iter().map(scan).collect();
+    }
+    let threads = items.len().min(SCAN_THREADS);
+    let queue = Mutex::new(items.into_iter());
+    let results = Mutex::new(Vec::new());
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let Some(item) = queue.lock().unwrap().next() else {
+                    break;
+                };
+                let result = scan(item);
+                results.lock().unwrap().push(result);
+            });
+        }
+    });
+    results.into_inner().unwrap()
+}
+
+/// A source file's size and content hash, which stand in for its content in
+/// duplicate checks so large videos are never read into memory
+#[derive(Debug, Clone)]
+struct ContentDigest {
+    len: u64,
+    hash: String,
+    /// Modification time, recorded with the size in the transfer log
+    modified: Option<SystemTime>,
+}
+
+/// A source archived this run, where it went, and the dates it was named by
+#[derive(Debug, Clone)]
+struct RunSource {
+    source: PathBuf,
+    dest: PathBuf,
+    dates: MediaDates,
+}
+
+/// Outcome of looking for a file's destination name
+enum Claim<'a> {
+    /// An identical file is already archived at this path
+    Duplicate(PathBuf),
+    /// This path was free and is now reserved for the file
+    Free(Reservation<'a>),
+}
+
+/// A destination path claimed by one in-flight transfer, released on drop
+struct Reservation<'a> {
+    reserved: &'a Mutex<HashSet<PathBuf>>,
+    path: PathBuf,
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        self.reserved.lock().unwrap().remove(&self.path);
+    }
+}
+
+/// Split work items into one queue per source volume (device id), keeping
+/// each queue in the original order. Files whose volume can't be read are
+/// queued together rather than failing here; the worker reports the error
+fn partition_by_volume(files: Vec<WorkItem>) -> Vec<Vec<WorkItem>> {
+    use std::os::unix::fs::MetadataExt;
+
+    let mut queues: BTreeMap<u64, Vec<WorkItem>> = BTreeMap::new();
+    for item in files {
+        let dev = fs::metadata(&item.path).map(|metadata| metadata.dev()).unwrap_or(0);
+        queues.entry(dev).or_default().push(item);
+    }
+    queues.into_values().collect()
+}
+
+/// Worker thread function
+fn worker_thread(
+    worker_id: usize,
+    work_receiver: Receiver<WorkItem>,
+    result_sender: Sender<WorkerResult>,
+    options: ExtractOptions,
+    validate_decode: bool,
+) {
+    // Create ExifTool instance for this worker
+    let mut reader = match MetadataReader::new(&options) {
+        Ok(reader) => reader,
+        Err(e) => {
+            eprintln!("Worker {}: Failed to initialize ExifTool: {}", worker_id, e);
+            return;
+        }
+    };
+
+    // Process work items in batches with progressive sizing
+    let mut batch = Vec::new();
+    let mut current_batch_size = INITIAL_BATCH_SIZE; // Start at 50
+
+    for work_item in work_receiver {
+        batch.push(work_item);
+
+        if batch.len() >= current_batch_size {
+            if !process_batch(&mut reader, &batch, &result_sender, &options, validate_decode) {
+                return; // Main thread stopped taking results (run aborted)
+            }
+            batch.clear();
+
+            // Grow batch size: 50 → 60 → 70 → ... → MAX_BATCH_SIZE
+            current_batch_size = (current_batch_size + BATCH_SIZE_INCREMENT).min(MAX_BATCH_SIZE);
+        }
+    }
+
+    // Process remaining files in the last batch
+    if !batch.is_empty() {
+        process_batch(&mut reader, &batch, &result_sender, &options, validate_decode);
+    }
+}
+
+/// Extract and send back results for a batch, returning false once the main
+/// thread has stopped receiving them
+fn process_batch(
+    reader: &mut MetadataReader,
+    batch: &[WorkItem],
+    result_sender: &Sender<WorkerResult>,
+    options: &ExtractOptions,
+    validate_decode: bool,
+) -> bool {
+    // Extract metadata for all files in batch
+    let paths: Vec<PathBuf> = batch.iter().map(|item| item.path.clone()).collect();
+    let metadata_results = extract_dates_batch(reader, &paths, options);
+
+    // Process each file with its metadata
+    for work_item in batch {
+        let file_path = &work_item.path;
+        let dates_result = metadata_results.get(file_path);
+
+        let result = match dates_result {
+            Some(Ok(info)) => {
+                // We have metadata, extract extension
+                match source_extension(file_path, info) {
+                    Some(extension) => Ok(ProcessedFile {
+                        info: info.clone(),
+                        extension,
+                        decode_error: (validate_decode && info.kind() == MediaKind::Image)
+                            .then(|| decode_error(file_path))
+                            .flatten(),
+                    }),
+                    None => Err(anyhow::anyhow!("File has no extension and exiftool can't tell its type")),
+                }
+            }
+            Some(Err(e)) => {
+                // Metadata extraction failed
+                Err(anyhow::anyhow!("{}", e))
+            }
+            None => {
+                // Shouldn't happen, but handle gracefully
+                Err(anyhow::anyhow!("No metadata result for file"))
+            }
+        };
+
+        let worker_result = WorkerResult {
+            item: work_item.clone(),
+            result,
+        };
+
+        // Send result back to main thread
+        if result_sender.send(worker_result).is_err() {
+            return false; // Main thread has shut down
+        }
+    }
+
+    true
+}
+
+/// The extension to archive a file under: its own, or for a file without
+/// one, the extension of the file type exiftool detected
+fn source_extension(path: &Path, info: &MediaInfo) -> Option<String> {
+    get_extension(path).or_else(|| info.file_type_extension.clone().filter(|ext| !ext.is_empty()))
+}
+
+/// Self-test of the collision counter logic: archives `count` distinct
+/// synthetic files that all share the same dates into a temporary library,
+/// reading them with exiftool on the worker pool as a real run does, and
+/// checks they land on counters 1..=count without overwriting each other
+pub fn run_collision_selftest(count: usize) -> Result<()> {
+    collision_selftest(count, Settings::default())
+}
+
+fn collision_selftest(count: usize, settings: Settings) -> Result<()> {
+    let root = std::env::temp_dir().join(format!("collect_media_selftest_{}", std::process::id()));
+    let source_dir = root.join("source");
+    let output_dir = root.join("output");
+    fs::create_dir_all(&source_dir)
+        .with_context(|| format!("Failed to create self-test directory: {}", source_dir.display()))?;
+
+    let result = selftest_in(&source_dir, &output_dir, count, settings);
+
+    if let Err(e) = fs::remove_dir_all(&root) {
+        eprintln!("Warning: failed to clean up {}: {}", root.display(), e);
+    }
+
+    result?;
+    println!("Self-test passed: {} colliding files received counters 1..={}", count, count);
+    Ok(())
+}
+
+fn selftest_in(source_dir: &Path, output_dir: &Path, count: usize, settings: Settings) -> Result<()> {
+    // Every source has the same modification date, which exiftool falls
+    // back to as the files carry no EXIF
+    let modified = SystemTime::from(chrono::Utc::now() - chrono::Duration::days(1));
+
+    // Write distinct synthetic sources, remembering their contents since
+    // archiving removes sources after transferring them. Each is a bare JPEG
+    // (start and end markers) with a numbered comment, so exiftool reads it
+    let mut expected = Vec::with_capacity(count);
+    for i in 1..=count {
+        let path = source_dir.join(format!("selftest_{}.jpg", i));
+        let comment = format!("collect_media self-test file {}", i);
+        let mut content = vec![0xFF, 0xD8, 0xFF, 0xFE];
+        content.extend_from_slice(&(comment.len() as u16 + 2).to_be_bytes());
+        content.extend_from_slice(comment.as_bytes());
+        content.extend_from_slice(&[0xFF, 0xD9]);
+        fs::write(&path, &content)
+            .and_then(|_| fs::File::options().write(true).open(&path)?.set_modified(modified))
+            .with_context(|| format!("Failed to write self-test file: {}", path.display()))?;
+        expected.push((path, content));
+    }
+
+    let processor = Processor::new(output_dir.to_path_buf(), settings)?;
+    let items = expected.iter().map(|(path, _)| WorkItem::new(path.clone(), source_dir)).collect();
+    processor.process_files_parallel(items)?;
+
+    let stats = processor.stats.lock().unwrap();
+    if stats.failed > 0 || stats.skipped > 0 {
+        anyhow::bail!(
+            "Self-test failed: {} files failed and {} were skipped as duplicates",
+            stats.failed,
+            stats.skipped
+        );
+    }
+    drop(stats);
+
+    // The dates exiftool read, which the names are built from
+    let dates = processor.run_sources.lock().unwrap().values().next().map(|run_source| run_source.dates.clone());
+    let Some(dates) = dates else {
+        anyhow::bail!("Self-test failed: no file was archived");
+    };
+
+    let mut remaining: Vec<Vec<u8>> = expected.into_iter().map(|(_, content)| content).collect();
+    for counter in 1..=count as u32 {
+        let path = output_dir.join(generate_filename(&dates, "jpg", counter, &NameFormat::default()));
+        let content = fs::read(&path)
+            .with_context(|| format!("Self-test failed: expected {} to exist", path.display()))?;
+        match remaining.iter().position(|c| *c == content) {
+            Some(index) => {
+                remaining.swap_remove(index);
+            }
+            None => anyhow::bail!("Self-test failed: {} overwrote or duplicated another file", path.display()),
+        }
+    }
+
+    let extra = output_dir.join(generate_filename(&dates, "jpg", count as u32 + 1, &NameFormat::default()));
+    if extra.exists() {
+        anyhow::bail!("Self-test failed: unexpected extra file {}", extra.display());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fileops::MemoryFs;
+    use crate::filename::{generate_filename_without_counter, CounterStyle};
+    use crate::settings::GpsFilter;
+    use crate::test_util::{write_stub_exiftool, TempDir};
+
+    #[test]
+    fn test_is_permission_error() {
+        let denied = anyhow::Error::from(io::Error::from(io::ErrorKind::PermissionDenied))
+            .context("Failed to move file");
+        assert!(is_permission_error(&denied));
+
+        let missing = anyhow::Error::from(io::Error::from(io::ErrorKind::NotFound));
+        assert!(!is_permission_error(&missing));
+    }
+
+    #[test]
+    fn test_on_conflict_keep_larger() {
+        let root = TempDir::new("conflict");
+        let source_dir = root.join("source");
+        let output_dir = root.join("output");
+        fs::create_dir_all(&source_dir).unwrap();
+
+        let dates = dates_on(2024, 5, 6);
+        let settings = Settings {
+            on_conflict: ConflictPolicy::KeepLarger,
+            ..Settings::default()
+        };
+        let processor = Processor::new(output_dir.clone(), settings).unwrap();
+
+        for (name, content) in [("small.jpg", "small"), ("large.jpg", "larger file"), ("tiny.jpg", "tiny")] {
+            let path = source_dir.join(name);
+            fs::write(&path, content).unwrap();
+            handle_read(&processor, WorkItem::new(path, &source_dir), info(&dates));
+        }
+
+        let first = output_dir.join(generate_filename(&dates, "jpg", 1, &NameFormat::default()));
+        assert_eq!(fs::read(&first).unwrap(), b"larger file");
+        assert!(!output_dir.join(generate_filename(&dates, "jpg", 2, &NameFormat::default())).exists());
+        assert_eq!(fs::read_dir(output_dir.join("Replaced")).unwrap().count(), 2);
+        assert_eq!(processor.stats.lock().unwrap().replaced, 2);
+    }
+
+    #[test]
+    fn test_on_conflict_skips_duplicate_at_later_counter() {
+        let root = TempDir::new("conflict_counter");
+        let source_dir = root.join("source");
+        let output_dir = root.join("output");
+        fs::create_dir_all(&source_dir).unwrap();
+        fs::create_dir_all(&output_dir).unwrap();
+
+        let dates = dates_on(2024, 5, 6);
+        let name = |counter| output_dir.join(generate_filename(&dates, "jpg", counter, &NameFormat::default()));
+        fs::write(name(1), "small").unwrap();
+        fs::write(name(2), "larger file").unwrap();
+
+        for _ in 0..2 {
+            let settings = Settings {
+                on_conflict: ConflictPolicy::KeepLarger,
+                ..Settings::default()
+            };
+            let processor = Processor::new(output_dir.clone(), settings).unwrap();
+            let path = source_dir.join("copy.jpg");
+            fs::write(&path, "larger file").unwrap();
+            handle_read(&processor, WorkItem::new(path, &source_dir), info(&dates));
+
+            let stats = processor.stats.lock().unwrap();
+            assert_eq!((stats.moved, stats.skipped, stats.replaced), (0, 1, 0));
+            assert_eq!(stats.duplicates, vec![(source_dir.join("copy.jpg"), name(2))]);
+        }
+        assert_eq!(fs::read(name(1)).unwrap(), b"small");
+        assert_eq!(fs::read(name(2)).unwrap(), b"larger file");
+        assert!(!name(3).exists());
+        assert!(!output_dir.join("Replaced").exists());
+    }
+
+    #[test]
+    fn test_on_conflict_set_aside_is_not_archived() {
+        let root = TempDir::new("conflict_aside");
+        let source_dir = root.join("source");
+        let output_dir = root.join("output");
+        fs::create_dir_all(&source_dir).unwrap();
+
+        let dates = dates_on(2024, 5, 6);
+        let settings = Settings {
+            on_conflict: ConflictPolicy::KeepLarger,
+            ..Settings::default()
+        };
+        let processor = Processor::new(output_dir.clone(), settings).unwrap();
+        for (name, content) in [("large.jpg", "larger file"), ("small.jpg", "small")] {
+            let path = source_dir.join(name);
+            fs::write(&path, content).unwrap();
+            handle_read(&processor, WorkItem::new(path, &source_dir), info(&dates));
+        }
+
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!((stats.moved, stats.skipped, stats.replaced), (1, 1, 1));
+        assert!(stats.duplicates.is_empty());
+        assert_eq!(processor.run_sources.lock().unwrap().len(), 1);
+        assert_eq!(fs::read_dir(output_dir.join("Replaced")).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_collect_files_skips_hidden() {
+        let root = TempDir::new("hidden");
+        for dir in ["DCIM", ".Trashes/501", ".Spotlight-V100"] {
+            fs::create_dir_all(root.join("input").join(dir)).unwrap();
+        }
+        for file in ["DCIM/IMG_0001.JPG", ".Trashes/501/IMG_0002.JPG", ".Spotlight-V100/store.db", ".hidden.jpg"] {
+            fs::write(root.join("input").join(file), b"x").unwrap();
+        }
+
+        let mut settings = Settings {
+            recursive: true,
+            ..Settings::default()
+        };
+        let processor = Processor::new(root.join("output"), settings.clone()).unwrap();
+        let files = processor.collect_files(&root.join("input"), &root.join("input"), usize::MAX).unwrap();
+        assert_eq!(files.len(), 1);
+        assert!(files[0].path.ends_with("DCIM/IMG_0001.JPG"));
+
+        settings.include_hidden = true;
+        let processor = Processor::new(root.join("output"), settings).unwrap();
+        assert_eq!(processor.collect_files(&root.join("input"), &root.join("input"), usize::MAX).unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_scan_trees() {
+        let root = TempDir::new("scan");
+        for dir in ["a/DCIM/100CANON/deeper", "a/.Trashes", "b/Misc"] {
+            fs::create_dir_all(root.join(dir)).unwrap();
+        }
+        for file in [
+            "a/top.jpg",
+            "a/DCIM/100CANON/IMG_0001.JPG",
+            "a/DCIM/100CANON/Thumbs.db",
+            "a/DCIM/100CANON/deeper/IMG_0002.JPG",
+            "a/.Trashes/IMG_0003.JPG",
+            "b/Misc/IMG_0004.JPG",
+        ] {
+            fs::write(root.join(file), b"x").unwrap();
+        }
+
+        let processor = Processor::new(root.join("output"), Settings::default()).unwrap();
+        let (a, b) = (root.join("a"), root.join("b"));
+        let mut files = processor.scan_trees(&[(&a, &a), (&b, &b)], 3);
+        files.sort_by(|x, y| x.path.cmp(&y.path));
+        let found: Vec<(PathBuf, PathBuf, PathBuf)> = files
+            .into_iter()
+            .map(|item| (item.path.strip_prefix(&root).unwrap().to_path_buf(), item.relative_dir, item.source))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (PathBuf::from("a/DCIM/100CANON/IMG_0001.JPG"), PathBuf::from("DCIM/100CANON"), a.clone()),
+                (PathBuf::from("a/top.jpg"), PathBuf::new(), a.clone()),
+                (PathBuf::from("b/Misc/IMG_0004.JPG"), PathBuf::from("Misc"), b.clone()),
+            ]
+        );
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!(stats.filtered[&SkipReason::Hidden], 1);
+        assert_eq!(stats.filtered[&SkipReason::Ignored], 1);
+    }
+
+    #[test]
+    fn test_scan_skips_output_inside_input() {
+        let root = TempDir::new("scan_output");
+        let input = root.join("input");
+        fs::create_dir_all(input.join("2020")).unwrap();
+        let processor = Processor::new(input.join("Library"), Settings::default()).unwrap();
+        for file in [
+            "IMG_0001.JPG",
+            "2020/IMG_0002.JPG",
+            "Library/2020-01-01 10.00.00.jpg",
+            "Library/Failed Cases/x.jpg",
+        ] {
+            fs::write(input.join(file), b"x").unwrap();
+        }
+
+        // Reached through a path that isn't canonical
+        let start = root.join("input/../input");
+        let mut found: Vec<PathBuf> = processor
+            .scan_trees(&[(&start, &start)], usize::MAX)
+            .into_iter()
+            .map(|item| item.path.strip_prefix(&start).unwrap().to_path_buf())
+            .collect();
+        found.sort();
+        assert_eq!(found, [PathBuf::from("2020/IMG_0002.JPG"), PathBuf::from("IMG_0001.JPG")]);
+    }
+
+    #[test]
+    fn test_is_disk_full_error() {
+        let full = anyhow::Error::from(io::Error::from(io::ErrorKind::StorageFull))
+            .context("Failed to copy file");
+        assert!(is_disk_full_error(&full));
+        assert!(!is_disk_full_error(&anyhow::anyhow!("No valid creation date found")));
+    }
+
+    /// Creation and modification dates both at noon UTC on the given day
+    fn dates_on(year: i32, month: u32, day: u32) -> MediaDates {
+        let date = chrono::TimeZone::with_ymd_and_hms(&chrono::Utc, year, month, day, 12, 0, 0).unwrap();
+        MediaDates {
+            creation_date: date,
+            modify_date: date,
+        }
+    }
+
+    /// Metadata holding only `dates`; set other fields with struct update
+    fn info(dates: &MediaDates) -> MediaInfo {
+        MediaInfo {
+            dates: dates.clone(),
+            file_type: None,
+            mime_type: None,
+            gps: None,
+            orientation: None,
+            file_type_extension: None,
+            identity: None,
+            creation_tag: None,
+            modify_tag: None,
+        }
+    }
+
+    /// Hand `item` to the processor as if a worker had read `info` for it,
+    /// with the extension of its path
+    fn handle_read(processor: &Processor, item: WorkItem, info: MediaInfo) {
+        let extension = get_extension(&item.path).unwrap_or_default();
+        processor.handle_worker_result(WorkerResult {
+            item,
+            result: Ok(ProcessedFile {
+                info,
+                extension,
+                decode_error: None,
+            }),
+        });
+    }
+
+    /// Hand a file to the processor as if a worker had extracted `dates` for it
+    fn archive_with_dates(processor: &Processor, path: &Path, dates: &MediaDates) {
+        handle_read(processor, WorkItem::new(path.to_path_buf(), Path::new("/src")), info(dates));
+    }
+
+    #[test]
+    fn test_collisions_and_duplicates_in_memory() {
+        let dates = dates_on(2024, 5, 6);
+
+        for counter_style in [CounterStyle::Always, CounterStyle::OnCollision] {
+            let file_ops = Arc::new(MemoryFs::default());
+            let mut settings = Settings::default();
+            settings.name_format.counter_style = counter_style;
+            let format = settings.name_format.clone();
+            let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+
+            for (name, content) in [("a.jpg", "A"), ("b.jpg", "B"), ("c.jpg", "A")] {
+                let path = Path::new("/src").join(name);
+                file_ops.write(&path, content.as_bytes());
+                archive_with_dates(&processor, &path, &dates);
+            }
+
+            let name = |counter| Path::new("/out").join(generate_filename(&dates, "jpg", counter, &format));
+            assert_eq!(file_ops.read(&name(1)).unwrap(), b"A");
+            assert_eq!(file_ops.read(&name(2)).unwrap(), b"B");
+            // The duplicate stays in place for the deletion prompt
+            let mut expected = vec![name(1), name(2), PathBuf::from("/src/c.jpg")];
+            expected.sort();
+            assert_eq!(file_ops.files(), expected);
+
+            let stats = processor.stats.lock().unwrap();
+            assert_eq!((stats.moved, stats.copied, stats.skipped), (2, 0, 1));
+            assert_eq!(stats.duplicates, vec![(PathBuf::from("/src/c.jpg"), name(1))]);
+        }
+    }
+
+    #[test]
+    fn test_events_in_memory() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let mut processor =
+            Processor::with_file_ops(PathBuf::from("/out"), Settings::default(), file_ops.clone()).unwrap();
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        processor.set_event_sender(sender);
+        let dates = dates_on(2024, 5, 6);
+
+        for name in ["a.jpg", "b.jpg"] {
+            file_ops.write(&Path::new("/src").join(name), b"A");
+            archive_with_dates(&processor, &Path::new("/src").join(name), &dates);
+        }
+        processor.handle_worker_result(WorkerResult {
+            item: WorkItem::new(PathBuf::from("/src/c.jpg"), Path::new("/src")),
+            result: Err(anyhow::anyhow!("No date tags")),
+        });
+
+        let archived = Path::new("/out").join(generate_filename(&dates, "jpg", 1, &NameFormat::default()));
+        let path = |name| PathBuf::from("/src").join(name);
+        assert_eq!(
+            receiver.try_iter().collect::<Vec<_>>(),
+            vec![
+                ProcessEvent::Started { path: path("a.jpg") },
+                ProcessEvent::Moved { src: path("a.jpg"), dst: archived.clone() },
+                ProcessEvent::Started { path: path("b.jpg") },
+                ProcessEvent::Skipped { path: path("b.jpg"), duplicate_of: archived },
+                ProcessEvent::Started { path: path("c.jpg") },
+                ProcessEvent::Failed { path: path("c.jpg"), reason: "No date tags".to_string() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_gps_filter_in_memory() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let settings = Settings { gps_filter: Some(GpsFilter::WithGps), ..Settings::default() };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+        let dates = dates_on(2020, 5, 6);
+
+        for (name, gps) in [("tagged.jpg", Some((48.8584, 2.2945))), ("untagged.jpg", None)] {
+            let path = Path::new("/src").join(name);
+            file_ops.write(&path, name.as_bytes());
+            handle_read(&processor, WorkItem::new(path, Path::new("/src")), MediaInfo { gps, ..info(&dates) });
+        }
+
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!(stats.moved, 1);
+        assert_eq!(stats.filtered[&SkipReason::Gps], 1);
+        assert!(file_ops.exists(Path::new("/src/untagged.jpg")));
+        assert!(!file_ops.exists(Path::new("/src/tagged.jpg")));
+    }
+
+    #[test]
+    fn test_dedupe_by_identity_in_memory() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let settings = Settings { dedupe_by: DedupeBy::Identity, ..Settings::default() };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+        let dates = dates_on(2024, 5, 6);
+        let identity = |serial: &str| ShotIdentity {
+            date_time_original: "2024:05:06 07:08:09".to_string(),
+            serial_number: serial.to_string(),
+            width: "6000".to_string(),
+            height: "4000".to_string(),
+        };
+
+        // Two exports of one shot, and the same moment on another body
+        for (name, serial) in [("export1.jpg", "0123"), ("export2.jpg", "0123"), ("other.jpg", "4567")] {
+            let path = Path::new("/src").join(name);
+            file_ops.write(&path, name.as_bytes());
+            let info = MediaInfo { identity: Some(identity(serial)), ..info(&dates) };
+            handle_read(&processor, WorkItem::new(path, Path::new("/src")), info);
+        }
+
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!(stats.moved, 3);
+        let name = |counter| Path::new("/out").join(generate_filename(&dates, "jpg", counter, &NameFormat::default()));
+        assert_eq!(stats.same_shots, vec![(name(2), name(1))]);
+    }
+
+    #[test]
+    fn test_quarantine_empty_in_memory() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let settings = Settings { quarantine_empty: true, ..Settings::default() };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+        file_ops.write(Path::new("/out/Empty Files/IMG_0001.JPG"), b"");
+        file_ops.write(Path::new("/src/IMG_0001.JPG"), b"");
+
+        processor.handle_empty_files(vec![WorkItem::new(PathBuf::from("/src/IMG_0001.JPG"), Path::new("/src"))]);
+        assert_eq!(
+            file_ops.files(),
+            vec![PathBuf::from("/out/Empty Files/IMG_0001-1.JPG"), PathBuf::from("/out/Empty Files/IMG_0001.JPG")]
+        );
+        assert_eq!(processor.stats.lock().unwrap().empty, [PathBuf::from("/src/IMG_0001.JPG")]);
+    }
+
+    #[test]
+    fn test_report_duplicates_in_memory() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let processor =
+            Processor::with_file_ops(PathBuf::from("/out"), Settings::default(), file_ops.clone()).unwrap();
+        let dates = dates_on(2024, 5, 6);
+        let name = |counter| Path::new("/out").join(generate_filename(&dates, "jpg", counter, &NameFormat::default()));
+        file_ops.write(&name(1), b"first");
+        file_ops.write(&name(2), b"second");
+        file_ops.write(Path::new("/src/copy.jpg"), b"second");
+
+        processor.report_duplicates(Path::new("/src/copy.jpg"), Path::new("/out"), &dates, "jpg");
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!(stats.duplicates, vec![(PathBuf::from("/src/copy.jpg"), name(2))]);
+        assert_eq!(stats.skipped, 1);
+    }
+
+    #[test]
+    fn test_skip_oversized_in_memory() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let settings = Settings { max_read_size: Some(4), ..Settings::default() };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+        file_ops.write(Path::new("/src/small.jpg"), b"1234");
+        file_ops.write(Path::new("/src/huge.mov"), b"12345");
+
+        let files = ["/src/small.jpg", "/src/huge.mov"]
+            .iter()
+            .map(|path| WorkItem::new(PathBuf::from(path), Path::new("/src")))
+            .collect();
+        let kept: Vec<PathBuf> = processor.skip_oversized(files).into_iter().map(|item| item.path).collect();
+        assert_eq!(kept, vec![PathBuf::from("/src/small.jpg")]);
+        assert_eq!(processor.stats.lock().unwrap().filtered[&SkipReason::TooLarge], 1);
+    }
+
+    #[test]
+    fn test_emit_sidecar_in_memory() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let settings = Settings { emit_sidecar: true, ..Settings::default() };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+        let dates = dates_on(2022, 3, 4);
+        file_ops.write(Path::new("/src/a.jpg"), b"A");
+        archive_with_dates(&processor, Path::new("/src/a.jpg"), &dates);
+
+        let archived = Path::new("/out").join(generate_filename(&dates, "jpg", 1, &NameFormat::default()));
+        let sidecar = sidecar_path(&archived);
+        assert_eq!(file_ops.files(), vec![archived, sidecar.clone()]);
+        let json: serde_json::Value = serde_json::from_slice(&file_ops.read(&sidecar).unwrap()).unwrap();
+        assert_eq!(json["source"], "/src/a.jpg");
+        assert_eq!(json["creation_date"], "2022-03-04T12:00:00+00:00");
+    }
+
+    #[test]
+    fn test_max_collisions() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let settings = Settings { max_collisions: Some(2), ..Settings::default() };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+        let dates = dates_on(2024, 5, 6);
+        for counter in 1..=2 {
+            let name = generate_filename(&dates, "jpg", counter, &NameFormat::default());
+            file_ops.write(&Path::new("/out").join(name), &[counter as u8]);
+        }
+        file_ops.write(Path::new("/src/burst.jpg"), b"third");
+
+        let digest = processor.digest(Path::new("/src/burst.jpg")).unwrap();
+        let source = Path::new("/src/burst.jpg");
+        let result = processor.claim_destination(source, &digest, Path::new("/out"), &dates, "jpg");
+        let message = format!("{:#}", result.err().unwrap());
+        let base = generate_filename_without_counter(&dates, "jpg", &NameFormat::default());
+        assert!(message.contains(&format!("2 files already share the dates of '{}' in /out", base)), "{}", message);
+    }
+
+    #[test]
+    fn test_unwritable_output_fails_fast() {
+        let file_ops = Arc::new(MemoryFs::default());
+        file_ops.mount_read_only(Path::new("/share"));
+        let result = Processor::with_file_ops(PathBuf::from("/share/out"), Settings::default(), file_ops.clone());
+        let err = result.err().unwrap();
+        assert!(format!("{:#}", err).contains("not writable: /share/out"), "{:#}", err);
+
+        // Report-only runs don't write, so a read-only output is fine
+        let settings = Settings { dry_run: true, ..Settings::default() };
+        assert!(Processor::with_file_ops(PathBuf::from("/share/out"), settings, file_ops).is_ok());
+    }
+
+    #[test]
+    fn test_summary_json() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let processor =
+            Processor::with_file_ops(PathBuf::from("/out"), Settings::default(), file_ops.clone()).unwrap();
+        let dates = dates_on(2024, 5, 6);
+        for name in ["a.jpg", "b.jpg"] {
+            let path = Path::new("/src").join(name);
+            file_ops.write(&path, b"A");
+            archive_with_dates(&processor, &path, &dates);
+        }
+        processor.count_skipped(SkipReason::Hidden, 3);
+
+        let summary = processor.summary_json(&processor.stats.lock().unwrap());
+        assert_eq!(summary["moved"], 1);
+        assert_eq!(summary["skipped"], 1);
+        assert_eq!(summary["filtered"], serde_json::json!({ "hidden": 3 }));
+        assert_eq!(summary["duplicates"][0]["source"], "/src/b.jpg");
+        assert!(summary.get("planned").is_none());
+    }
+
+    #[test]
+    fn test_dedupe_scope_global_in_memory() {
+        let day = |day| dates_on(2024, 5, day);
+
+        for (scope, archived) in [(DedupeScope::DateBucket, 2), (DedupeScope::Global, 1)] {
+            let file_ops = Arc::new(MemoryFs::default());
+            let settings = Settings {
+                dedupe_scope: scope,
+                ..Settings::default()
+            };
+            let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+
+            // The same bytes with different embedded dates
+            for (name, days) in [("a.jpg", 1), ("b.jpg", 2)] {
+                let path = Path::new("/src").join(name);
+                file_ops.write(&path, b"A");
+                archive_with_dates(&processor, &path, &day(days));
+            }
+
+            let stats = processor.stats.lock().unwrap();
+            assert_eq!((stats.moved, stats.skipped), (archived, 2 - archived));
+        }
+    }
+
+    #[test]
+    fn test_duplicates_within_run_in_memory() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), Settings::default(), file_ops.clone()).unwrap();
+        let dates = dates_on(2024, 5, 6);
+
+        for path in ["/backup1/a.jpg", "/backup2/a.jpg", "/backup2/b.jpg"] {
+            file_ops.write(Path::new(path), if path.ends_with("b.jpg") { b"B" } else { b"A" });
+            archive_with_dates(&processor, Path::new(path), &dates);
+        }
+
+        let first = Path::new("/out").join(generate_filename(&dates, "jpg", 1, &NameFormat::default()));
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!((stats.moved, stats.skipped, stats.duplicates_in_run), (2, 1, 1));
+        assert_eq!(stats.duplicates, vec![(PathBuf::from("/backup2/a.jpg"), first)]);
+    }
+
+    #[test]
+    fn test_keep_earliest_date_in_memory() {
+        let day = |day| dates_on(2025, 1, day);
+        let name = |dates: &MediaDates| Path::new("/out").join(generate_filename(dates, "jpg", 1, &NameFormat::default()));
+        let file_ops = Arc::new(MemoryFs::default());
+        let settings = Settings {
+            dedupe_scope: DedupeScope::Global,
+            keep_earliest_date: true,
+            ..Settings::default()
+        };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+
+        // Archived this run, then identical sources dated earlier and in between
+        for (source, day_of_month) in [("/src/a.jpg", 3), ("/src/b.jpg", 1), ("/src/c.jpg", 2)] {
+            file_ops.write(Path::new(source), b"A");
+            archive_with_dates(&processor, Path::new(source), &day(day_of_month));
+        }
+
+        assert_eq!(file_ops.files(), vec![name(&day(1)), "/src/b.jpg".into(), "/src/c.jpg".into()]);
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!((stats.moved, stats.skipped, stats.redated), (1, 2, 1));
+        assert_eq!(stats.transfers, vec![(PathBuf::from("/src/a.jpg"), name(&day(1)))]);
+    }
+
+    #[test]
+    fn test_fix_extension_in_memory() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let settings = Settings {
+            fix_extension: true,
+            ..Settings::default()
+        };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+        let dates = dates_on(2024, 5, 6);
+
+        // A HEIC saved as .jpg, and a .jpeg that really is a JPEG
+        for (name, detected) in [("a.jpg", "heic"), ("b.jpeg", "jpg")] {
+            let path = Path::new("/src").join(name);
+            file_ops.write(&path, name.as_bytes());
+            let info = MediaInfo { file_type_extension: Some(detected.to_string()), ..info(&dates) };
+            handle_read(&processor, WorkItem::new(path, Path::new("/src")), info);
+        }
+
+        let name = |ext, counter| Path::new("/out").join(generate_filename(&dates, ext, counter, &NameFormat::default()));
+        assert_eq!(file_ops.files(), vec![name("heic", 1), name("jpg", 1)]);
+        assert_eq!(processor.stats.lock().unwrap().extensions_fixed, 1);
+    }
+
+    #[test]
+    fn test_link_hardlink_in_memory() {
+        let source = Path::new("/src/IMG_0001.JPG");
+        let target = Path::new("/out/2024-01-01 photo.jpg");
+        for (same_volume, keep_linked_sources) in [(true, false), (true, true), (false, true)] {
+            let file_ops = Arc::new(MemoryFs::new(same_volume));
+            let settings = Settings {
+                link: LinkMode::Hardlink,
+                keep_linked_sources,
+                ..Settings::default()
+            };
+            let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+            file_ops.write(source, b"photo");
+
+            // Across devices the link fails and the file is copied instead
+            let result = processor.transfer_file(source, target, false).unwrap();
+            let linked = processor.stats.lock().unwrap().hardlinked == 1;
+            assert_eq!(linked, same_volume);
+            assert_eq!(matches!(result, ProcessResult::Moved(_)), same_volume);
+            assert_eq!(file_ops.read(target).unwrap(), b"photo");
+            assert_eq!(file_ops.exists(source), keep_linked_sources);
+        }
+    }
+
+    #[test]
+    fn test_dry_run_in_memory() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let settings = Settings {
+            dry_run: true,
+            ..Settings::default()
+        };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+        let dates = |day| dates_on(2024, 5, day);
+        let name = |days, counter| {
+            Path::new("/out").join(generate_filename(&dates(days), "jpg", counter, &NameFormat::default()))
+        };
+        file_ops.write(&name(1, 1), b"archived");
+
+        let sources = [("a.jpg", "archived", 1), ("b.jpg", "different", 1), ("c.jpg", "different", 1), ("d.jpg", "new", 2)];
+        for (source, content, days) in sources {
+            let path = Path::new("/src").join(source);
+            file_ops.write(&path, content.as_bytes());
+            archive_with_dates(&processor, &path, &dates(days));
+        }
+
+        // c.jpg matches the source planned before it, not a library file
+        let stats = processor.stats.lock().unwrap();
+        let planned: Vec<&Plan> = stats.planned.iter().map(|(_, plan)| plan).collect();
+        assert_eq!(
+            planned,
+            [
+                &Plan::DuplicateOf(name(1, 1)),
+                &Plan::WouldCollide(name(1, 2)),
+                &Plan::DuplicateOf(PathBuf::from("/src/b.jpg")),
+                &Plan::New(name(2, 1)),
+            ]
+        );
+        assert_eq!(file_ops.files().len(), 5, "nothing was written or removed");
+        assert_eq!(
+            processor.duplicate_savings(&stats),
+            DuplicateSavings { in_library: (1, 8), in_sources: (1, 9) }
+        );
+    }
+
+    #[test]
+    fn test_read_only_source_in_memory() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let mut processor =
+            Processor::with_file_ops(PathBuf::from("/out"), Settings::default(), file_ops.clone()).unwrap();
+        file_ops.write(Path::new("/card/DCIM/IMG_0001.JPG"), b"locked");
+        file_ops.write(Path::new("/disk/IMG_0002.JPG"), b"writable");
+        file_ops.mount_read_only(Path::new("/card"));
+
+        let items = [
+            WorkItem::new(PathBuf::from("/card/DCIM/IMG_0001.JPG"), Path::new("/card")),
+            WorkItem::new(PathBuf::from("/disk/IMG_0002.JPG"), Path::new("/disk")),
+        ];
+        processor.find_read_only_dirs(&items);
+
+        // Same volume, so both would be renamed, but the card's file is copied
+        for (item, target) in items.iter().zip(["/out/a.jpg", "/out/b.jpg"]) {
+            processor.transfer_file(&item.path, Path::new(target), true).unwrap();
+        }
+        assert!(file_ops.exists(Path::new("/card/DCIM/IMG_0001.JPG")));
+        assert!(!file_ops.exists(Path::new("/disk/IMG_0002.JPG")));
+        assert_eq!(file_ops.read(Path::new("/out/a.jpg")).unwrap(), b"locked");
+        assert_eq!(processor.stats.lock().unwrap().kept_read_only, 1);
+    }
+
+    #[test]
+    fn test_cross_volume_copy_in_memory() {
+        let file_ops = Arc::new(MemoryFs::new(false));
+        let processor =
+            Processor::with_file_ops(PathBuf::from("/out"), Settings::default(), file_ops.clone()).unwrap();
+        let dates = dates_on(2024, 5, 6);
+
+        file_ops.write(Path::new("/src/a.jpg"), b"A");
+        archive_with_dates(&processor, Path::new("/src/a.jpg"), &dates);
+
+        let target = Path::new("/out").join(generate_filename(&dates, "jpg", 1, &NameFormat::default()));
+        assert_eq!(file_ops.files(), vec![target]);
+        assert_eq!(processor.stats.lock().unwrap().copied, 1);
+    }
+
+    #[test]
+    fn test_confirm_deletes_defers_source_removal() {
+        let file_ops = Arc::new(MemoryFs::new(false));
+        let settings = Settings { confirm_deletes: true, ..Settings::default() };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+
+        file_ops.write(Path::new("/src/a.jpg"), b"A");
+        processor.transfer_file(Path::new("/src/a.jpg"), Path::new("/out/a.jpg"), false).unwrap();
+        assert!(file_ops.exists(Path::new("/src/a.jpg")));
+        assert_eq!(file_ops.read(Path::new("/out/a.jpg")).unwrap(), b"A");
+        assert_eq!(processor.stats.lock().unwrap().deferred_deletes, vec![PathBuf::from("/src/a.jpg")]);
+    }
+
+    #[test]
+    fn test_trash_in_memory() {
+        let file_ops = Arc::new(MemoryFs::new(false));
+        let settings = Settings { trash: true, ..Settings::default() };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+
+        file_ops.write(Path::new("/src/a.jpg"), b"A");
+        processor.transfer_file(Path::new("/src/a.jpg"), Path::new("/out/a.jpg"), false).unwrap();
+        assert!(!file_ops.exists(Path::new("/src/a.jpg")));
+        assert_eq!(file_ops.trashed(), vec![PathBuf::from("/src/a.jpg")]);
+    }
+
+    #[test]
+    fn test_preserve_mtime_in_memory() {
+        let file_ops = Arc::new(MemoryFs::new(false));
+        let settings = Settings { preserve_mtime: true, ..Settings::default() };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+
+        file_ops.write(Path::new("/src/a.jpg"), b"A");
+        let modified = file_ops.metadata(Path::new("/src/a.jpg")).unwrap().modified;
+        processor.transfer_file(Path::new("/src/a.jpg"), Path::new("/out/a.jpg"), false).unwrap();
+        assert!(!file_ops.exists(Path::new("/src/a.jpg")));
+        assert_eq!(file_ops.metadata(Path::new("/out/a.jpg")).unwrap().modified, modified);
+        // The .part file the copy was written to was renamed into place
+        assert_eq!(file_ops.files(), vec![PathBuf::from("/out/a.jpg")]);
+    }
+
+    #[test]
+    fn test_pair_previews_sidecar_in_memory() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let settings = Settings {
+            pair_previews: Some(PreviewPolicy::Sidecar),
+            ..Settings::default()
+        };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, file_ops.clone()).unwrap();
+        let dates = dates_on(2024, 5, 6);
+
+        let files: Vec<WorkItem> = ["/src/IMG_0001.JPG", "/src/IMG_0001.DNG"]
+            .iter()
+            .map(|path| {
+                file_ops.write(Path::new(path), path.as_bytes());
+                WorkItem::new(PathBuf::from(path), Path::new("/src"))
+            })
+            .collect();
+        let mut files = processor.group_pairs(files, PreviewPolicy::Sidecar);
+        assert_eq!(files.len(), 1);
+        let item = files.remove(0);
+        assert_eq!(item.path, Path::new("/src/IMG_0001.DNG"));
+        assert_eq!(item.previews, vec![PathBuf::from("/src/IMG_0001.JPG")]);
+
+        handle_read(&processor, item, info(&dates));
+
+        let name = |ext| Path::new("/out").join(generate_filename(&dates, ext, 1, &NameFormat::default()));
+        assert_eq!(file_ops.files(), vec![name("DNG"), name("JPG")]);
+        assert_eq!(file_ops.read(&name("JPG")).unwrap(), b"/src/IMG_0001.JPG");
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!((stats.moved, stats.previews_archived), (1, 1));
+    }
+
+    #[test]
+    fn test_run_outcome() {
+        let settings = Settings {
+            tolerate_failures: 1,
+            ..Settings::default()
+        };
+        let processor = Processor::with_file_ops(PathBuf::from("/out"), settings, Arc::new(MemoryFs::default())).unwrap();
+        assert_eq!(processor.outcome(), RunOutcome::Success);
+
+        processor.stats.lock().unwrap().failed = 1;
+        assert_eq!(processor.outcome(), RunOutcome::Success);
+        processor.stats.lock().unwrap().failed = 2;
+        assert_eq!(processor.outcome().exit_code(), 2);
+        processor.stats.lock().unwrap().scan_errors = 1;
+        assert_eq!(processor.outcome().exit_code(), 3);
+    }
+
+    #[test]
+    fn test_corrupt_images_are_set_aside() {
+        let file_ops = Arc::new(MemoryFs::default());
+        let processor =
+            Processor::with_file_ops(PathBuf::from("/out"), Settings::default(), file_ops.clone()).unwrap();
+        let dates = dates_on(2024, 5, 6);
+
+        file_ops.write(Path::new("/src/broken.jpg"), b"half a jpeg");
+        processor.handle_worker_result(WorkerResult {
+            item: WorkItem::new(PathBuf::from("/src/broken.jpg"), Path::new("/src")),
+            result: Ok(ProcessedFile {
+                info: MediaInfo {
+                    mime_type: Some("image/jpeg".to_string()),
+                    ..info(&dates)
+                },
+                extension: "jpg".to_string(),
+                decode_error: Some("unexpected end of file".to_string()),
+            }),
+        });
+
+        let name = generate_filename(&dates, "jpg", 1, &NameFormat::default());
+        assert_eq!(file_ops.files(), vec![Path::new("/out/Corrupt").join(name)]);
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!((stats.moved, stats.corrupt), (1, 1));
+    }
+
+    #[test]
+    fn test_overlap_matrix() {
+        let hashes = |list: &[&str]| list.iter().map(|h| h.to_string()).collect::<Vec<_>>();
+        // The third input's files were too different in size to be hashed
+        let (matrix, in_others) =
+            overlap_matrix(&[4, 2, 3], &[hashes(&["a", "b", "b", "c"]), hashes(&["b", "c"]), Vec::new()]);
+        assert_eq!(matrix, vec![vec![4, 3, 0], vec![2, 2, 0], vec![0, 0, 3]]);
+        assert_eq!(in_others, vec![3, 2, 0]);
+    }
+
+    #[test]
+    fn test_prune_empty_dirs() {
+        let root = TempDir::new("prune");
+        fs::create_dir_all(root.join("DCIM/100APPLE")).unwrap();
+        fs::create_dir_all(root.join("DCIM/101APPLE")).unwrap();
+        fs::create_dir_all(root.join("Keep")).unwrap();
+        fs::write(root.join("DCIM/100APPLE/.DS_Store"), b"").unwrap();
+        fs::write(root.join("DCIM/101APPLE/._IMG_0001.JPG"), b"").unwrap();
+        fs::write(root.join("Keep/notes.txt"), b"real file").unwrap();
+
+        // DCIM empties once both of its subfolders are gone
+        assert_eq!(prune_empty_dirs(&root), 3);
+        assert!(root.exists());
+        assert!(!root.join("DCIM").exists());
+        assert!(root.join("Keep/notes.txt").exists());
+    }
+
+    #[test]
+    fn test_collect_files_counts_skip_reasons() {
+        let root = TempDir::new("skips");
+        let input = root.join("input");
+        fs::create_dir_all(input.join(".Trashes/501")).unwrap();
+        fs::write(input.join(".Trashes/501/IMG_0001.JPG"), b"trashed").unwrap();
+        fs::write(input.join(".hidden.jpg"), b"hidden").unwrap();
+        fs::write(input.join("Thumbs.db"), b"thumbs").unwrap();
+        fs::write(input.join("IMG_0002.AAE"), b"edits").unwrap();
+        fs::write(input.join("IMG_0002.JPG"), b"photo").unwrap();
+        std::os::unix::fs::symlink(input.join("IMG_0002.JPG"), input.join("link.jpg")).unwrap();
+
+        let settings = Settings {
+            skip_symlinks: true,
+            ..Settings::default()
+        };
+        let processor = Processor::new(root.join("output"), settings).unwrap();
+        let files = processor.collect_files(&input, &input, usize::MAX).unwrap();
+        // The AAE edit sidecar is kept
+        assert_eq!(files.len(), 2);
+
+        // The trash folder counts once, as it isn't descended into
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!(stats.filtered[&SkipReason::Hidden], 2);
+        assert_eq!(stats.filtered[&SkipReason::Ignored], 1);
+        assert_eq!(stats.filtered[&SkipReason::Symlink], 1);
+    }
+
+    #[test]
+    fn test_on_failure_stop() {
+        let root = TempDir::new("on_failure");
+        let settings = Settings {
+            on_failure: FailurePolicy::Stop,
+            ..Settings::default()
+        };
+        let processor = Processor::new(root.join("output"), settings).unwrap();
+
+        processor.record_failure(&root.join("IMG_0001.JPG"), &anyhow::anyhow!("No date tags"));
+        processor.record_failure(&root.join("IMG_0002.JPG"), &anyhow::anyhow!("No date tags"));
+
+        // The first failure is the one reported
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!(stats.failed, 2);
+        let reason = stats.aborted.as_deref().unwrap();
+        assert!(reason.contains("IMG_0001.JPG failed: No date tags"), "{}", reason);
+        assert_eq!(stats.failed_by_extension.get("JPG"), Some(&2));
+    }
+
+    #[test]
+    fn test_abort_with_full_result_channel() {
+        let root = TempDir::new("abort_queue");
+        let input = root.join("input");
+        fs::create_dir_all(&input).unwrap();
+        let mut settings = Settings {
+            on_failure: FailurePolicy::Stop,
+            ..Settings::default()
+        };
+        settings.extract.exiftool_path = Some(write_stub_exiftool(&root, ""));
+        let processor = Processor::new(root.join("output"), settings).unwrap();
+
+        // Far more undatable files than the result channel holds, so the
+        // workers are still sending when the first failure stops the run
+        let items: Vec<WorkItem> = (0..500)
+            .map(|i| {
+                let path = input.join(format!("IMG_{:04}.JPG", i));
+                fs::write(&path, b"not a photo").unwrap();
+                WorkItem::new(path, &input)
+            })
+            .collect();
+        processor.process_files_parallel(items).unwrap();
+
+        let stats = processor.stats.lock().unwrap();
+        assert!(stats.aborted.is_some());
+        assert!(stats.failed < 500, "{}", stats.failed);
+    }
+
+    #[test]
+    fn test_limit_counts_only_files_read() {
+        let root = TempDir::new("limit");
+        let input = root.join("input");
+        fs::create_dir_all(&input).unwrap();
+        let mut settings = Settings {
+            limit: Some(2),
+            max_read_size: Some(10),
+            ..Settings::default()
+        };
+        let date = r#","File:FileModifyDate":"2024:01:01 12:00:00+00:00""#;
+        settings.extract.exiftool_path = Some(write_stub_exiftool(&root, date));
+        let mut processor = Processor::new(root.join("output"), settings).unwrap();
+
+        // The oversized and empty files sort first, but don't use up the limit
+        let contents: [(&str, &[u8]); 5] =
+            [("a_big.jpg", b"far too many bytes"), ("b_empty.jpg", b""), ("c.jpg", b"c"), ("d.jpg", b"d"), ("e.jpg", b"e")];
+        let items = contents
+            .iter()
+            .map(|(name, content)| {
+                fs::write(input.join(name), content).unwrap();
+                WorkItem::new(input.join(name), &input)
+            })
+            .collect();
+        processor.process_work_items(items).unwrap();
+
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!(stats.moved, 2);
+        assert_eq!(stats.limited_from, Some(3));
+        assert_eq!(stats.filtered[&SkipReason::TooLarge], 1);
+        assert_eq!(stats.empty, [input.join("b_empty.jpg")]);
+        assert!(input.join("e.jpg").exists());
+    }
+
+    #[test]
+    fn test_unread_files_fail_the_run() {
+        let root = TempDir::new("unread");
+        let input = root.join("input");
+        fs::create_dir_all(&input).unwrap();
+        let mut settings = Settings::default();
+        settings.extract.exiftool_path = Some(root.join("missing-exiftool"));
+        let processor = Processor::new(root.join("output"), settings).unwrap();
+
+        let items: Vec<WorkItem> = (0..3)
+            .map(|i| {
+                let path = input.join(format!("IMG_{:04}.JPG", i));
+                fs::write(&path, b"x").unwrap();
+                WorkItem::new(path, &input)
+            })
+            .collect();
+        processor.process_files_parallel(items).unwrap();
+
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!((stats.unread, stats.failed), (3, 3));
+        drop(stats);
+        assert_eq!(processor.outcome(), RunOutcome::Failures);
+    }
+
+    #[test]
+    fn test_source_extension() {
+        let detected = |ext: Option<&str>| MediaInfo {
+            file_type_extension: ext.map(str::to_string),
+            ..info(&dates_on(2024, 5, 6))
+        };
+        assert_eq!(source_extension(Path::new("/src/IMG_0001.jpg"), &detected(Some("heic"))), Some("jpg".to_string()));
+        assert_eq!(source_extension(Path::new("/src/DSC00001"), &detected(Some("jpg"))), Some("jpg".to_string()));
+        assert_eq!(source_extension(Path::new("/src/DSC00001"), &detected(Some(""))), None);
+        assert_eq!(source_extension(Path::new("/src/DSC00001"), &detected(None)), None);
+    }
+
+    #[test]
+    fn test_progress_line() {
+        assert_eq!(
+            progress_line(12, 480, Path::new("/Volumes/Card/DCIM/IMG \"0012\".JPG")),
+            r#"{"done":12,"path":"/Volumes/Card/DCIM/IMG \"0012\".JPG","total":480}"#
+        );
+        assert!(open_progress_fd(-1).is_err());
+    }
+
+    #[test]
+    fn test_extension_failure_lines() {
+        let by_extension = BTreeMap::from([
+            ("CR3".to_string(), 40),
+            ("".to_string(), 2),
+            ("MPO".to_string(), 40),
+            ("JPG".to_string(), 1),
+        ]);
+        assert_eq!(
+            extension_failure_lines(&by_extension),
+            vec![".CR3: 40 failed", ".MPO: 40 failed", "(no extension): 2 failed", ".JPG: 1 failed"]
+        );
+    }
+
+    #[test]
+    fn test_partition_by_volume() {
+        let dir = TempDir::new("volumes");
+        let names = ["b.jpg", "a.jpg", "c.jpg"];
+        for name in names {
+            fs::write(dir.join(name), name).unwrap();
+        }
+        let mut files: Vec<WorkItem> = names.iter().map(|name| WorkItem::new(dir.join(name), &dir)).collect();
+        files.insert(1, WorkItem::new(dir.join("missing.jpg"), &dir));
+
+        let queues = partition_by_volume(files);
+        assert_eq!(queues.len(), 2);
+        let on_disk = queues.iter().find(|queue| queue.len() == 3).unwrap();
+        let order: Vec<PathBuf> = on_disk.iter().map(|item| item.path.clone()).collect();
+        assert_eq!(order, names.iter().map(|name| dir.join(name)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_suspicious_date_lines() {
+        let date = |y, m, d| chrono::TimeZone::with_ymd_and_hms(&chrono::Utc, y, m, d, 12, 0, 0).unwrap();
+        let entries = vec![
+            (PathBuf::from("/card/IMG_0002.JPG"), date(2000, 1, 1)),
+            (PathBuf::from("/card/scan.jpg"), date(1995, 6, 3)),
+            (PathBuf::from("/card/IMG_0001.JPG"), date(2000, 1, 1)),
+        ];
+        assert_eq!(
+            suspicious_date_lines(&entries),
+            [
+                "1995-06-03 (1 files)",
+                "  /card/scan.jpg",
+                "2000-01-01 (2 files)",
+                "  /card/IMG_0001.JPG",
+                "  /card/IMG_0002.JPG",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_collision_selftest() {
+        let root = TempDir::new("selftest_stub");
+        let mut settings = Settings::default();
+        let date = r#","File:FileModifyDate":"2024:01:01 12:00:00+00:00""#;
+        settings.extract.exiftool_path = Some(write_stub_exiftool(&root, date));
+        collision_selftest(5, settings).unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_transfers_claim_distinct_destinations() {
+        let root = TempDir::new("reserve");
+        let source_dir = root.join("source");
+        let output_dir = root.join("output");
+        fs::create_dir_all(&source_dir).unwrap();
+
+        let info = MediaInfo {
+            file_type: Some("JPEG".to_string()),
+            mime_type: Some("image/jpeg".to_string()),
+            ..info(&dates_on(2024, 5, 6))
+        };
+
+        let (threads, per_thread) = (8, 10);
+        let processor = Processor::new(output_dir.clone(), Settings::default()).unwrap();
+        thread::scope(|scope| {
+            for t in 0..threads {
+                let (processor, info, source_dir) = (&processor, &info, &source_dir);
+                scope.spawn(move || {
+                    for i in 0..per_thread {
+                        let path = source_dir.join(format!("{}_{}.jpg", t, i));
+                        fs::write(&path, format!("thread {} file {}", t, i)).unwrap();
+                        processor.handle_worker_result(WorkerResult {
+                            item: WorkItem::new(path, source_dir),
+                            result: Ok(ProcessedFile {
+                                info: info.clone(),
+                                extension: "jpg".to_string(),
+                                decode_error: None,
+                            }),
+                        });
+                    }
+                });
+            }
+        });
+
+        let stats = processor.stats.lock().unwrap();
+        assert_eq!((stats.failed, stats.skipped), (0, 0));
+        assert!(processor.reserved.lock().unwrap().is_empty());
+        let counts = &stats.by_source[&source_dir];
+        assert_eq!(counts.moved + counts.copied, threads * per_thread);
+
+        // Every file landed on its own counter, with nothing overwritten
+        let mut contents = HashSet::new();
+        for counter in 1..=(threads * per_thread) as u32 {
+            let path = output_dir.join(generate_filename(&info.dates, "jpg", counter, &NameFormat::default()));
+            assert!(contents.insert(fs::read(&path).unwrap()));
         }
     }
 }
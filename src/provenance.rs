@@ -0,0 +1,62 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, SecondsFormat, Utc};
+use exiftool::ExifTool;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Writes where each archived file came from into its XMP (--tag-provenance):
+/// the source path as `XMP-dc:Source` and the import time as
+/// `XMP-xmp:MetadataDate`. One exiftool process is kept open for the run
+pub struct ProvenanceWriter {
+    exiftool: Mutex<ExifTool>,
+}
+
+impl ProvenanceWriter {
+    pub fn new() -> Result<Self> {
+        let exiftool = ExifTool::new().context("Failed to start exiftool for --tag-provenance")?;
+        Ok(ProvenanceWriter { exiftool: Mutex::new(exiftool) })
+    }
+
+    /// Record `source` in the archived file at `dest`. exiftool writes the
+    /// tagged file beside it and renames it over the original, so `dest` is
+    /// left untouched if anything fails; its modification time is kept
+    pub fn tag(&self, dest: &Path, source: &Path, imported: DateTime<Utc>) -> Result<()> {
+        let dest_str = dest.to_str().ok_or_else(|| anyhow!("File path contains invalid UTF-8"))?;
+        let args = provenance_args(source, imported);
+        let mut args: Vec<&str> = args.iter().map(String::as_str).collect();
+        args.extend(["-P", "-overwrite_original", dest_str]);
+
+        self.exiftool
+            .lock()
+            .unwrap()
+            .execute_raw(&args)
+            .with_context(|| format!("exiftool failed to tag {}", dest.display()))?;
+        Ok(())
+    }
+}
+
+/// The tag assignments recording `source` and the import time
+fn provenance_args(source: &Path, imported: DateTime<Utc>) -> Vec<String> {
+    vec![
+        format!("-XMP-dc:Source={}", source.display()),
+        format!("-XMP-xmp:MetadataDate={}", imported.to_rfc3339_opts(SecondsFormat::Secs, true)),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_provenance_args() {
+        let imported = Utc.with_ymd_and_hms(2025, 3, 4, 5, 6, 7).unwrap();
+        assert_eq!(
+            provenance_args(Path::new("/Volumes/Card/DCIM/IMG_0001.JPG"), imported),
+            vec![
+                "-XMP-dc:Source=/Volumes/Card/DCIM/IMG_0001.JPG".to_string(),
+                "-XMP-xmp:MetadataDate=2025-03-04T05:06:07Z".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use anyhow::{Context, Result};
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::copy::PARTIAL_SUFFIX;
+
+/// Log in the output directory of every transfer, appended to as each file
+/// is archived so it survives an interrupted run (read by --resume)
+pub const TRANSFER_LOG_NAME: &str = ".collect_media transfers.jsonl";
+
+/// Appends one JSON line per archived file to the transfer log
+#[derive(Debug)]
+pub struct TransferLog {
+    file: Mutex<File>,
+}
+
+/// A source file recorded as archived, with the size and modification time
+/// it had then, so a different file later reusing its path (a reformatted
+/// camera card) isn't mistaken for it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoggedTransfer {
+    pub destination: PathBuf,
+    pub len: u64,
+    pub modified: Option<u64>,
+}
+
+impl TransferLog {
+    /// Open the log in `output_dir` for appending, creating it if needed
+    pub fn open(output_dir: &Path) -> Result<Self> {
+        let path = output_dir.join(TRANSFER_LOG_NAME);
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open transfer log {}", path.display()))?;
+        Ok(TransferLog { file: Mutex::new(file) })
+    }
+
+    /// Record that `source` (of size `len`, modified at `modified`) was
+    /// archived to `destination`
+    pub fn record(&self, source: &Path, destination: &Path, len: u64, modified: Option<SystemTime>) -> io::Result<()> {
+        let line = json!({
+            "source": source,
+            "destination": destination,
+            "len": len,
+            "modified_ns": modified.and_then(to_nanos),
+        });
+        writeln!(self.file.lock().unwrap(), "{}", line)
+    }
+
+    /// Record that a copy is about to be written to `partial`, so a resumed
+    /// run knows the file is its own to delete if the copy never finished
+    pub fn record_partial(&self, partial: &Path) -> io::Result<()> {
+        writeln!(self.file.lock().unwrap(), "{}", json!({ "partial": partial }))
+    }
+}
+
+/// The entries of the transfer log in `output_dir` (none if there is no log
+/// yet). A line cut short by an interruption is skipped
+fn read_log_entries(output_dir: &Path) -> Result<Vec<serde_json::Value>> {
+    let path = output_dir.join(TRANSFER_LOG_NAME);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e).with_context(|| format!("Failed to read transfer log {}", path.display())),
+    };
+    Ok(contents.lines().filter_map(|line| serde_json::from_str(line).ok()).collect())
+}
+
+/// Read the transfer log in `output_dir`, keyed by source path; an empty map
+/// if there is no log yet
+pub fn read_transfer_log(output_dir: &Path) -> Result<HashMap<PathBuf, LoggedTransfer>> {
+    let mut transfers = HashMap::new();
+    for entry in read_log_entries(output_dir)? {
+        let (Some(source), Some(destination), Some(len)) =
+            (entry["source"].as_str(), entry["destination"].as_str(), entry["len"].as_u64())
+        else {
+            continue;
+        };
+        transfers.insert(
+            PathBuf::from(source),
+            LoggedTransfer {
+                destination: PathBuf::from(destination),
+                len,
+                modified: entry["modified_ns"].as_u64(),
+            },
+        );
+    }
+    Ok(transfers)
+}
+
+impl LoggedTransfer {
+    /// Check whether a source file of size `len` modified at `modified` is
+    /// still the one that was logged
+    pub fn matches(&self, len: u64, modified: Option<SystemTime>) -> bool {
+        self.len == len && self.modified == modified.and_then(to_nanos)
+    }
+}
+
+/// Delete the `.part` files an interrupted run left in `output_dir` while
+/// copying, returning how many there were. They only ever hold a copy that
+/// didn't finish, which the resumed run makes again. Only the ones the
+/// transfer log says a run started writing are removed, so a `.part` file
+/// archived from a source, or anything in `failed_cases_dir`, is left alone
+pub fn remove_partial_copies(output_dir: &Path, failed_cases_dir: &Path) -> Result<usize> {
+    let mut removed = 0;
+    for entry in read_log_entries(output_dir)? {
+        let Some(partial) = entry["partial"].as_str().map(Path::new) else {
+            continue;
+        };
+        let ours = partial.starts_with(output_dir)
+            && !partial.starts_with(failed_cases_dir)
+            && partial.as_os_str().to_string_lossy().ends_with(PARTIAL_SUFFIX);
+        if ours && partial.is_file() {
+            fs::remove_file(partial).with_context(|| format!("Failed to remove {}", partial.display()))?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Nanoseconds since the Unix epoch
+fn to_nanos(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH)
+        .ok()
+        .and_then(|d| u64::try_from(d.as_nanos()).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::time::Duration;
+
+    #[test]
+    fn test_transfer_log() {
+        let dir = TempDir::new("resume");
+        assert!(read_transfer_log(&dir).unwrap().is_empty());
+
+        let modified = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let log = TransferLog::open(&dir).unwrap();
+        log.record(Path::new("/card/IMG_0001.JPG"), &dir.join("a.jpg"), 10, Some(modified)).unwrap();
+        log.record(Path::new("/card/IMG_0002.JPG"), &dir.join("b.jpg"), 20, None).unwrap();
+        drop(log);
+
+        // An interrupted write leaves a partial last line
+        let mut file = OpenOptions::new().append(true).open(dir.join(TRANSFER_LOG_NAME)).unwrap();
+        file.write_all(br#"{"source":"/card/IMG_0003.JPG","destin"#).unwrap();
+
+        let transfers = read_transfer_log(&dir).unwrap();
+        assert_eq!(transfers.len(), 2);
+        let first = &transfers[Path::new("/card/IMG_0001.JPG")];
+        assert_eq!(first.destination, dir.join("a.jpg"));
+        assert!(first.matches(10, Some(modified)));
+        assert!(!first.matches(10, Some(modified + Duration::from_secs(1))));
+        assert!(!first.matches(11, Some(modified)));
+        assert!(transfers[Path::new("/card/IMG_0002.JPG")].matches(20, None));
+    }
+
+    #[test]
+    fn test_remove_partial_copies() {
+        let dir = TempDir::new("partial");
+        let failed = dir.join("Failed Cases");
+        fs::create_dir_all(dir.join("2020")).unwrap();
+        fs::create_dir_all(&failed).unwrap();
+        fs::write(dir.join("a.JPG"), b"a").unwrap();
+        fs::write(dir.join("b.JPG.part"), b"b").unwrap();
+        fs::write(dir.join("2020").join("c.MOV.part"), b"c").unwrap();
+        // Archived from a source that happened to be named .part
+        fs::write(dir.join("2021-01-01 10.00.00 1.part"), b"d").unwrap();
+        fs::write(failed.join("e.JPG.part"), b"e").unwrap();
+
+        let log = TransferLog::open(&dir).unwrap();
+        for partial in [dir.join("b.JPG.part"), dir.join("2020").join("c.MOV.part"), failed.join("e.JPG.part")] {
+            log.record_partial(&partial).unwrap();
+        }
+        // Written and renamed into place, so no longer there
+        log.record_partial(&dir.join("a.JPG.part")).unwrap();
+        drop(log);
+
+        assert_eq!(remove_partial_copies(&dir, &failed).unwrap(), 2);
+        assert!(dir.join("a.JPG").exists());
+        assert!(!dir.join("b.JPG.part").exists());
+        assert!(!dir.join("2020").join("c.MOV.part").exists());
+        assert!(dir.join("2021-01-01 10.00.00 1.part").exists());
+        assert!(failed.join("e.JPG.part").exists());
+    }
+}
@@ -0,0 +1,334 @@
+use anyhow::{bail, Result};
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::filename::NameFormat;
+use crate::hash::Hasher;
+use crate::metadata::ExtractOptions;
+use crate::organize::DateLayout;
+use crate::pairs::PreviewPolicy;
+use crate::style::ColorChoice;
+
+/// Options that change how files are processed, parsed from the command line
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    /// Rotate archived images upright according to their EXIF Orientation
+    pub auto_orient: bool,
+    /// Write each archived image's or video's source path into its XMP
+    pub tag_provenance: bool,
+    /// When to color the per-file and summary output
+    pub color: ColorChoice,
+    /// Copy in chunks of this many bytes instead of the platform's fast copy
+    pub copy_buffer_size: Option<usize>,
+    /// Only report duplicates to this file, without transferring anything
+    pub dedupe_report: Option<PathBuf>,
+    /// Only print how many files each pair of inputs share, without
+    /// transferring anything
+    pub analyze_overlap: bool,
+    /// Only print what each source would become in the library (new, a
+    /// duplicate, or numbered past a different file), without transferring
+    pub dry_run: bool,
+    /// Fully decode images and archive those that fail into a Corrupt folder
+    pub validate_decode: bool,
+    /// Remove source folders left empty after the run
+    pub prune_empty_sources: bool,
+    /// Highest filename counter tried for one date pair (None: 10000)
+    pub max_collisions: Option<u32>,
+    /// Leave out source files larger than this many bytes (None: 64 GiB)
+    pub max_read_size: Option<u64>,
+    /// Whether the end-of-run summary is printed as text, JSON, or both
+    pub summary_format: SummaryFormat,
+    /// List failed files by extension at the end of the summary
+    pub list_unsupported: bool,
+    /// Keep copied sources until the end of the run, then delete them all
+    /// after one confirmation
+    pub confirm_deletes: bool,
+    /// Give copied files their source's access and modification times
+    pub preserve_mtime: bool,
+    /// Send deleted sources and duplicates to the system trash
+    pub trash: bool,
+    /// After the run, look for byte-identical files across the whole library
+    pub verify_output: bool,
+    /// Name archived files with the extension of the file type exiftool
+    /// detects when the source's extension disagrees
+    pub fix_extension: bool,
+    /// Skip source files the transfer log records as archived by an earlier run
+    pub resume: bool,
+    /// Archive files dated before the date policy's `suspicious_before` into a
+    /// Review folder
+    pub review_suspicious: bool,
+    /// Scan input directories recursively instead of only their top level
+    pub recursive: bool,
+    /// Recreate each file's subdirectory (relative to its input root) under the output
+    pub preserve_structure: bool,
+    /// Move zero-byte source files into an "Empty Files" folder
+    pub quarantine_empty: bool,
+    /// Command to run on each archived file, with `{}` for its path
+    pub exec: Option<String>,
+    /// Name of the Failed Cases folder under the output (default "Failed Cases")
+    pub failed_name: Option<String>,
+    /// On permission denied, add owner read/write to the source and retry once
+    pub fix_perms: bool,
+    /// Ask about each duplicate source file instead of all at once
+    pub interactive_dupes: bool,
+    /// Never ask on stdin: duplicates and copied sources are left in place
+    /// and library duplicates kept (for scripts and embedders)
+    pub no_prompt: bool,
+    /// Process at most this many files (the first N by path)
+    pub limit: Option<usize>,
+    /// Print progress after every this many files (None: 100, 0: never)
+    pub stats_interval: Option<usize>,
+    /// Also print progress whenever this long passes without it
+    pub stats_every: Option<std::time::Duration>,
+    /// File descriptor to write a JSON line to as each file is handled
+    pub progress_fd: Option<i32>,
+    /// Give each source volume its own pool of this many reader threads
+    /// instead of one pool shared by all inputs
+    pub workers_per_volume: Option<usize>,
+    /// Failed files allowed before the exit code reports failures
+    pub tolerate_failures: usize,
+    /// Flag images within this perceptual-hash distance of a library image
+    pub phash_dedupe: Option<u32>,
+    /// Limit copy throughput to this many bytes per second
+    pub max_rate: Option<u64>,
+    /// How many directory levels to scan below each input (default 1)
+    pub max_depth: Option<usize>,
+    /// Write a `shasum`-style manifest of every file archived to this path
+    pub manifest: Option<PathBuf>,
+    /// How to settle a same-name file with different content
+    pub on_conflict: ConflictPolicy,
+    /// Which library files a source is checked against for identical copies
+    pub dedupe_scope: DedupeScope,
+    /// What else, besides identical bytes, makes two files the same
+    pub dedupe_by: DedupeBy,
+    /// When a source duplicates a library file but leads with an earlier
+    /// date, rename the library file to the source's name
+    pub keep_earliest_date: bool,
+    /// Hash function for duplicate checks, reports and the manifest
+    pub checksum_algo: Hasher,
+    /// What to do with the rest of the run once a file fails
+    pub on_failure: FailurePolicy,
+    /// Move failed originals into Failed Cases instead of linking to them
+    /// where they are (files on read-only volumes are always linked)
+    pub move_failed: bool,
+    /// Group same-shot files (IMG_0001.DNG + IMG_0001.JPG) and handle the
+    /// lower-priority previews this way
+    pub pair_previews: Option<PreviewPolicy>,
+    /// File types in --pair-previews priority order (empty: DNG, HEIC, JPG)
+    pub pair_priority: Vec<String>,
+    /// Date-based folder layout under the output
+    pub organize_by: DateLayout,
+    /// Write each archived file's dates and source to `<file>.json` beside it
+    pub emit_sidecar: bool,
+    /// Only archive files with (or without) GPS coordinates
+    pub gps_filter: Option<GpsFilter>,
+    /// Sort files with GPS data into lat/long grid-cell folders instead
+    pub organize_by_location: bool,
+    /// Grid cell size in degrees for --organize-by-location (default 1)
+    pub location_grid: Option<f64>,
+    /// Route files into Photos/Videos/Other subfolders by media type
+    pub split_media: bool,
+    /// Overrides for the --split-media folder names, keyed by media
+    /// category (`image`, `video`, `other`) or by exiftool FileType (`DNG`)
+    pub media_folders: HashMap<String, String>,
+    /// Scan dotfiles and dot-directories instead of skipping them
+    pub include_hidden: bool,
+    /// File name patterns to skip besides the default system files
+    pub ignore_names: Vec<String>,
+    /// Skip symlinked input files instead of archiving their targets
+    pub skip_symlinks: bool,
+    /// How files are put into the library
+    pub link: LinkMode,
+    /// With --link hardlink, leave the source's own name in place as well
+    pub keep_linked_sources: bool,
+    /// Options passed to the metadata extraction in each worker
+    pub extract: ExtractOptions,
+    /// How generated filenames are built
+    pub name_format: NameFormat,
+}
+
+impl Settings {
+    /// Whether this run must leave the output and the sources untouched
+    pub fn read_only(&self) -> bool {
+        self.dedupe_report.is_some() || self.analyze_overlap || self.dry_run
+    }
+}
+
+/// What to do when a different file already has the first counter's name
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Keep both, giving the new file the next free counter
+    #[default]
+    Increment,
+    /// Keep the larger file under the name
+    KeepLarger,
+    /// Keep the file with the later modification time under the name
+    KeepNewer,
+}
+
+impl ConflictPolicy {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "increment" => Ok(ConflictPolicy::Increment),
+            "keep-larger" => Ok(ConflictPolicy::KeepLarger),
+            "keep-newer" => Ok(ConflictPolicy::KeepNewer),
+            other => bail!("--on-conflict expects increment, keep-larger, or keep-newer, got '{}'", other),
+        }
+    }
+}
+
+/// Which library files a source is checked against for byte-identical copies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DedupeScope {
+    /// Only the files sharing its date-derived name
+    #[default]
+    DateBucket,
+    /// Every file in the library, including those archived this run
+    Global,
+}
+
+impl DedupeScope {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "date-bucket" => Ok(DedupeScope::DateBucket),
+            "global" => Ok(DedupeScope::Global),
+            other => bail!("--dedupe-scope expects date-bucket or global, got '{}'", other),
+        }
+    }
+}
+
+/// What --dedupe-by compares beyond the bytes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DedupeBy {
+    /// Only byte-identical files are duplicates
+    #[default]
+    Bytes,
+    /// Also report files sharing DateTimeOriginal, camera serial number and
+    /// image size as the same shot, keeping both for review
+    Identity,
+}
+
+impl DedupeBy {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "bytes" => Ok(DedupeBy::Bytes),
+            "identity" => Ok(DedupeBy::Identity),
+            other => bail!("--dedupe-by expects bytes or identity, got '{}'", other),
+        }
+    }
+}
+
+/// How a file is put into the library
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LinkMode {
+    /// Rename on the same volume, otherwise copy and delete the source
+    #[default]
+    Auto,
+    /// Hard link, falling back to a copy where the link would cross devices
+    Hardlink,
+    /// Always copy and delete the source, even on the same volume
+    Copy,
+}
+
+impl LinkMode {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(LinkMode::Auto),
+            "hardlink" => Ok(LinkMode::Hardlink),
+            "copy" => Ok(LinkMode::Copy),
+            other => bail!("--link expects auto, hardlink, or copy, got '{}'", other),
+        }
+    }
+}
+
+/// What a failed file means for the rest of the run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FailurePolicy {
+    /// Link it into Failed Cases and carry on
+    #[default]
+    Symlink,
+    /// Link it into Failed Cases, then stop the run
+    Stop,
+}
+
+impl FailurePolicy {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "symlink" => Ok(FailurePolicy::Symlink),
+            "stop" => Ok(FailurePolicy::Stop),
+            other => bail!("--on-failure expects symlink or stop, got '{}'", other),
+        }
+    }
+}
+
+/// Which files --only-with-gps / --only-without-gps let through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GpsFilter {
+    WithGps,
+    WithoutGps,
+}
+
+impl GpsFilter {
+    /// Whether a file with this GPS position (if any) is archived
+    pub fn allows(self, gps: Option<(f64, f64)>) -> bool {
+        match self {
+            GpsFilter::WithGps => gps.is_some(),
+            GpsFilter::WithoutGps => gps.is_none(),
+        }
+    }
+}
+
+/// How the end-of-run summary is written to stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SummaryFormat {
+    #[default]
+    Text,
+    /// One JSON object instead of the text
+    Json,
+    /// The text, then the JSON object
+    Both,
+}
+
+impl SummaryFormat {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "text" => Ok(SummaryFormat::Text),
+            "json" => Ok(SummaryFormat::Json),
+            "both" => Ok(SummaryFormat::Both),
+            other => bail!("--summary-format expects text, json, or both, got '{}'", other),
+        }
+    }
+}
+
+/// Check that a user-supplied folder name is a single, normal path component
+pub fn validate_folder_name(name: &str) -> Result<()> {
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(()),
+        _ => bail!("Invalid folder name '{}': must be a single path component", name),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_folder_name() {
+        assert!(validate_folder_name("Photos").is_ok());
+        assert!(validate_folder_name("Raw Files").is_ok());
+        assert!(validate_folder_name("").is_err());
+        assert!(validate_folder_name("..").is_err());
+        assert!(validate_folder_name("a/b").is_err());
+        assert!(validate_folder_name("/abs").is_err());
+    }
+
+    #[test]
+    fn test_gps_filter() {
+        let position = Some((37.7749, -122.4194));
+        assert!(GpsFilter::WithGps.allows(position));
+        assert!(!GpsFilter::WithGps.allows(None));
+        assert!(GpsFilter::WithoutGps.allows(None));
+        assert!(!GpsFilter::WithoutGps.allows(position));
+    }
+}
@@ -0,0 +1,69 @@
+use serde_json::json;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+use crate::metadata::MediaInfo;
+
+/// Where the --emit-sidecar metadata of the archived file `dest` is written:
+/// beside it, with `.json` added to its name
+pub fn sidecar_path(dest: &Path) -> PathBuf {
+    let mut name = OsString::from(dest.as_os_str());
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+/// The sidecar of a file archived from `source`: its dates and the tags they
+/// were read from, and the other details exiftool reported, so a catalog can
+/// be rebuilt without reading the library's metadata again
+pub fn sidecar_json(source: &Path, info: &MediaInfo) -> serde_json::Value {
+    json!({
+        "source": source.display().to_string(),
+        "creation_date": info.dates.creation_date.to_rfc3339(),
+        "creation_tag": info.creation_tag,
+        "modify_date": info.dates.modify_date.to_rfc3339(),
+        "modify_tag": info.modify_tag,
+        "file_type": info.file_type,
+        "mime_type": info.mime_type,
+        "gps": info.gps.map(|(lat, lon)| json!([lat, lon])),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::MediaDates;
+    use chrono::{TimeZone, Utc};
+
+    #[test]
+    fn test_sidecar() {
+        assert_eq!(sidecar_path(Path::new("/out/a 1.JPG")), PathBuf::from("/out/a 1.JPG.json"));
+
+        let info = MediaInfo {
+            dates: MediaDates {
+                creation_date: Utc.with_ymd_and_hms(2021, 6, 15, 10, 20, 30).unwrap(),
+                modify_date: Utc.with_ymd_and_hms(2021, 6, 15, 10, 25, 0).unwrap(),
+            },
+            file_type: Some("JPEG".to_string()),
+            mime_type: Some("image/jpeg".to_string()),
+            gps: None,
+            orientation: None,
+            file_type_extension: Some("jpg".to_string()),
+            identity: None,
+            creation_tag: Some("EXIF:DateTimeOriginal".to_string()),
+            modify_tag: None,
+        };
+        assert_eq!(
+            sidecar_json(Path::new("/card/IMG_0001.JPG"), &info),
+            json!({
+                "source": "/card/IMG_0001.JPG",
+                "creation_date": "2021-06-15T10:20:30+00:00",
+                "creation_tag": "EXIF:DateTimeOriginal",
+                "modify_date": "2021-06-15T10:25:00+00:00",
+                "modify_tag": null,
+                "file_type": "JPEG",
+                "mime_type": "image/jpeg",
+                "gps": null,
+            })
+        );
+    }
+}
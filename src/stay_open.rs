@@ -0,0 +1,114 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::Value;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+
+/// Printed after each command's output on stdout, and echoed to stderr
+/// (`-echo4`) after its messages, so both streams can be read to the end of
+/// the command without waiting on a guess
+const READY: &str = "{ready}";
+
+/// A running `exiftool -stay_open` process. Unlike the exiftool crate's, the
+/// child handle stays here while its pipes are in use elsewhere, so a process
+/// stuck on a file can be killed; dropping it kills and reaps the process
+pub struct Process {
+    child: Child,
+}
+
+/// The pipes to a [`Process`], for running commands on it
+pub struct Session {
+    stdin: BufWriter<ChildStdin>,
+    stdout: BufReader<ChildStdout>,
+    stderr: Receiver<String>,
+}
+
+/// Start `executable` in stay-open mode
+pub fn start(executable: &Path) -> Result<(Process, Session)> {
+    let child = Command::new(executable)
+        .args(["-stay_open", "True", "-@", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to start {}", executable.display()))?;
+    let mut process = Process { child };
+    let child = &mut process.child;
+
+    let pipes = (child.stdin.take(), child.stdout.take(), child.stderr.take());
+    let (Some(stdin), Some(stdout), Some(stderr)) = pipes else {
+        bail!("Failed to open pipes to {}", executable.display());
+    };
+
+    // Read stderr as it comes, so a command with many warnings can't fill the
+    // pipe and stall exiftool while stdout is being read
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stderr).lines().map_while(Result::ok) {
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let session = Session {
+        stdin: BufWriter::new(stdin),
+        stdout: BufReader::new(stdout),
+        stderr: lines,
+    };
+    Ok((process, session))
+}
+
+impl Drop for Process {
+    fn drop(&mut self) {
+        // An idle exiftool exits once its stdin closes, but one stuck reading
+        // a file wouldn't, so it's killed either way
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl Session {
+    /// Run exiftool with `-json` and `args`, failing if it reports an error
+    pub fn json(&mut self, args: &[&str]) -> Result<Value> {
+        writeln!(self.stdin, "-json")?;
+        for arg in args {
+            writeln!(self.stdin, "{}", arg)?;
+        }
+        writeln!(self.stdin, "-echo4\n{}\n-execute", READY)?;
+        self.stdin.flush().context("Failed to send the command to exiftool")?;
+
+        let mut output = String::new();
+        loop {
+            let mut line = String::new();
+            if self.stdout.read_line(&mut line)? == 0 {
+                bail!("exiftool exited unexpectedly");
+            }
+            if line.trim_end() == READY {
+                break;
+            }
+            output.push_str(&line);
+        }
+
+        let mut errors = Vec::new();
+        loop {
+            let line = self.stderr.recv().map_err(|_| anyhow!("exiftool exited unexpectedly"))?;
+            if line.trim_end() == READY {
+                break;
+            }
+            if line.contains("Error:") {
+                errors.push(line);
+            }
+        }
+        if !errors.is_empty() {
+            bail!("{}", errors.join("; "));
+        }
+
+        if output.trim().is_empty() {
+            bail!("exiftool returned no output");
+        }
+        serde_json::from_str(&output).context("Failed to parse exiftool JSON output")
+    }
+}
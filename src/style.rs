@@ -0,0 +1,113 @@
+use anyhow::{bail, Result};
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{self, IsTerminal, Write};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// When to color the per-file and summary output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color when the stream is a terminal and NO_COLOR is unset
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            other => bail!("--color expects auto, always, or never, got '{}'", other),
+        }
+    }
+}
+
+/// The choice set by `set_color`, stored as its discriminant
+static COLOR: AtomicU8 = AtomicU8::new(ColorChoice::Auto as u8);
+
+/// Set the color choice for the rest of the run
+pub fn set_color(choice: ColorChoice) {
+    COLOR.store(choice as u8, Ordering::Relaxed);
+}
+
+/// What a line reports, which picks its color
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// Archived, deleted, or otherwise done (green)
+    Success,
+    /// Left alone, e.g. already in the library (yellow)
+    Skipped,
+    /// Failed (red)
+    Failure,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::Success => "32",
+            Style::Skipped => "33",
+            Style::Failure => "31",
+        }
+    }
+}
+
+fn enabled(is_terminal: bool) -> bool {
+    match COLOR.load(Ordering::Relaxed) {
+        c if c == ColorChoice::Always as u8 => true,
+        c if c == ColorChoice::Never as u8 => false,
+        _ => is_terminal && std::env::var_os("NO_COLOR").is_none(),
+    }
+}
+
+/// `line` wrapped in the style's ANSI color when `color` is set
+fn paint(style: Style, line: impl Display, color: bool) -> String {
+    if color {
+        format!("\x1b[{}m{}\x1b[0m", style.code(), line)
+    } else {
+        line.to_string()
+    }
+}
+
+/// Print a line to stdout in the style's color
+pub fn print(style: Style, line: impl Display) {
+    println!("{}", paint(style, line, enabled(std::io::stdout().is_terminal())));
+}
+
+/// Print a line to stderr in the style's color
+pub fn eprint(style: Style, line: impl Display) {
+    eprintln!("{}", paint(style, line, enabled(std::io::stderr().is_terminal())));
+}
+
+/// Point stdout at stderr for the rest of the run, returning a handle to the
+/// original stdout. With --summary-format json the summary is written there
+/// and everything else (per-file lines, prompts) goes to stderr
+pub fn divert_stdout() -> io::Result<File> {
+    use std::os::fd::FromRawFd;
+    io::stdout().flush()?;
+    // SAFETY: dup and dup2 only change the descriptor table
+    let original = unsafe { libc::dup(libc::STDOUT_FILENO) };
+    if original < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // SAFETY: `original` is a new descriptor that nothing else owns
+    let original = unsafe { File::from_raw_fd(original) };
+    if unsafe { libc::dup2(libc::STDERR_FILENO, libc::STDOUT_FILENO) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(original)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paint() {
+        assert_eq!(paint(Style::Success, "✓ Moved: a.jpg", true), "\x1b[32m✓ Moved: a.jpg\x1b[0m");
+        assert_eq!(paint(Style::Failure, "Failed: 2", false), "Failed: 2");
+        assert!(ColorChoice::parse("sometimes").is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use std::fs;
+use std::ops::Deref;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// A scratch directory for a test, removed again when dropped, so a test
+/// that fails partway doesn't leave its files behind. Public only for the
+/// integration tests; not part of the library's API
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    /// Create an empty `collect_media_<name>_<pid>` under the system temp
+    /// directory, clearing out anything an earlier run left there
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("collect_media_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir { path }
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+/// Write a stand-in exiftool that speaks the stay-open protocol and
+/// reports every file with SourceFile and the JSON members in `tags`
+pub fn write_stub_exiftool(dir: &Path, tags: &str) -> PathBuf {
+    let path = dir.join("exiftool");
+    fs::write(
+        &path,
+        r#"#!/bin/sh
+files=""
+while IFS= read -r line; do
+    case "$line" in
+        -echo4) read -r marker ;;
+        -execute)
+            printf '['
+            sep=""
+            for file in $files; do
+                printf '%s{"SourceFile":"%s"TAGS}' "$sep" "$file"
+                sep=","
+            done
+            printf ']\n{ready}\n'
+            printf '%s\n' "$marker" >&2
+            files=""
+            ;;
+        -*) ;;
+        *) files="$files $line" ;;
+    esac
+done
+"#
+        .replace("TAGS", tags),
+    )
+    .unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+    path
+}
@@ -0,0 +1,53 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Token bucket limiting throughput to a number of bytes per second, with at
+/// most one second's worth of burst after an idle period
+#[derive(Debug)]
+pub struct RateLimiter {
+    bytes_per_sec: f64,
+    /// Bytes that may be sent without waiting; negative while in debt
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    pub fn new(bytes_per_sec: u64) -> Self {
+        RateLimiter {
+            bytes_per_sec: bytes_per_sec as f64,
+            tokens: bytes_per_sec as f64,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Account for `bytes` transferred, sleeping long enough to keep the
+    /// average rate at or below the limit
+    pub fn consume(&mut self, bytes: usize) {
+        let now = Instant::now();
+        let refill = now.duration_since(self.last_refill).as_secs_f64() * self.bytes_per_sec;
+        self.tokens = (self.tokens + refill).min(self.bytes_per_sec);
+        self.last_refill = now;
+
+        self.tokens -= bytes as f64;
+        if self.tokens < 0.0 {
+            thread::sleep(Duration::from_secs_f64(-self.tokens / self.bytes_per_sec));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_limiter_waits_once_burst_is_spent() {
+        let mut limiter = RateLimiter::new(1000);
+
+        let start = Instant::now();
+        limiter.consume(1000);
+        assert!(start.elapsed() < Duration::from_millis(100));
+
+        limiter.consume(200);
+        assert!(start.elapsed() >= Duration::from_millis(150));
+    }
+}
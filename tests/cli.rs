@@ -0,0 +1,41 @@
+//! Runs the `collect_media` binary, with a stand-in exiftool first on PATH,
+//! and checks what it writes to its output streams
+
+use collect_media::test_util::{write_stub_exiftool, TempDir};
+use std::fs;
+use std::process::{Command, Stdio};
+
+#[test]
+fn test_json_summary_is_all_of_stdout() {
+    let root = TempDir::new("cli_json_summary");
+    let bin = root.join("bin");
+    let input = root.join("input");
+    fs::create_dir_all(&bin).unwrap();
+    fs::create_dir_all(&input).unwrap();
+    write_stub_exiftool(&bin, r#","File:FileModifyDate":"2024:01:01 12:00:00+00:00""#);
+
+    // The second file is a duplicate of the first, so the run ends by asking
+    // whether to delete it
+    fs::write(input.join("a.jpg"), "photo").unwrap();
+    fs::write(input.join("b.jpg"), "photo").unwrap();
+
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default());
+    let output = Command::new(env!("CARGO_BIN_EXE_collect_media"))
+        .arg(&input)
+        .arg("-o")
+        .arg(root.join("output"))
+        .args(["--summary-format", "json"])
+        .env("PATH", path)
+        .stdin(Stdio::null())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+
+    let summary: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(summary["moved"], 1);
+    assert_eq!(summary["skipped"], 1);
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("Starting media collection..."));
+    assert!(stderr.contains("Delete these 1 duplicate source files?"));
+}
@@ -0,0 +1,88 @@
+//! Runs the whole `Processor` over the sample media in `tests/fixtures` and
+//! checks what lands in the library. Metadata is read with exiftool, so these
+//! tests are ignored by default; run them with `cargo test -- --ignored` where
+//! exiftool is installed
+
+use collect_media::processor::{Processor, RunOutcome};
+use collect_media::settings::Settings;
+use collect_media::test_util::TempDir;
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+const FIXTURES: [&str; 3] = ["sample.jpg", "sample.heic", "sample.mp4"];
+
+/// A fresh temp folder holding copies of the fixtures in `input` (a run
+/// moves them) and an empty `output`
+fn setup(name: &str) -> (TempDir, PathBuf, PathBuf) {
+    let root = TempDir::new(&format!("e2e_{}", name));
+    let input = root.join("input");
+    let output = root.join("output");
+    copy_fixtures(&input);
+    fs::create_dir_all(&output).unwrap();
+    (root, input, output)
+}
+
+fn copy_fixtures(dir: &Path) {
+    fs::create_dir_all(dir).unwrap();
+    for fixture in FIXTURES {
+        let from = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(fixture);
+        fs::copy(from, dir.join(fixture)).unwrap();
+    }
+}
+
+fn run(input: &Path, output: &Path) -> Processor {
+    let settings = Settings { no_prompt: true, ..Settings::default() };
+    let mut processor = Processor::new(output.to_path_buf(), settings).unwrap();
+    processor.process_directories(&[input.to_path_buf()]).unwrap();
+    processor
+}
+
+/// Names of the files archived into `output`, leaving out Failed Cases and
+/// the run's own bookkeeping files
+fn archived_names(output: &Path) -> Vec<String> {
+    let mut names: Vec<String> = WalkDir::new(output)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != "Failed Cases")
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.'))
+        .collect();
+    names.sort();
+    names
+}
+
+#[test]
+#[ignore = "needs exiftool"]
+fn test_archives_fixtures_by_date() {
+    let (_root, input, output) = setup("dates");
+
+    let processor = run(&input, &output);
+    assert_eq!(processor.outcome(), RunOutcome::Success);
+    assert_eq!(
+        archived_names(&output),
+        vec![
+            "2021-06-15_10.20.30.000 2021-06-15_10.25.00.000 1.JPG",
+            "2022-03-04_05.06.07.000 2022-03-04_05.07.08.000 1.MP4",
+            "2023-07-08_09.10.11.000 2023-07-08_09.10.12.000 1.HEIC",
+        ]
+    );
+    let stats = processor.stats();
+    assert_eq!((stats.total_files, stats.moved, stats.failed), (3, 3, 0));
+}
+
+#[test]
+#[ignore = "needs exiftool"]
+fn test_second_run_skips_duplicates() {
+    let (root, input, output) = setup("rerun");
+    run(&input, &output);
+
+    // The same media again is recognised as already archived
+    let again = root.join("again");
+    copy_fixtures(&again);
+    let processor = run(&again, &output);
+    assert_eq!(archived_names(&output).len(), 3);
+    let stats = processor.stats();
+    assert_eq!((stats.moved, stats.skipped), (0, 3));
+}